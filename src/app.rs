@@ -1,15 +1,60 @@
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
+    style::Style,
     text::{Line, Span},
-    widgets::{Block, Borders, Paragraph},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
     Frame,
 };
 
-use crossterm::event::{KeyCode, KeyEvent};
+use std::time::Duration;
 
-use crate::game::{components::Direction as GameDirection, GameState};
-use crate::render::widgets::hud;
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+
+use crate::command::{Command, CommandLine, Completions, Setting};
+use crate::config::Config;
+
+use crate::game::{
+    components::{Direction as Heading, Position},
+    controller::Action,
+    entity::EntityType,
+    mode::{BattleRules, GameMode, GameSetup, MatchState, RuleField},
+    stage::{
+        loader::{load_stage, stage_names},
+        StageConfig,
+    },
+    systems::visibility::{TerrainMemory, Visibility},
+    GameState,
+};
+use crate::input::InputQueue;
+use crate::keys::{KeyMap, PlayerBindings};
+use crate::net::{
+    apply_frame,
+    client::{round_setup, NetClient},
+    protocol::{ClientMessage, ServerMessage},
+    TICK_SECONDS,
+};
+use crate::render::{
+    accessibility::Accessibility,
+    camera::{Camera, Zoom},
+    glyphs::{GlyphSet, Glyphs},
+    theme::Theme,
+    widgets::{
+        board::{Board, Fog},
+        hud::{self, HudContext},
+        messages::{self, Messages},
+        minimap::Minimap,
+        scoreboard::{self, Scoreboard},
+        settings,
+    },
+};
+
+/// Tiles of a big stage that must fit on the board; the camera scrolls
+/// over the rest.
+const MIN_VIEW: (usize, usize) = (11, 9);
+/// Narrowest screen the HUD and overlays still make sense on.
+const MIN_WIDTH: u16 = 40;
+/// Local ticks per second unless `:set tickrate` says otherwise.
+const DEFAULT_TICK_RATE: u32 = 20;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AppState {
@@ -17,209 +62,1331 @@ pub enum AppState {
     Paused,
     GameOver,
     Clear,
+    RoundOver,
+    MatchOver,
+    Lobby,
+    /// Choosing the rules before a local battle match.
+    Settings,
 }
 
 pub struct App {
     should_quit: bool,
+    setup: GameSetup,
     game_state: GameState,
     app_state: AppState,
+    keys: KeyMap,
+    /// Controls of the players at this keyboard.
+    bindings: Vec<PlayerBindings>,
+    /// Whether the terminal reports key releases, so held keys can be
+    /// tracked; otherwise every press is one step.
+    key_release: bool,
+    /// Movement keys each local player is holding, the latest last.
+    held: Vec<Vec<Heading>>,
+    /// Vim counts and motions, when a single player has the keyboard.
+    input: InputQueue,
+    match_state: Option<MatchState>,
+    round_winner: Option<usize>,
+    show_danger: bool,
+    show_minimap: bool,
+    show_full_map: bool,
+    glyph_set: GlyphSet,
+    glyphs: Glyphs,
+    /// `glyphs` cut to one column, for the zoomed-out board.
+    narrow_glyphs: Glyphs,
+    zoom: Zoom,
+    camera: Camera,
+    terrain_memory: TerrainMemory,
+    theme: Theme,
+    accessibility: Accessibility,
+    messages: Messages,
+    /// Terminal size as of the last resize or frame.
+    screen: (u16, u16),
+    /// The game was paused because the terminal got too small, and picks
+    /// up again by itself once it is big enough.
+    paused_for_size: bool,
+    /// Selected line on the match settings screen.
+    settings_cursor: usize,
+    /// Player whose stats panel a spectator has picked out.
+    highlighted: usize,
+    /// The `:` prompt, open or not, with its history.
+    command_line: CommandLine,
+    command_open: bool,
+    /// What the last command had to say, and whether it was an error;
+    /// shown where the prompt was until the next key.
+    command_message: Option<(String, bool)>,
+    /// Stage chosen with `:stage`, played instead of the setup's own.
+    stage: Option<StageConfig>,
+    /// Seed of the game being played, and the one `:seed` asked for next.
+    seed: u64,
+    next_seed: Option<u64>,
+    tick_rate: u32,
+    /// Whether `:save` may write the config file, which an ssh guest
+    /// doesn't own.
+    can_save: bool,
+    /// Theme and glyphs picked with commands, which `:save` keeps.
+    chosen_theme: Option<String>,
+    chosen_glyphs: Option<GlyphSet>,
+    net: Option<NetClient>,
 }
 
 impl App {
-    pub fn new() -> Self {
+    pub fn new(setup: GameSetup) -> Self {
+        let keys = KeyMap::default();
+        let bindings = (0..setup.human_slots().len()).map(|index| keys.player(index)).collect();
+        let (match_state, app_state) = match &setup {
+            GameSetup::Battle { slots, rules } => (
+                Some(MatchState::new(rules.best_of, rules.sides(slots.len()))),
+                AppState::Settings,
+            ),
+            GameSetup::Story => (None, AppState::Playing),
+        };
+
+        let seed = rand::random();
         Self {
             should_quit: false,
-            game_state: GameState::new(),
-            app_state: AppState::Playing,
+            game_state: setup.build(seed),
+            setup,
+            app_state,
+            keys,
+            bindings,
+            key_release: false,
+            held: Vec::new(),
+            input: InputQueue::default(),
+            match_state,
+            round_winner: None,
+            show_danger: false,
+            show_minimap: false,
+            show_full_map: false,
+            glyph_set: GlyphSet::Emoji,
+            glyphs: GlyphSet::Emoji.glyphs(),
+            narrow_glyphs: GlyphSet::Emoji.glyphs().narrow(),
+            zoom: Zoom::Near,
+            camera: Camera::default(),
+            terrain_memory: TerrainMemory::default(),
+            theme: Theme::classic(),
+            accessibility: Accessibility::default(),
+            messages: Messages::default(),
+            screen: (0, 0),
+            paused_for_size: false,
+            settings_cursor: 0,
+            highlighted: 0,
+            command_line: CommandLine::default(),
+            command_open: false,
+            command_message: None,
+            stage: None,
+            seed,
+            next_seed: None,
+            tick_rate: DEFAULT_TICK_RATE,
+            can_save: false,
+            chosen_theme: None,
+            chosen_glyphs: None,
+            net: None,
         }
     }
 
-    pub fn render(&self, frame: &mut Frame) {
+    /// An app whose game is driven by a lockstep host instead of local ticks.
+    pub fn networked(client: NetClient) -> Self {
+        let mut app = Self::new(round_setup(2, &BattleRules::default()));
+        app.bindings.truncate(1);
+        app.app_state = AppState::Lobby;
+        app.net = Some(client);
+        app
+    }
+
+    /// A read-only app that shows a hosted match a few seconds late.
+    pub fn spectating(client: NetClient) -> Self {
+        let mut app = Self::networked(client);
+        app.bindings.clear();
+        app
+    }
+
+    /// Glyphs from the theme, if it brings its own, win over the set,
+    /// unless the player picked a set by name this session.
+    pub fn set_glyphs(&mut self, glyph_set: GlyphSet) {
+        self.glyph_set = glyph_set;
+        self.glyphs = match (self.chosen_glyphs, &self.theme.glyphs) {
+            (Some(chosen), _) => chosen.glyphs(),
+            (None, Some(glyphs)) => glyphs.clone(),
+            (None, None) => glyph_set.glyphs(),
+        };
+        self.narrow_glyphs = self.glyphs.narrow();
+    }
+
+    /// Rebinds the keys, keeping the number of players at this keyboard.
+    pub fn set_keys(&mut self, keys: KeyMap) {
+        self.bindings = (0..self.bindings.len()).map(|index| keys.player(index)).collect();
+        self.keys = keys;
+    }
+
+    pub fn set_key_release(&mut self, key_release: bool) {
+        self.key_release = key_release;
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.set_glyphs(self.glyph_set);
+    }
+
+    pub fn set_accessibility(&mut self, accessibility: Accessibility) {
+        self.accessibility = accessibility;
+    }
+
+    pub fn set_can_save(&mut self, can_save: bool) {
+        self.can_save = can_save;
+    }
+
+    /// Time between local ticks; whoever drives `tick` should follow it
+    /// when `:set tickrate` changes it.
+    pub fn tick_period(&self) -> Duration {
+        Duration::from_secs_f32(self.tick_seconds())
+    }
+
+    fn tick_seconds(&self) -> f32 {
+        1.0 / self.tick_rate as f32
+    }
+
+    fn is_spectating(&self) -> bool {
+        self.net.as_ref().is_some_and(|net| net.spectating.is_some())
+    }
+
+    /// The stats panel to emphasise: our own in a networked game, the
+    /// chosen one when watching.
+    fn highlighted_slot(&self) -> Option<usize> {
+        let net = self.net.as_ref()?;
+        if net.spectating.is_some() {
+            Some(self.highlighted)
+        } else {
+            net.slot
+        }
+    }
+
+    pub fn render(&mut self, frame: &mut Frame) {
+        let area = frame.area();
+        if self.screen != (area.width, area.height) {
+            self.resize(area.width, area.height);
+        }
+        if self.is_too_small() {
+            self.render_too_small(frame);
+            return;
+        }
+
+        let minimap = self.show_minimap.then(|| Minimap::size(&self.game_state, 1));
+        let hud_height = hud::height(&self.game_state, frame.area().width);
+        let hud_height = minimap.map_or(hud_height, |(_, height)| (height + 2).max(hud_height));
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
                 Constraint::Length(3),
                 Constraint::Min(0),
-                Constraint::Length(5),
+                Constraint::Length(hud_height),
             ])
             .split(frame.area());
 
+        self.follow_camera(chunks[1]);
+        let visibility = Visibility::compute(&self.game_state, &self.viewers());
+        if let Some(visibility) = &visibility {
+            self.terrain_memory.update(&self.game_state.world, visibility);
+        }
+        let fog = visibility.as_ref().map(|visibility| Fog {
+            visibility,
+            memory: &self.terrain_memory,
+        });
+
         self.render_header(frame, chunks[0]);
-        self.render_game_board(frame, chunks[1]);
-        hud::render_hud(frame, chunks[2], &self.game_state);
+        self.render_game_board(frame, chunks[1], fog);
+        let board_inner = Block::default().borders(Borders::ALL).inner(chunks[1]);
+        if self.messages.open {
+            let close = KeyMap::hint(&self.keys.log, "閉じる");
+            messages::render_log(frame, board_inner, &self.messages, &close, &self.theme);
+        } else {
+            messages::render_toasts(frame, board_inner, &self.messages, &self.theme);
+        }
+        let mut hud_area = chunks[2];
+        if let Some((width, _)) = minimap {
+            let [rest, map_area] = Layout::horizontal([
+                Constraint::Min(0),
+                Constraint::Length(width + 2),
+            ])
+            .areas(hud_area);
+            hud_area = rest;
+            self.render_map(frame, map_area, 1, fog);
+        }
+        hud::render_hud(
+            frame,
+            hud_area,
+            &self.game_state,
+            &HudContext {
+                match_state: self.match_state.as_ref(),
+                controls: &self.controls(),
+                highlighted: self.highlighted_slot(),
+                glyphs: &self.glyphs,
+                theme: &self.theme,
+                blink: self.blink(),
+            },
+        );
+        if self.show_full_map {
+            self.render_full_map(frame, fog);
+        }
 
         match self.app_state {
-            AppState::Paused => self.render_overlay(frame, "⏸ PAUSED", "p: 再開 | q: 終了"),
-            AppState::GameOver => self.render_overlay(frame, "☠ GAME OVER", "r: リスタート | q: 終了"),
-            AppState::Clear => self.render_overlay(frame, "🎉 STAGE CLEAR!", "r: リスタート | q: 終了"),
+            AppState::Paused => {
+                let footer = format!("{} | {}", KeyMap::hint(&self.keys.pause, "再開"), self.quit_hint());
+                self.render_overlay(frame, "⏸ PAUSED", &footer);
+            }
+            AppState::GameOver => self.render_overlay(frame, "☠ GAME OVER", &self.restart_footer()),
+            AppState::Clear => self.render_overlay(frame, "🎉 STAGE CLEAR!", &self.restart_footer()),
+            AppState::RoundOver => {
+                let title = match self.round_winner {
+                    Some(side) => format!("{} WINS THE ROUND", self.side_name(side)),
+                    None => "DRAW".to_string(),
+                };
+                let heading = format!(
+                    "Round {}",
+                    self.match_state.as_ref().map(|m| m.round).unwrap_or(1)
+                );
+                let footer = if self.net.is_some() {
+                    format!("次のラウンドを待っています… | {}", self.quit_hint())
+                } else {
+                    format!("Enter: 次のラウンド | {}", self.quit_hint())
+                };
+                let scoreboard = Scoreboard {
+                    title: &title,
+                    heading: &heading,
+                    stats: &self.game_state.round_stats,
+                    wins: &self.wins_by_slot(),
+                    footer: &footer,
+                };
+                scoreboard::render_scoreboard(frame, &scoreboard, &self.glyphs, &self.theme);
+            }
+            AppState::MatchOver => {
+                let title = match self.match_state.as_ref().and_then(|m| m.champion()) {
+                    Some(side) => format!("🏆 {} WINS THE MATCH!", self.side_name(side)),
+                    None => "MATCH OVER".to_string(),
+                };
+                let footer = if self.net.is_some() {
+                    format!("Enter: ロビーへ | {}", self.quit_hint())
+                } else {
+                    format!(
+                        "{} | {} | {}",
+                        KeyMap::hint(&self.keys.restart, "新しいマッチ"),
+                        KeyMap::hint(&self.keys.menu, "設定"),
+                        self.quit_hint()
+                    )
+                };
+                let scoreboard = Scoreboard {
+                    title: &title,
+                    heading: "通算",
+                    stats: &self.match_state.as_ref().map(|m| m.totals()).unwrap_or_default(),
+                    wins: &self.wins_by_slot(),
+                    footer: &footer,
+                };
+                scoreboard::render_scoreboard(frame, &scoreboard, &self.glyphs, &self.theme);
+            }
+            AppState::Settings => {
+                if let GameSetup::Battle { slots, rules } = &self.setup {
+                    settings::render_settings(
+                        frame,
+                        rules,
+                        slots.len(),
+                        self.settings_cursor,
+                        &self.settings_footer(),
+                        &self.theme,
+                    );
+                }
+            }
+            AppState::Lobby => self.render_lobby(frame),
             AppState::Playing => {}
         }
+
+        if let Some(net) = &self.net {
+            if let Some(err) = &net.host_error {
+                let message = format!("{} | {}", err, self.quit_hint());
+                self.render_overlay(frame, "ホストが停止しました", &message);
+            } else if let Some(reason) = &net.rejected {
+                self.render_overlay(frame, "接続を拒否されました", reason);
+            } else if net.disconnected {
+                self.render_overlay(frame, "DISCONNECTED", &self.quit_hint());
+            } else if let Some((tick, slot)) = net.desync {
+                let banner = Paragraph::new(format!("⚠ DESYNC: P{} @ tick {}", slot + 1, tick))
+                    .style(self.theme.hud.alert.style())
+                    .alignment(Alignment::Center);
+                frame.render_widget(banner, Rect { height: 1, ..frame.area() });
+            }
+        }
+        self.render_command_line(frame);
+    }
+
+    /// The `:` prompt or the last command's message on the bottom row, with
+    /// the completion candidates above it while Tab is cycling.
+    fn render_command_line(&self, frame: &mut Frame) {
+        let area = frame.area();
+        let bottom = Rect { y: area.bottom() - 1, height: 1, ..area };
+        let line = if self.command_open {
+            Line::from(format!(":{}", self.command_line.input)).style(self.theme.hud.overlay.style())
+        } else if let Some((message, error)) = &self.command_message {
+            let style = if *error { self.theme.hud.alert } else { self.theme.hud.overlay };
+            Line::from(message.as_str()).style(style.style())
+        } else {
+            return;
+        };
+        frame.render_widget(Clear, bottom);
+        frame.render_widget(Paragraph::new(line), bottom);
+
+        let Some((candidates, shown)) = self.command_line.candidates().filter(|_| self.command_open) else {
+            return;
+        };
+        let above = Rect { y: bottom.y.saturating_sub(1), ..bottom };
+        let spans: Vec<Span> = candidates
+            .iter()
+            .enumerate()
+            .flat_map(|(index, candidate)| {
+                let style = if index == shown { self.theme.hud.selected } else { self.theme.hud.overlay };
+                [Span::raw(" "), Span::styled(candidate.as_str(), style.style())]
+            })
+            .collect();
+        frame.render_widget(Clear, above);
+        frame.render_widget(
+            Paragraph::new(Line::from(spans)).style(self.theme.hud.overlay.style()),
+            above,
+        );
+    }
+
+    /// Smallest terminal the stage fits in at the current zoom: all of it,
+    /// or `MIN_VIEW` tiles of a bigger one, with the header and HUD.
+    fn min_size(&self) -> (u16, u16) {
+        let world = &self.game_state.world;
+        let tiles = (world.width().min(MIN_VIEW.0), world.height().min(MIN_VIEW.1));
+        let width = ((tiles.0 * self.zoom.cell_width()) as u16 + 2).max(MIN_WIDTH);
+        let height = 3 + tiles.1 as u16 + 2 + hud::height(&self.game_state, width);
+        (width, height)
+    }
+
+    fn is_too_small(&self) -> bool {
+        let (width, height) = self.min_size();
+        self.screen.0 < width || self.screen.1 < height
+    }
+
+    /// Pauses a local game while the terminal is too small to play in, and
+    /// resumes it once it has grown back.
+    fn resize(&mut self, width: u16, height: u16) {
+        self.screen = (width, height);
+        self.guard_size();
+    }
+
+    fn guard_size(&mut self) {
+        if self.net.is_some() {
+            return;
+        }
+        if self.is_too_small() {
+            if self.app_state == AppState::Playing {
+                self.app_state = AppState::Paused;
+                self.paused_for_size = true;
+            }
+        } else if self.paused_for_size {
+            self.paused_for_size = false;
+            if self.app_state == AppState::Paused {
+                self.app_state = AppState::Playing;
+            }
+        }
+    }
+
+    fn render_too_small(&self, frame: &mut Frame) {
+        let (width, height) = self.min_size();
+        let area = frame.area();
+        let mut text = vec![
+            Line::from(Span::styled("端末が小さすぎます", self.theme.hud.title.style())),
+            Line::from(format!("必要: {}×{}", width, height)),
+            Line::from(format!("現在: {}×{}", area.width, area.height)),
+        ];
+        if self.net.is_none() && self.paused_for_size {
+            text.push(Line::from("ポーズ中"));
+        }
+        if self.zoom == Zoom::Near {
+            text.push(Line::from(KeyMap::hint(&self.keys.zoom, "ズーム切り替え")));
+        }
+        text.push(Line::from(self.quit_hint()));
+
+        let top = area.height.saturating_sub(text.len() as u16) / 2;
+        let paragraph = Paragraph::new(text)
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true })
+            .style(self.theme.hud.overlay.style());
+        frame.render_widget(Clear, area);
+        frame.render_widget(paragraph, Rect { y: area.y + top, height: area.height - top, ..area });
+    }
+
+    fn render_lobby(&self, frame: &mut Frame) {
+        let Some(net) = &self.net else {
+            return;
+        };
+
+        let mut text = vec![
+            Line::from(Span::styled("LOBBY", self.theme.hud.title.style())),
+            Line::from(""),
+        ];
+        for (slot, name) in net.lobby.iter().enumerate() {
+            let marker = if net.slot == Some(slot) { "▶" } else { " " };
+            let name = name.as_deref().unwrap_or("---");
+            text.push(Line::from(Span::styled(
+                format!("{} P{} {}", marker, slot + 1, name),
+                Style::default().fg(self.theme.player(slot)),
+            )));
+        }
+        text.push(Line::from(""));
+        text.push(Line::from(if net.slot == Some(0) {
+            format!("Enter: 開始 | {}", self.quit_hint())
+        } else {
+            format!("ホストの開始を待っています… | {}", self.quit_hint())
+        }));
+
+        let area = frame.area();
+        let height = (text.len() as u16 + 2).min(area.height);
+        let width = 44.min(area.width);
+        let overlay_area = Rect {
+            x: (area.width - width) / 2,
+            y: (area.height - height) / 2,
+            width,
+            height,
+        };
+        let paragraph = Paragraph::new(text)
+            .alignment(Alignment::Center)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .style(self.theme.hud.overlay.style()),
+            );
+        frame.render_widget(paragraph, overlay_area);
     }
 
     fn render_header(&self, frame: &mut Frame, area: Rect) {
-        let title = Paragraph::new("💣 BMTUI 💣")
-            .style(Style::default().fg(Color::Yellow))
+        let logo = format!("{0} BMTUI {0}", self.glyphs.bomb_icon);
+        let text = match self.net.as_ref().and_then(|net| net.spectating) {
+            Some(delay) => format!(
+                "{}  観戦中 ({:.0}秒遅れ) | Tab: 注目プレイヤー切替",
+                logo,
+                delay as f32 * TICK_SECONDS
+            ),
+            None => logo,
+        };
+        let title = Paragraph::new(text)
+            .style(self.theme.hud.header.style())
             .alignment(Alignment::Center)
-            .block(Block::default().borders(Borders::ALL));
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(self.theme.hud.border.style()),
+            );
         frame.render_widget(title, area);
     }
 
-    fn render_game_board(&self, frame: &mut Frame, area: Rect) {
-        let board = self.create_game_board();
-        let paragraph = Paragraph::new(board)
-            .alignment(Alignment::Center)
-            .block(Block::default().borders(Borders::ALL).title("Game Board"));
-        frame.render_widget(paragraph, area);
-    }
-
-    fn create_game_board(&self) -> Vec<Line<'static>> {
-        let mut lines = Vec::new();
-
-        for y in 0..self.game_state.world.height() {
-            let mut spans = Vec::new();
-            for x in 0..self.game_state.world.width() {
-                let mut found = false;
-
-                for entity in &self.game_state.entities {
-                    if entity.is_alive && entity.position.x == x && entity.position.y == y {
-                        let char_str = entity.to_char().to_string();
-                        let span = if entity.entity_type == crate::game::entity::EntityType::Item {
-                            Span::styled(char_str, Style::default().bg(Color::White).fg(Color::Black))
-                        } else {
-                            Span::raw(char_str)
-                        };
-                        spans.push(span);
-                        found = true;
-                        break;
-                    }
-                }
+    /// Fits the camera to the board's `area` and keeps it on the viewers.
+    fn follow_camera(&mut self, area: Rect) {
+        let world = (self.game_state.world.width(), self.game_state.world.height());
+        let view = (
+            area.width.saturating_sub(2) as usize / self.zoom.cell_width(),
+            area.height.saturating_sub(2) as usize,
+        );
+        let target = self.camera_target();
+        self.camera.follow(target, view, world);
+    }
 
-                if !found {
-                    if let Some(tile) = self.game_state.world.get_tile(x, y) {
-                        spans.push(Span::raw(tile.to_char().to_string()));
-                    }
-                }
+    fn render_game_board(&self, frame: &mut Frame, area: Rect, fog: Option<Fog>) {
+        let world = (self.game_state.world.width(), self.game_state.world.height());
+        let camera = self.camera;
+        let mut title = String::from("Game Board");
+        if let Some(count) = self.input.pending() {
+            title.push_str(&format!(" [{}]", count));
+        }
+        for (hidden, arrow) in [
+            (camera.x > 0, " <"),
+            (camera.y > 0, " ^"),
+            (camera.y + camera.height < world.1, " v"),
+            (camera.x + camera.width < world.0, " >"),
+        ] {
+            if hidden {
+                title.push_str(arrow);
             }
-            lines.push(Line::from(spans));
         }
 
-        lines
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(self.theme.hud.border.style())
+            .title(title);
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let danger = self.show_danger.then(|| self.game_state.danger_map());
+        let board = Board {
+            state: &self.game_state,
+            camera,
+            zoom: self.zoom,
+            glyphs: self.board_glyphs(),
+            theme: &self.theme,
+            accessibility: self.accessibility,
+            danger: danger.as_ref(),
+            blink: self.blink(),
+            fog,
+        };
+        frame.render_widget(board, inner);
+    }
+
+    /// The minimap at `scale`, framed in a block titled "Map".
+    fn render_map(&self, frame: &mut Frame, area: Rect, scale: usize, fog: Option<Fog>) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(self.theme.hud.border.style())
+            .style(self.theme.hud.overlay.style())
+            .title("Map");
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+        let (width, height) = Minimap::size(&self.game_state, scale);
+        let centered = Rect {
+            x: inner.x + inner.width.saturating_sub(width) / 2,
+            y: inner.y + inner.height.saturating_sub(height) / 2,
+            width: width.min(inner.width),
+            height: height.min(inner.height),
+        };
+        let minimap = Minimap {
+            state: &self.game_state,
+            theme: &self.theme,
+            scale,
+            fog,
+        };
+        frame.render_widget(minimap, centered);
+    }
+
+    /// The whole stage as large as it fits over the screen.
+    fn render_full_map(&self, frame: &mut Frame, fog: Option<Fog>) {
+        let area = frame.area();
+        let margin = Rect {
+            x: area.x + 2,
+            y: area.y + 1,
+            width: area.width.saturating_sub(4),
+            height: area.height.saturating_sub(2),
+        };
+        let inner = Block::default().borders(Borders::ALL).inner(margin);
+        let scale = Minimap::fit(&self.game_state, inner);
+        let (width, height) = Minimap::size(&self.game_state, scale);
+        let outer = Rect {
+            x: area.x + area.width.saturating_sub(width + 2) / 2,
+            y: area.y + area.height.saturating_sub(height + 2) / 2,
+            width: (width + 2).min(area.width),
+            height: (height + 2).min(area.height),
+        };
+        self.render_map(frame, outer, scale, fog);
+    }
+
+    /// Bombers whose view the screen shows: our own or the watched one in
+    /// a networked game, otherwise every local human.
+    fn viewers(&self) -> Vec<usize> {
+        let state = &self.game_state;
+        match self.highlighted_slot() {
+            Some(slot) => state.players.get(slot).copied().into_iter().collect(),
+            None => state
+                .players
+                .iter()
+                .enumerate()
+                .filter(|(slot, _)| !state.is_cpu(*slot))
+                .map(|(_, id)| *id)
+                .collect(),
+        }
+    }
+
+    /// What the camera keeps in view: the middle of the live viewers.
+    fn camera_target(&self) -> Option<Position> {
+        let viewers = self.viewers();
+        let positions: Vec<Position> = self
+            .game_state
+            .entities
+            .iter()
+            .filter(|e| e.is_alive && viewers.contains(&e.id))
+            .map(|e| e.position)
+            .collect();
+        let count = positions.len().max(1);
+        (!positions.is_empty()).then(|| {
+            Position::new(
+                positions.iter().map(|p| p.x).sum::<usize>() / count,
+                positions.iter().map(|p| p.y).sum::<usize>() / count,
+            )
+        })
+    }
+
+    fn quit_hint(&self) -> String {
+        KeyMap::hint(&self.keys.quit, "終了")
+    }
+
+    fn settings_footer(&self) -> String {
+        let keys = self.keys.player(0);
+        format!(
+            "{}/←↓↑→: 選択・変更 | Enter: 開始 | {}",
+            keys.move_keys(),
+            self.quit_hint()
+        )
+    }
+
+    fn restart_footer(&self) -> String {
+        format!("{} | {}", KeyMap::hint(&self.keys.restart, "リスタート"), self.quit_hint())
+    }
+
+    fn board_glyphs(&self) -> &Glyphs {
+        match self.zoom {
+            Zoom::Near => &self.glyphs,
+            Zoom::Far => &self.narrow_glyphs,
+        }
+    }
+
+    /// Whether blinking things are in their alternate look this frame.
+    fn blink(&self) -> bool {
+        !self.accessibility.reduced_motion && ((self.game_state.elapsed * 8.0) as u32).is_multiple_of(2)
     }
 
+    /// A box sized to its text, centered on the screen; on a screen too
+    /// narrow for it the message wraps.
     fn render_overlay(&self, frame: &mut Frame, title: &str, message: &str) {
         let area = frame.area();
-        let overlay_width = 40;
-        let overlay_height = 7;
-        let x = (area.width.saturating_sub(overlay_width)) / 2;
-        let y = (area.height.saturating_sub(overlay_height)) / 2;
+        let text_width = Line::from(title).width().max(Line::from(message).width()) as u16;
+        let overlay_width = (text_width + 6).max(24).min(area.width);
+        let overlay_height = 7.min(area.height);
 
         let overlay_area = Rect {
-            x,
-            y,
-            width: overlay_width.min(area.width),
-            height: overlay_height.min(area.height),
+            x: (area.width - overlay_width) / 2,
+            y: (area.height - overlay_height) / 2,
+            width: overlay_width,
+            height: overlay_height,
         };
 
         let text = vec![
             Line::from(""),
-            Line::from(Span::styled(
-                title,
-                Style::default().fg(Color::Yellow).add_modifier(ratatui::style::Modifier::BOLD),
-            )),
+            Line::from(Span::styled(title, self.theme.hud.title.style())),
             Line::from(""),
             Line::from(message),
         ];
 
         let paragraph = Paragraph::new(text)
             .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true })
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .style(Style::default().bg(Color::Black)),
+                    .style(self.theme.hud.overlay.style()),
             );
 
+        frame.render_widget(Clear, overlay_area);
         frame.render_widget(paragraph, overlay_area);
     }
 
     pub fn handle_event(&mut self, event: crossterm::event::Event) -> bool {
-        if let crossterm::event::Event::Key(key) = event {
-            self.handle_key(key)
-        } else {
-            true
+        match event {
+            crossterm::event::Event::Key(key) if key.kind == KeyEventKind::Release => {
+                self.release_key(&key);
+                true
+            }
+            crossterm::event::Event::Key(key) => self.handle_key(key),
+            crossterm::event::Event::Resize(width, height) => {
+                self.resize(width, height);
+                true
+            }
+            _ => true,
         }
     }
 
     fn handle_key(&mut self, key: KeyEvent) -> bool {
+        self.command_message = None;
+        if self.command_open {
+            return self.handle_command_key(key);
+        }
+        if key.code == KeyCode::Char(':') && !self.is_spectating() {
+            self.command_line.open();
+            self.command_open = true;
+            self.held.iter_mut().for_each(Vec::clear);
+            self.input.clear();
+            return true;
+        }
+        if self.keys.is_danger(&key) {
+            self.show_danger = !self.show_danger;
+            return true;
+        }
+        if self.keys.is_zoom(&key) {
+            self.zoom = self.zoom.toggle();
+            return true;
+        }
+        if self.keys.is_minimap(&key) {
+            self.show_minimap = !self.show_minimap;
+            return true;
+        }
+        if self.keys.is_full_map(&key) {
+            self.show_full_map = !self.show_full_map;
+            return true;
+        }
+        if self.keys.is_log(&key) {
+            self.messages.toggle();
+            return true;
+        }
+        if self.messages.open {
+            let lines = match key.code {
+                KeyCode::PageUp => Some(5),
+                KeyCode::PageDown => Some(-5),
+                KeyCode::Home => Some(isize::MAX),
+                KeyCode::End => Some(isize::MIN),
+                _ => None,
+            };
+            if let Some(lines) = lines {
+                self.messages.scroll(lines);
+                return true;
+            }
+        }
+
+        if self.net.is_some() {
+            return self.handle_network_key(key);
+        }
+
+        if self.keys.is_quit(&key) {
+            self.should_quit = true;
+            return false;
+        }
+
         match self.app_state {
-            AppState::Playing => match key.code {
-                KeyCode::Char('q') | KeyCode::Esc => {
-                    self.should_quit = true;
-                    false
-                }
-                KeyCode::Char('p') => {
-                    self.app_state = AppState::Paused;
-                    true
-                }
-                KeyCode::Char('h') => {
-                    self.game_state
-                        .move_entity(self.game_state.player_id, GameDirection::Left);
-                    true
-                }
-                KeyCode::Char('j') => {
-                    self.game_state
-                        .move_entity(self.game_state.player_id, GameDirection::Down);
-                    true
-                }
-                KeyCode::Char('k') => {
-                    self.game_state
-                        .move_entity(self.game_state.player_id, GameDirection::Up);
-                    true
-                }
-                KeyCode::Char('l') => {
-                    self.game_state
-                        .move_entity(self.game_state.player_id, GameDirection::Right);
-                    true
-                }
-                KeyCode::Char(' ') => {
-                    self.game_state.place_bomb();
-                    true
-                }
-                _ => true,
-            },
-            AppState::Paused => match key.code {
-                KeyCode::Char('q') | KeyCode::Esc => {
-                    self.should_quit = true;
-                    false
+            AppState::Playing if self.keys.is_pause(&key) => {
+                self.app_state = AppState::Paused;
+            }
+            AppState::Playing => self.handle_player_key(&key),
+            AppState::Paused if self.keys.is_pause(&key) => {
+                self.app_state = AppState::Playing;
+            }
+            AppState::RoundOver if key.code == KeyCode::Enter || self.keys.is_restart(&key) => {
+                if let Some(match_state) = &mut self.match_state {
+                    match_state.next_round();
                 }
-                KeyCode::Char('p') => {
-                    self.app_state = AppState::Playing;
-                    true
+                self.restart();
+            }
+            AppState::Settings => self.handle_settings_key(&key),
+            AppState::GameOver | AppState::Clear | AppState::MatchOver
+                if self.keys.is_restart(&key) =>
+            {
+                self.start_match();
+            }
+            AppState::MatchOver if self.keys.is_menu(&key) => {
+                self.app_state = AppState::Settings;
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn handle_command_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Esc => self.command_open = false,
+            KeyCode::Enter => {
+                self.command_open = false;
+                let line = self.command_line.submit();
+                if !line.trim().is_empty() {
+                    return self.run_command(&line);
+                }
+            }
+            KeyCode::Backspace => self.command_open = self.command_line.backspace(),
+            KeyCode::Up => self.command_line.history_back(),
+            KeyCode::Down => self.command_line.history_forward(),
+            KeyCode::Tab => self.command_line.complete(&Completions {
+                stages: stage_names(),
+                themes: Theme::available(),
+            }),
+            KeyCode::Char(c) if !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+                self.command_line.push(c);
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Runs a `:` line. Returns false once the app should quit.
+    fn run_command(&mut self, line: &str) -> bool {
+        self.command_message = match Command::parse(line).and_then(|command| self.execute(command)) {
+            Ok(message) => message.map(|message| (message, false)),
+            Err(err) => Some((err, true)),
+        };
+        !self.should_quit
+    }
+
+    fn execute(&mut self, command: Command) -> Result<Option<String>, String> {
+        match command {
+            Command::Stage(name) => {
+                self.local_only()?;
+                let stage = load_stage(&name)?;
+                let message = format!("ステージ: {}", stage.name);
+                self.stage = Some(stage);
+                self.start_match();
+                Ok(Some(message))
+            }
+            Command::Restart => {
+                self.local_only()?;
+                self.start_match();
+                Ok(None)
+            }
+            Command::Seed(None) => Ok(Some(format!("シード: {}", self.seed))),
+            Command::Seed(Some(seed)) => {
+                self.local_only()?;
+                self.next_seed = Some(seed);
+                self.start_match();
+                Ok(Some(format!("シード {} で開始しました", seed)))
+            }
+            Command::Set(setting) => self.apply_setting(setting),
+            Command::Theme(name) => match Theme::load(&name) {
+                Ok(theme) => {
+                    self.set_theme(theme);
+                    self.chosen_theme = Some(name);
+                    Ok(None)
+                }
+                Err(err) => {
+                    let glyph_set = GlyphSet::parse(&name).ok_or(err)?;
+                    self.apply_setting(Setting::Glyphs(glyph_set))
                 }
-                _ => true,
             },
-            AppState::GameOver | AppState::Clear => match key.code {
-                KeyCode::Char('q') | KeyCode::Esc => {
-                    self.should_quit = true;
-                    false
+            Command::Save => {
+                if !self.can_save {
+                    return Err("このセッションでは設定を保存できません".to_string());
+                }
+                let mut config = Config::read()?;
+                if let Some(theme) = &self.chosen_theme {
+                    config.theme = Some(theme.clone());
+                }
+                if let Some(glyph_set) = self.chosen_glyphs {
+                    config.glyphs = Some(glyph_set);
+                }
+                config.reduced_motion = self.accessibility.reduced_motion;
+                config.save()?;
+                Ok(Some(format!("{} に保存しました", Config::path().display())))
+            }
+            Command::Quit => {
+                self.should_quit = true;
+                Ok(None)
+            }
+        }
+    }
+
+    fn apply_setting(&mut self, setting: Setting) -> Result<Option<String>, String> {
+        match setting {
+            Setting::TickRate(rate) => {
+                self.local_only()?;
+                self.tick_rate = rate;
+            }
+            Setting::Glyphs(glyph_set) => {
+                self.chosen_glyphs = Some(glyph_set);
+                self.set_glyphs(glyph_set);
+            }
+            Setting::Zoom(zoom) => self.zoom = zoom,
+            Setting::Minimap(on) => self.show_minimap = on,
+            Setting::Danger(on) => self.show_danger = on,
+            Setting::Labels(on) => self.accessibility.labels = on,
+            Setting::ReducedMotion(on) => self.accessibility.reduced_motion = on,
+            Setting::SuddenDeath(on) => self.change_rules(|rules| rules.sudden_death = on)?,
+            Setting::SuddenDeathAfter(seconds) => self.change_rules(|rules| {
+                rules.sudden_death = true;
+                rules.sudden_death_after = seconds;
+            })?,
+        }
+        Ok(None)
+    }
+
+    /// Changes a match rule both for the rounds to come and for the one
+    /// being played.
+    fn change_rules(&mut self, change: impl Fn(&mut BattleRules)) -> Result<(), String> {
+        self.local_only()?;
+        let GameSetup::Battle { rules, .. } = &mut self.setup else {
+            return Err("バトルモードでのみ使えます".to_string());
+        };
+        change(rules);
+        change(&mut self.game_state.rules);
+        Ok(())
+    }
+
+    /// Commands that change the game itself are the host's call in a
+    /// networked match.
+    fn local_only(&self) -> Result<(), String> {
+        match self.net {
+            Some(_) => Err("ネット対戦中は使えません".to_string()),
+            None => Ok(()),
+        }
+    }
+
+    /// The settings screen follows player 1's keys, plus the arrow keys:
+    /// up and down pick a line, left and right or the bomb key change it.
+    fn handle_settings_key(&mut self, key: &KeyEvent) {
+        let GameSetup::Battle { rules, .. } = &mut self.setup else {
+            return;
+        };
+        let field = RuleField::ALL[self.settings_cursor];
+        let step = match (key.code, self.keys.player(0).action(key)) {
+            (KeyCode::Up, _) | (_, Some(Action::Move(Heading::Up))) => {
+                self.settings_cursor =
+                    (self.settings_cursor + RuleField::ALL.len() - 1) % RuleField::ALL.len();
+                return;
+            }
+            (KeyCode::Down | KeyCode::Tab, _) | (_, Some(Action::Move(Heading::Down))) => {
+                self.settings_cursor = (self.settings_cursor + 1) % RuleField::ALL.len();
+                return;
+            }
+            (KeyCode::Enter, _) => {
+                self.start_match();
+                return;
+            }
+            (KeyCode::Left, _) | (_, Some(Action::Move(Heading::Left))) => -1,
+            (KeyCode::Right, _) | (_, Some(Action::Move(Heading::Right) | Action::PlaceBomb)) => 1,
+            _ => return,
+        };
+        // Picking a stage here takes over from one chosen with `:stage`.
+        if field == RuleField::Stage {
+            self.stage = None;
+        }
+        rules.adjust(field, step);
+    }
+
+    fn handle_network_key(&mut self, key: KeyEvent) -> bool {
+        if self.keys.is_quit(&key) {
+            self.should_quit = true;
+            return false;
+        }
+
+        if self.is_spectating() {
+            self.handle_spectator_key(key.code);
+            return true;
+        }
+
+        if self.app_state == AppState::Playing && self.vim_input(&key) {
+            return true;
+        }
+        let action = self.bindings.first().and_then(|b| b.action(&key));
+        let mut act = action.is_some();
+        if let Some(Action::Move(heading)) = action
+            && self.key_release
+            && self.app_state == AppState::Playing
+        {
+            act = key.kind != KeyEventKind::Repeat;
+            if act {
+                self.hold(0, heading);
+            }
+        }
+        let Some(net) = &mut self.net else {
+            return true;
+        };
+        match self.app_state {
+            AppState::Lobby if key.code == KeyCode::Enter => {
+                net.send(ClientMessage::StartMatch);
+            }
+            AppState::MatchOver if key.code == KeyCode::Enter => {
+                self.app_state = AppState::Lobby;
+            }
+            AppState::Playing => {
+                if let Some(action) = action
+                    && act
+                {
+                    net.queue_action(action);
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn handle_spectator_key(&mut self, code: KeyCode) {
+        let players = self.game_state.players.len().max(1);
+        match code {
+            KeyCode::Enter if self.app_state == AppState::MatchOver => {
+                self.app_state = AppState::Lobby;
+            }
+            KeyCode::Tab | KeyCode::Right | KeyCode::Char('l') => {
+                self.highlighted = (self.highlighted + 1) % players;
+            }
+            KeyCode::BackTab | KeyCode::Left | KeyCode::Char('h') => {
+                self.highlighted = (self.highlighted + players - 1) % players;
+            }
+            KeyCode::Char(c @ '1'..='4') => {
+                let slot = c as usize - '1' as usize;
+                if slot < players {
+                    self.highlighted = slot;
                 }
-                KeyCode::Char('r') => {
-                    self.game_state = GameState::new();
+            }
+            _ => {}
+        }
+    }
+
+    fn tick_network(&mut self) {
+        let Some(net) = &mut self.net else {
+            return;
+        };
+
+        for message in net.poll() {
+            match message {
+                ServerMessage::Lobby { .. } => {
+                    if self.app_state != AppState::MatchOver {
+                        self.app_state = AppState::Lobby;
+                    }
+                }
+                ServerMessage::RoundStart {
+                    seed,
+                    players,
+                    round,
+                    rules,
+                    ..
+                } => {
+                    self.setup = round_setup(players, &rules);
+                    self.game_state = self.setup.build(seed);
+                    self.terrain_memory.clear();
+                    self.held.clear();
+                    self.input.clear();
+                    self.messages.new_round(format!("── Round {} ──", round));
+                    let sides = rules.sides(players);
+                    let match_state = self
+                        .match_state
+                        .get_or_insert_with(|| MatchState::new(rules.best_of, sides));
+                    if round == 1 {
+                        *match_state = MatchState::new(rules.best_of, sides);
+                    }
+                    match_state.round = round;
+                    self.round_winner = None;
                     self.app_state = AppState::Playing;
-                    true
                 }
-                _ => true,
-            },
+                ServerMessage::Frame { tick, actions, left } => {
+                    apply_frame(&mut self.game_state, &actions, &left);
+                    net.frame_applied(tick, &self.game_state);
+                    self.messages.collect(&self.game_state);
+                }
+                ServerMessage::RoundOver { winner } => {
+                    self.round_winner = winner;
+                    if let Some(match_state) = &mut self.match_state {
+                        match_state.record_round(winner, &self.game_state.round_stats);
+                    }
+                    self.app_state = AppState::RoundOver;
+                }
+                ServerMessage::MatchOver { .. } => {
+                    self.app_state = AppState::MatchOver;
+                }
+                ServerMessage::Welcome { .. }
+                | ServerMessage::Spectating { .. }
+                | ServerMessage::Rejected { .. }
+                | ServerMessage::Desync { .. }
+                | ServerMessage::Input { .. }
+                | ServerMessage::PlayerLeft { .. } => {}
+            }
+        }
+
+        if self.app_state != AppState::Playing {
+            return;
+        }
+        if !self.is_spectating() {
+            self.walk_held();
+        }
+        let Some(net) = &mut self.net else {
+            return;
+        };
+        if net.rollback().is_some() {
+            net.advance_rollback();
+            if let Some(session) = net.rollback() {
+                self.game_state = session.state().clone();
+                self.messages.collect(&self.game_state);
+            }
+        } else {
+            net.send_inputs();
+        }
+    }
+
+    fn handle_player_key(&mut self, key: &KeyEvent) {
+        if self.vim_input(key) {
+            self.walk_held();
+            return;
+        }
+        let human_slots = self.setup.human_slots();
+        let pressed = self
+            .bindings
+            .iter()
+            .zip(human_slots)
+            .enumerate()
+            .find_map(|(index, (bindings, slot))| Some((index, slot, bindings.action(key)?)));
+        match pressed {
+            Some((_, _, Action::Move(_))) if key.kind == KeyEventKind::Repeat && self.key_release => {}
+            Some((index, _, Action::Move(heading))) => {
+                self.hold(index, heading);
+                self.walk_held();
+            }
+            Some((_, slot, action)) => {
+                if let Some(&entity_id) = self.game_state.players.get(slot) {
+                    self.game_state.apply_action(entity_id, action);
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// Passes a key to the Vim input queue when one player has the
+    /// keyboard; several players need the digits and letters for their own
+    /// keys. Returns whether the queue took the key.
+    fn vim_input(&mut self, key: &KeyEvent) -> bool {
+        let [bindings] = self.bindings.as_slice() else {
+            return false;
+        };
+        let slot = match &self.net {
+            Some(net) => net.slot,
+            None => self.setup.human_slots().first().copied(),
+        };
+        let Some(&entity_id) = slot.and_then(|slot| self.game_state.players.get(slot)) else {
+            return false;
+        };
+        self.input.press(key, bindings, &self.game_state, entity_id)
+    }
+
+    /// Notes a pressed movement key. With release events it stays held
+    /// until let go; without them it is one step, taken as soon as the
+    /// bomber's speed allows, so the key repeat walks at that speed.
+    fn hold(&mut self, index: usize, heading: Heading) {
+        if self.held.len() <= index {
+            self.held.resize(index + 1, Vec::new());
+        }
+        let held = &mut self.held[index];
+        if self.key_release {
+            held.retain(|h| *h != heading);
+            held.push(heading);
+        } else {
+            *held = vec![heading];
+        }
+    }
+
+    fn release_key(&mut self, key: &KeyEvent) {
+        for (bindings, held) in self.bindings.iter().zip(&mut self.held) {
+            if let Some(Action::Move(heading)) = bindings.action(key) {
+                held.retain(|h| *h != heading);
+            }
+        }
+    }
+
+    /// Steps every local bomber towards its latest held key whenever its
+    /// speed allows.
+    fn walk_held(&mut self) {
+        if let Some(net) = &mut self.net {
+            if net.has_queued() {
+                return;
+            }
+            if let Some(action) = self.input.next() {
+                net.queue_action(action);
+            } else if let Some(&heading) = self.held.first().and_then(|held| held.last()) {
+                net.queue_action(Action::Move(heading));
+            }
+            return;
+        }
+        let human_slots = self.setup.human_slots();
+        if let Some(&entity_id) = human_slots.first().and_then(|slot| self.game_state.players.get(*slot))
+            && self.game_state.ready_to_step(entity_id)
+            && let Some(action) = self.input.next()
+        {
+            self.game_state.apply_action(entity_id, action);
+            return;
+        }
+        for (index, slot) in human_slots.into_iter().enumerate() {
+            let Some(&heading) = self.held.get(index).and_then(|held| held.last()) else {
+                continue;
+            };
+            let Some(&entity_id) = self.game_state.players.get(slot) else {
+                continue;
+            };
+            if self.game_state.ready_to_step(entity_id) {
+                self.game_state.apply_action(entity_id, Action::Move(heading));
+                if !self.key_release {
+                    self.held[index].clear();
+                }
+            }
+        }
+    }
+
+    /// Starts a fresh local match with the current settings.
+    fn start_match(&mut self) {
+        if let GameSetup::Battle { slots, rules } = &self.setup {
+            self.match_state = Some(MatchState::new(rules.best_of, rules.sides(slots.len())));
+        }
+        self.restart();
+    }
+
+    /// How a round or match winner is announced.
+    fn side_name(&self, side: usize) -> String {
+        if self.game_state.rules.teams {
+            format!("TEAM {}", (b'A' + side as u8) as char)
+        } else {
+            format!("PLAYER {}", side + 1)
+        }
+    }
+
+    /// Round wins of each player's side, by player slot.
+    fn wins_by_slot(&self) -> Vec<u32> {
+        let Some(match_state) = &self.match_state else {
+            return Vec::new();
+        };
+        (0..self.game_state.players.len())
+            .map(|slot| {
+                let side = self.game_state.rules.side_of(slot);
+                match_state.wins.get(side).copied().unwrap_or(0)
+            })
+            .collect()
+    }
+
+    fn restart(&mut self) {
+        self.seed = self.next_seed.take().unwrap_or_else(rand::random);
+        self.game_state = match &self.stage {
+            Some(stage) => self.setup.build_on(stage.clone(), self.seed),
+            None => self.setup.build(self.seed),
+        };
+        self.terrain_memory.clear();
+        self.held.clear();
+        self.input.clear();
+        let note = match &self.match_state {
+            Some(match_state) => format!("── Round {} ──", match_state.round),
+            None => "── Start ──".to_string(),
+        };
+        self.messages.new_round(note);
+        self.round_winner = None;
+        self.app_state = AppState::Playing;
+    }
+
+    fn controls(&self) -> Vec<String> {
+        match (&self.setup, self.bindings.first()) {
+            (GameSetup::Story, Some(bindings)) => vec![
+                format!("{}: 移動", bindings.move_keys()),
+                format!("{}: 爆弾 {}: 起爆", bindings.bomb_key(), bindings.detonate_key()),
+                format!("{}: 表示", self.keys.view_keys()),
+            ],
+            _ => self.bindings.iter().map(|b| b.describe()).collect(),
         }
     }
 
     pub fn tick(&mut self) {
-        if self.app_state == AppState::Playing {
-            self.game_state.tick(0.05);
+        self.messages.tick(self.tick_seconds());
+        self.guard_size();
+        if self.net.is_some() {
+            self.tick_network();
+            return;
+        }
+
+        // The game holds still while a command is being typed.
+        if self.app_state == AppState::Playing && !self.command_open {
+            self.walk_held();
+            self.game_state.tick(self.tick_seconds());
+            self.messages.collect(&self.game_state);
             self.update_game_state();
         }
     }
 
     fn update_game_state(&mut self) {
+        if self.game_state.mode == GameMode::Battle {
+            if let Some(winner) = self.game_state.round_result() {
+                self.round_winner = winner;
+                self.app_state = AppState::RoundOver;
+                if let Some(match_state) = &mut self.match_state {
+                    match_state.record_round(winner, &self.game_state.round_stats);
+                    if match_state.champion().is_some() {
+                        self.app_state = AppState::MatchOver;
+                    }
+                }
+            }
+            return;
+        }
+
         let player_alive = self
             .game_state
             .get_player()
@@ -230,7 +1397,7 @@ impl App {
             .game_state
             .entities
             .iter()
-            .filter(|e| e.entity_type == crate::game::entity::EntityType::Enemy && e.is_alive)
+            .filter(|e| e.entity_type == EntityType::Enemy && e.is_alive)
             .count();
 
         if !player_alive {
@@ -0,0 +1,333 @@
+use std::path::PathBuf;
+
+use crate::game::{
+    mode::{BattleRules, BomberSlot, Difficulty, GameSetup, SUDDEN_DEATH_AFTER},
+    stage::loader::battle_stages,
+};
+use crate::net::{
+    loopback::{LinkConfig, LoopbackConfig},
+    protocol::Netcode,
+    DEFAULT_PORT, SPECTATOR_DELAY_SECONDS,
+};
+use crate::ssh::{default_authorized_keys_path, default_host_key_path, DEFAULT_SSH_PORT};
+
+pub const USAGE: &str = "\
+Usage:
+  bmtui                    Play the story stages
+  bmtui battle [OPTIONS]   Battle mode on a shared keyboard and/or against CPUs;
+                           opens the match settings screen first
+  bmtui host [OPTIONS]     Host a LAN battle and play in it
+  bmtui join <ADDR>        Join a LAN battle, e.g. 192.168.0.10:7777
+  bmtui watch <ADDR>       Watch a LAN battle as a spectator
+  bmtui netsim [OPTIONS]   Test rollback netcode over a simulated bad link
+  bmtui serve [OPTIONS]    Let others play over ssh, e.g. ssh -p 2222 host
+
+Battle options:
+  --players <1-4>          Local players on this keyboard (default: 1)
+  --cpu <0-3>              CPU opponents (default: 1 with one player, else 0)
+  --difficulty <LEVEL>     easy, normal or hard (default: normal)
+
+Match rules (battle and host):
+  --rounds <N>             Best of N rounds (default: 3)
+  --teams                  P1 and P3 against P2 and P4
+  --bombs <1-8>            Bombs each bomber starts with (default: 1)
+  --fire <1-8>             Starting blast range (default: 1)
+  --items <PERCENT>        Chance that a block drops an item (default: 30)
+  --sudden-death <SECS>    Close the arena in after this long (default: 60)
+  --no-sudden-death        Never close the arena in on long rounds
+  --stage <N>              1: Battle Arena, 2: Open Field, 3: Fortress
+
+Host options:
+  --port <PORT>            Port to listen on (default: 7777)
+  --players <2-4>          Start automatically once this many have joined
+  --name <NAME>            Name shown in the lobby
+  --netcode <MODE>         lockstep or rollback (default: lockstep)
+  --delay <SECONDS>        How far spectators lag behind (default: 3)
+  --headless               Only run the server, without playing
+
+Join options:
+  --name <NAME>            Name shown in the lobby
+  --bot <LEVEL>            Let the CPU play (easy, normal, hard) without a UI
+
+Serve options:
+  --ssh <[HOST]:PORT>      Address to listen on (default: :2222)
+  --host-key <PATH>        Host key file, created if missing
+                           (default: ~/.config/bmtui/ssh_host_ed25519_key)
+  --authorized-keys <PATH> Public keys allowed to log in, one per line
+                           (default: ~/.config/bmtui/authorized_keys)
+  --lobby                  Put every session in one LAN lobby instead of
+                           story mode; also takes the host options
+
+Watch keys:
+  Tab / Shift+Tab, 1-4     Highlight another player's stats
+
+Netsim options:
+  --players <2-4>          CPU peers (default: 2)
+  --frames <N>             Frames to simulate (default: 1200)
+  --latency <MS>           One-way latency (default: 80)
+  --jitter <MS>            Extra random latency (default: 20)
+  --loss <PERCENT>         Packets dropped (default: 10)
+  --seed <N>               Seed for the game and the link (default: random)";
+
+pub struct HostOptions {
+    pub port: u16,
+    pub auto_start: Option<usize>,
+    pub rules: BattleRules,
+    pub name: String,
+    pub netcode: Netcode,
+    pub spectator_delay: u64,
+    pub headless: bool,
+}
+
+pub struct JoinOptions {
+    pub addr: String,
+    pub name: String,
+    pub bot: Option<Difficulty>,
+    pub spectate: bool,
+}
+
+pub struct ServeOptions {
+    pub addr: String,
+    pub host_key: PathBuf,
+    pub authorized_keys: PathBuf,
+    pub lobby: Option<HostOptions>,
+}
+
+pub enum Command {
+    Play(GameSetup),
+    Host(HostOptions),
+    Join(JoinOptions),
+    Netsim(LoopbackConfig),
+    Serve(ServeOptions),
+    Help,
+}
+
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut args = args.into_iter();
+
+    match args.next().as_deref() {
+        None => Ok(Command::Play(GameSetup::Story)),
+        Some("-h" | "--help" | "help") => Ok(Command::Help),
+        Some("battle") => parse_battle(args),
+        Some("host") => parse_host(args),
+        Some("join") => parse_join(args, false),
+        Some("watch") => parse_join(args, true),
+        Some("netsim") => parse_netsim(args),
+        Some("serve") => parse_serve(args),
+        Some(other) => Err(format!("unknown command: {}", other)),
+    }
+}
+
+fn parse_battle(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut players = 1;
+    let mut cpus = None;
+    let mut difficulty = Difficulty::Normal;
+    let mut rules = BattleRules::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--players" => {
+                players = number(&mut args, &arg, 1..=4)?;
+            }
+            "--cpu" => {
+                cpus = Some(number(&mut args, &arg, 0..=3)?);
+            }
+            "--difficulty" => {
+                let name = value(&mut args, &arg)?;
+                difficulty = Difficulty::parse(&name)
+                    .ok_or_else(|| format!("unknown difficulty: {}", name))?;
+            }
+            _ => rule_option(&mut rules, &arg, &mut args)?,
+        }
+    }
+
+    let cpus = cpus.unwrap_or(if players == 1 { 1 } else { 0 });
+    if !(2..=4).contains(&(players + cpus)) {
+        return Err("a battle needs 2 to 4 bombers in total".to_string());
+    }
+
+    let mut slots = vec![BomberSlot::Human; players];
+    slots.extend(std::iter::repeat_n(BomberSlot::Cpu(difficulty), cpus));
+    Ok(Command::Play(GameSetup::Battle { slots, rules }))
+}
+
+fn parse_host(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut options = HostOptions::default();
+    while let Some(arg) = args.next() {
+        host_option(&mut options, &arg, &mut args)?;
+    }
+    Ok(Command::Host(options))
+}
+
+fn parse_serve(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut addr = format!("0.0.0.0:{}", DEFAULT_SSH_PORT);
+    let mut host_key = default_host_key_path();
+    let mut authorized_keys = default_authorized_keys_path();
+    let mut lobby = None;
+    let mut host = HostOptions::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--ssh" => {
+                let value = value(&mut args, &arg)?;
+                addr = match value.strip_prefix(':') {
+                    Some(port) => format!("0.0.0.0:{}", port),
+                    None => value,
+                };
+            }
+            "--host-key" => host_key = PathBuf::from(value(&mut args, &arg)?),
+            "--authorized-keys" => authorized_keys = PathBuf::from(value(&mut args, &arg)?),
+            "--lobby" => lobby = Some(()),
+            _ => host_option(&mut host, &arg, &mut args)?,
+        }
+    }
+
+    Ok(Command::Serve(ServeOptions {
+        addr,
+        host_key,
+        authorized_keys,
+        lobby: lobby.map(|_| host),
+    }))
+}
+
+impl Default for HostOptions {
+    fn default() -> Self {
+        Self {
+            port: DEFAULT_PORT,
+            auto_start: None,
+            rules: BattleRules::default(),
+            name: default_name(),
+            netcode: Netcode::Lockstep,
+            spectator_delay: SPECTATOR_DELAY_SECONDS,
+            headless: false,
+        }
+    }
+}
+
+fn host_option(
+    options: &mut HostOptions,
+    arg: &str,
+    args: &mut impl Iterator<Item = String>,
+) -> Result<(), String> {
+    match arg {
+        "--port" => options.port = number(args, arg, 1..=65535)? as u16,
+        "--players" => options.auto_start = Some(number(args, arg, 2..=4)?),
+        "--name" => options.name = value(args, arg)?,
+        "--netcode" => {
+            let mode = value(args, arg)?;
+            options.netcode =
+                Netcode::parse(&mode).ok_or_else(|| format!("unknown netcode: {}", mode))?;
+        }
+        "--delay" => options.spectator_delay = number(args, arg, 0..=60)? as u64,
+        "--headless" => options.headless = true,
+        _ => rule_option(&mut options.rules, arg, args)?,
+    }
+    Ok(())
+}
+
+fn rule_option(
+    rules: &mut BattleRules,
+    arg: &str,
+    args: &mut impl Iterator<Item = String>,
+) -> Result<(), String> {
+    match arg {
+        "--rounds" => rules.best_of = number(args, arg, 1..=99)? as u32,
+        "--teams" => rules.teams = true,
+        "--bombs" => rules.start_bombs = number(args, arg, 1..=8)?,
+        "--fire" => rules.start_fire = number(args, arg, 1..=8)?,
+        "--items" => rules.item_chance = number(args, arg, 0..=100)? as u32,
+        "--sudden-death" => {
+            rules.sudden_death = true;
+            let (min, max) = (*SUDDEN_DEATH_AFTER.start(), *SUDDEN_DEATH_AFTER.end());
+            rules.sudden_death_after = number(args, arg, min as usize..=max as usize)? as u32;
+        }
+        "--no-sudden-death" => rules.sudden_death = false,
+        "--stage" => rules.stage = number(args, arg, 1..=battle_stages().len())? - 1,
+        _ => return Err(format!("unknown option: {}", arg)),
+    }
+    Ok(())
+}
+
+fn parse_join(mut args: impl Iterator<Item = String>, spectate: bool) -> Result<Command, String> {
+    let mut addr = None;
+    let mut name = default_name();
+    let mut bot = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--name" => name = value(&mut args, &arg)?,
+            "--bot" if !spectate => {
+                let level = value(&mut args, &arg)?;
+                bot = Some(
+                    Difficulty::parse(&level).ok_or_else(|| format!("unknown difficulty: {}", level))?,
+                );
+            }
+            _ if addr.is_none() && !arg.starts_with('-') => addr = Some(arg),
+            _ => return Err(format!("unknown option: {}", arg)),
+        }
+    }
+
+    let addr = addr.ok_or("the host address is missing")?;
+    let addr = if addr.contains(':') {
+        addr
+    } else {
+        format!("{}:{}", addr, DEFAULT_PORT)
+    };
+    Ok(Command::Join(JoinOptions {
+        addr,
+        name,
+        bot,
+        spectate,
+    }))
+}
+
+fn parse_netsim(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut config = LoopbackConfig {
+        players: 2,
+        frames: 1200,
+        link: LinkConfig {
+            latency_ms: 80,
+            jitter_ms: 20,
+            loss: 0.1,
+        },
+        seed: rand::random(),
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--players" => config.players = number(&mut args, &arg, 2..=4)?,
+            "--frames" => config.frames = number(&mut args, &arg, 1..=1_000_000)? as u64,
+            "--latency" => config.link.latency_ms = number(&mut args, &arg, 0..=5000)? as u32,
+            "--jitter" => config.link.jitter_ms = number(&mut args, &arg, 0..=5000)? as u32,
+            "--loss" => config.link.loss = number(&mut args, &arg, 0..=90)? as f32 / 100.0,
+            "--seed" => {
+                config.seed = value(&mut args, &arg)?
+                    .parse()
+                    .map_err(|_| format!("{} must be a number", arg))?;
+            }
+            _ => return Err(format!("unknown option: {}", arg)),
+        }
+    }
+
+    Ok(Command::Netsim(config))
+}
+
+fn default_name() -> String {
+    std::env::var("USER").unwrap_or_else(|_| "player".to_string())
+}
+
+fn number(
+    args: &mut impl Iterator<Item = String>,
+    flag: &str,
+    range: std::ops::RangeInclusive<usize>,
+) -> Result<usize, String> {
+    value(args, flag)?
+        .parse()
+        .ok()
+        .filter(|n| range.contains(n))
+        .ok_or_else(|| format!("{} must be between {} and {}", flag, range.start(), range.end()))
+}
+
+fn value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, String> {
+    args.next().ok_or_else(|| format!("{} needs a value", flag))
+}
@@ -0,0 +1,413 @@
+use crate::game::mode::SUDDEN_DEATH_AFTER;
+use crate::render::{camera::Zoom, glyphs::GlyphSet};
+
+/// Lines kept in the command history.
+const HISTORY_LIMIT: usize = 50;
+
+const COMMANDS: [&str; 7] = ["stage", "restart", "seed", "set", "theme", "save", "quit"];
+const SETTINGS: [&str; 14] = [
+    "tickrate=",
+    "glyphs=",
+    "zoom=",
+    "minimap",
+    "nominimap",
+    "danger",
+    "nodanger",
+    "labels",
+    "nolabels",
+    "reducedmotion",
+    "noreducedmotion",
+    "suddendeath=",
+    "suddendeath",
+    "nosuddendeath",
+];
+/// Slowest and fastest tick rates `:set tickrate` accepts.
+pub const TICK_RATES: std::ops::RangeInclusive<u32> = 5..=60;
+
+/// What a `:` line asks for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Stage(String),
+    Restart,
+    /// Restarts with this seed, or shows the current one.
+    Seed(Option<u64>),
+    Set(Setting),
+    /// A theme, or a glyph set by name.
+    Theme(String),
+    Save,
+    Quit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Setting {
+    TickRate(u32),
+    Glyphs(GlyphSet),
+    Zoom(Zoom),
+    Minimap(bool),
+    Danger(bool),
+    Labels(bool),
+    ReducedMotion(bool),
+    SuddenDeath(bool),
+    /// Turns sudden death on, starting this many seconds into a round.
+    SuddenDeathAfter(u32),
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let mut words = line.split_whitespace();
+        let Some(name) = words.next() else {
+            return Err("コマンドがありません".to_string());
+        };
+        let argument = words.next();
+        if words.next().is_some() {
+            return Err(format!("引数が多すぎます: {}", line.trim()));
+        }
+        let required = |what: &str| argument.ok_or_else(|| format!(":{} には{}が必要です", name, what));
+
+        match name {
+            "stage" => Ok(Command::Stage(required("ステージ名")?.to_string())),
+            "restart" => Ok(Command::Restart),
+            "seed" => match argument {
+                None => Ok(Command::Seed(None)),
+                Some(seed) => seed
+                    .parse()
+                    .map(|seed| Command::Seed(Some(seed)))
+                    .map_err(|_| format!("シード値は数値で指定してください: {}", seed)),
+            },
+            "set" => Setting::parse(required("設定")?).map(Command::Set),
+            "theme" => Ok(Command::Theme(required("テーマ名")?.to_string())),
+            "save" | "w" => Ok(Command::Save),
+            "q" | "q!" | "quit" | "qa" => Ok(Command::Quit),
+            _ => Err(format!("不明なコマンド: {}", name)),
+        }
+    }
+}
+
+impl Setting {
+    fn parse(text: &str) -> Result<Self, String> {
+        if let Some((name, value)) = text.split_once('=') {
+            return match name {
+                "tickrate" => value
+                    .parse()
+                    .ok()
+                    .filter(|rate| TICK_RATES.contains(rate))
+                    .map(Setting::TickRate)
+                    .ok_or_else(|| {
+                        format!(
+                            "tickrate は {}〜{} で指定してください",
+                            TICK_RATES.start(),
+                            TICK_RATES.end()
+                        )
+                    }),
+                "glyphs" => GlyphSet::parse(value)
+                    .map(Setting::Glyphs)
+                    .ok_or_else(|| format!("不明な文字セット: {} (ascii, unicode, emoji)", value)),
+                "zoom" => match value {
+                    "near" => Ok(Setting::Zoom(Zoom::Near)),
+                    "far" => Ok(Setting::Zoom(Zoom::Far)),
+                    _ => Err(format!("不明なズーム: {} (near, far)", value)),
+                },
+                "suddendeath" => value
+                    .parse()
+                    .ok()
+                    .filter(|seconds| SUDDEN_DEATH_AFTER.contains(seconds))
+                    .map(Setting::SuddenDeathAfter)
+                    .ok_or_else(|| {
+                        format!(
+                            "suddendeath は {}〜{} 秒で指定してください",
+                            SUDDEN_DEATH_AFTER.start(),
+                            SUDDEN_DEATH_AFTER.end()
+                        )
+                    }),
+                _ => Err(format!("不明な設定: {}", name)),
+            };
+        }
+        let (name, on) = match text.strip_prefix("no") {
+            Some(name) => (name, false),
+            None => (text, true),
+        };
+        match name {
+            "minimap" => Ok(Setting::Minimap(on)),
+            "danger" => Ok(Setting::Danger(on)),
+            "labels" => Ok(Setting::Labels(on)),
+            "reducedmotion" => Ok(Setting::ReducedMotion(on)),
+            "suddendeath" => Ok(Setting::SuddenDeath(on)),
+            _ => Err(format!("不明な設定: {}", text)),
+        }
+    }
+}
+
+/// Names offered by Tab after a command that takes one.
+pub struct Completions {
+    pub stages: Vec<String>,
+    pub themes: Vec<String>,
+}
+
+/// The `:` prompt being typed, with its history and Tab completion.
+#[derive(Debug, Default)]
+pub struct CommandLine {
+    pub input: String,
+    history: Vec<String>,
+    /// History line shown by Up/Down, counted back from the newest.
+    browsing: Option<usize>,
+    /// What was typed before browsing the history.
+    draft: String,
+    /// Candidates Tab cycles through, the one shown, and where the
+    /// completed word starts in `input`.
+    completion: Option<(Vec<String>, usize, usize)>,
+}
+
+impl CommandLine {
+    /// Starts a fresh line; the history stays.
+    pub fn open(&mut self) {
+        self.input.clear();
+        self.browsing = None;
+        self.completion = None;
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.input.push(c);
+        self.completion = None;
+    }
+
+    /// Deletes the last character. Returns false when there was nothing
+    /// left to delete, which closes the prompt as in Vim.
+    pub fn backspace(&mut self) -> bool {
+        self.completion = None;
+        self.input.pop().is_some()
+    }
+
+    /// Takes the typed line and remembers it.
+    pub fn submit(&mut self) -> String {
+        let line = std::mem::take(&mut self.input);
+        if !line.trim().is_empty() && self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+            if self.history.len() > HISTORY_LIMIT {
+                self.history.remove(0);
+            }
+        }
+        self.browsing = None;
+        line
+    }
+
+    pub fn history_back(&mut self) {
+        let back = self.browsing.map_or(0, |back| back + 1);
+        if back < self.history.len() {
+            if self.browsing.is_none() {
+                self.draft = self.input.clone();
+            }
+            self.browsing = Some(back);
+            self.input = self.history[self.history.len() - 1 - back].clone();
+        }
+        self.completion = None;
+    }
+
+    pub fn history_forward(&mut self) {
+        match self.browsing {
+            Some(0) => {
+                self.browsing = None;
+                self.input = std::mem::take(&mut self.draft);
+            }
+            Some(back) => {
+                self.browsing = Some(back - 1);
+                self.input = self.history[self.history.len() - back].clone();
+            }
+            None => {}
+        }
+        self.completion = None;
+    }
+
+    /// Completes the word under the cursor, or moves on to the next
+    /// candidate when Tab is pressed again.
+    pub fn complete(&mut self, names: &Completions) {
+        if let Some((candidates, shown, start)) = &mut self.completion {
+            *shown = (*shown + 1) % candidates.len();
+            self.input.truncate(*start);
+            self.input.push_str(&candidates[*shown]);
+            return;
+        }
+
+        let start = self.input.rfind(' ').map_or(0, |space| space + 1);
+        let word = &self.input[start..];
+        let candidates: Vec<String> = if start == 0 {
+            COMMANDS.iter().map(|name| name.to_string()).collect()
+        } else {
+            match self.input.split_whitespace().next() {
+                Some("stage") => names.stages.clone(),
+                Some("theme") => names
+                    .themes
+                    .iter()
+                    .cloned()
+                    .chain(["ascii", "unicode", "emoji"].map(String::from))
+                    .collect(),
+                Some("set") => match word.split_once('=') {
+                    Some(("glyphs", _)) => ["ascii", "unicode", "emoji"].map(|v| format!("glyphs={}", v)).to_vec(),
+                    Some(("zoom", _)) => ["near", "far"].map(|v| format!("zoom={}", v)).to_vec(),
+                    _ => SETTINGS.iter().map(|name| name.to_string()).collect(),
+                },
+                _ => Vec::new(),
+            }
+        };
+        let candidates: Vec<String> = candidates
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(word))
+            .collect();
+        if let Some(first) = candidates.first() {
+            self.input.truncate(start);
+            self.input.push_str(first);
+            self.completion = Some((candidates, 0, start));
+        }
+    }
+
+    /// The candidates while Tab is cycling, with the shown one's index.
+    pub fn candidates(&self) -> Option<(&[String], usize)> {
+        self.completion
+            .as_ref()
+            .filter(|(candidates, ..)| candidates.len() > 1)
+            .map(|(candidates, shown, _)| (candidates.as_slice(), *shown))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names() -> Completions {
+        Completions {
+            stages: vec!["stage1".to_string(), "stage2".to_string(), "battle1".to_string()],
+            themes: vec!["classic".to_string(), "dusk".to_string()],
+        }
+    }
+
+    fn typed(text: &str) -> CommandLine {
+        let mut line = CommandLine::default();
+        text.chars().for_each(|c| line.push(c));
+        line
+    }
+
+    #[test]
+    fn commands_and_their_arguments() {
+        assert_eq!(Command::parse("stage stage2"), Ok(Command::Stage("stage2".to_string())));
+        assert_eq!(Command::parse("  restart "), Ok(Command::Restart));
+        assert_eq!(Command::parse("seed"), Ok(Command::Seed(None)));
+        assert_eq!(Command::parse("seed 42"), Ok(Command::Seed(Some(42))));
+        assert_eq!(Command::parse("w"), Ok(Command::Save));
+        assert_eq!(Command::parse("q!"), Ok(Command::Quit));
+        assert_eq!(Command::parse("theme dusk"), Ok(Command::Theme("dusk".to_string())));
+    }
+
+    #[test]
+    fn bad_lines_are_errors() {
+        assert!(Command::parse("").is_err());
+        assert!(Command::parse("stage").is_err());
+        assert!(Command::parse("seed abc").is_err());
+        assert!(Command::parse("seed 1 2").is_err());
+        assert!(Command::parse("frobnicate").is_err());
+    }
+
+    #[test]
+    fn settings_with_values() {
+        assert_eq!(Setting::parse("tickrate=30"), Ok(Setting::TickRate(30)));
+        assert!(Setting::parse("tickrate=4").is_err());
+        assert!(Setting::parse("tickrate=61").is_err());
+        assert!(Setting::parse("tickrate=fast").is_err());
+        assert_eq!(Setting::parse("suddendeath=90"), Ok(Setting::SuddenDeathAfter(90)));
+        assert!(Setting::parse("suddendeath=5").is_err());
+        assert_eq!(Setting::parse("nosuddendeath"), Ok(Setting::SuddenDeath(false)));
+        assert_eq!(Setting::parse("glyphs=ASCII"), Ok(Setting::Glyphs(GlyphSet::Ascii)));
+        assert!(Setting::parse("glyphs=braille").is_err());
+        assert_eq!(Setting::parse("zoom=far"), Ok(Setting::Zoom(Zoom::Far)));
+        assert!(Setting::parse("volume=3").is_err());
+    }
+
+    #[test]
+    fn switches_turn_off_with_no() {
+        assert_eq!(Setting::parse("minimap"), Ok(Setting::Minimap(true)));
+        assert_eq!(Setting::parse("nominimap"), Ok(Setting::Minimap(false)));
+        assert_eq!(Setting::parse("nodanger"), Ok(Setting::Danger(false)));
+        assert_eq!(Setting::parse("noreducedmotion"), Ok(Setting::ReducedMotion(false)));
+        assert!(Setting::parse("nothing").is_err());
+    }
+
+    #[test]
+    fn history_is_browsed_newest_first_and_keeps_the_draft() {
+        let mut line = CommandLine::default();
+        for command in ["seed 1", "restart", "restart", "  "] {
+            line.open();
+            command.chars().for_each(|c| line.push(c));
+            line.submit();
+        }
+        line.open();
+        "sta".chars().for_each(|c| line.push(c));
+
+        line.history_back();
+        assert_eq!(line.input, "restart");
+        line.history_back();
+        assert_eq!(line.input, "seed 1");
+        line.history_back();
+        assert_eq!(line.input, "seed 1", "blank and repeated lines are not kept");
+        line.history_forward();
+        assert_eq!(line.input, "restart");
+        line.history_forward();
+        assert_eq!(line.input, "sta");
+    }
+
+    #[test]
+    fn history_keeps_only_the_newest_lines() {
+        let mut line = CommandLine::default();
+        for seed in 0..HISTORY_LIMIT + 5 {
+            format!("seed {}", seed).chars().for_each(|c| line.push(c));
+            line.submit();
+        }
+        for _ in 0..HISTORY_LIMIT + 5 {
+            line.history_back();
+        }
+        assert_eq!(line.input, "seed 5");
+    }
+
+    #[test]
+    fn tab_completes_commands_then_cycles() {
+        let mut line = typed("s");
+        line.complete(&names());
+        assert_eq!(line.input, "stage");
+        assert_eq!(line.candidates(), Some((&["stage", "seed", "set", "save"].map(String::from)[..], 0)));
+        line.complete(&names());
+        assert_eq!(line.input, "seed");
+        for _ in 0..3 {
+            line.complete(&names());
+        }
+        assert_eq!(line.input, "stage", "cycling wraps around");
+    }
+
+    #[test]
+    fn tab_completes_arguments_by_command() {
+        let mut line = typed("stage st");
+        line.complete(&names());
+        assert_eq!(line.input, "stage stage1");
+        line.complete(&names());
+        assert_eq!(line.input, "stage stage2");
+
+        let mut line = typed("theme e");
+        line.complete(&names());
+        assert_eq!(line.input, "theme emoji");
+        assert_eq!(line.candidates(), None, "a single match is not listed");
+
+        let mut line = typed("set zoom=");
+        line.complete(&names());
+        assert_eq!(line.input, "set zoom=near");
+
+        let mut line = typed("seed ");
+        line.complete(&names());
+        assert_eq!(line.input, "seed ");
+    }
+
+    #[test]
+    fn typing_after_tab_starts_a_new_completion() {
+        let mut line = typed("set no");
+        line.complete(&names());
+        assert_eq!(line.input, "set nominimap");
+        line.backspace();
+        line.complete(&names());
+        assert_eq!(line.input, "set nominimap", "completes afresh instead of cycling");
+    }
+}
@@ -0,0 +1,152 @@
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::keys::KeyMap;
+use crate::render::{
+    accessibility::{Accessibility, Preset},
+    glyphs::GlyphSet,
+    theme::Theme,
+};
+
+/// `$XDG_CONFIG_HOME/bmtui`, falling back to `~/.config/bmtui`.
+pub fn config_dir() -> PathBuf {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("bmtui")
+}
+
+/// `<dir>/<name>.ron` for a stage or theme name typed by a player. Names
+/// that could reach outside `dir` are refused, since SSH guests type them
+/// too.
+pub fn named_file(dir: PathBuf, name: &str) -> Result<PathBuf, String> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.contains("..") {
+        return Err(format!("invalid name: {}", name));
+    }
+    Ok(dir.join(format!("{}.ron", name)))
+}
+
+/// User preferences from `config.ron` in the config directory. Everything
+/// is optional; a missing file means defaults throughout.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Board glyphs; detected from the terminal when unset.
+    pub glyphs: Option<GlyphSet>,
+    /// Name of a built-in theme or of `themes/<name>.ron`; classic if unset.
+    pub theme: Option<String>,
+    /// Accessibility preset, which also picks a theme and glyphs.
+    pub accessibility: Option<Preset>,
+    /// Keep the board still: no blinking fuses or flashing blasts.
+    pub reduced_motion: bool,
+    /// Key bindings; anything left out keeps its default.
+    #[serde(skip_serializing_if = "KeyMap::is_default")]
+    pub keys: KeyMap,
+}
+
+impl Config {
+    pub fn path() -> PathBuf {
+        config_dir().join("config.ron")
+    }
+
+    /// Reads the config file. A broken file is reported and ignored rather
+    /// than keeping the game from starting.
+    pub fn load() -> Self {
+        Self::read().unwrap_or_else(|err| {
+            eprintln!("Warning: ignoring {}", err);
+            Self::default()
+        })
+    }
+
+    /// The config file, defaults if there is none, or why it can't be read.
+    pub fn read() -> Result<Self, String> {
+        let path = Self::path();
+        let Ok(text) = fs::read_to_string(&path) else {
+            return Ok(Self::default());
+        };
+        ron::from_str(&text).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path();
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| err.to_string())?;
+        fs::create_dir_all(config_dir())
+            .and_then(|_| fs::write(&path, text + "\n"))
+            .map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    pub fn glyph_set(&self) -> GlyphSet {
+        self.glyphs
+            .or(self.accessibility.map(Preset::glyph_set))
+            .unwrap_or_else(GlyphSet::detect_local)
+    }
+
+    /// The configured theme, falling back to classic with a warning when it
+    /// cannot be loaded.
+    pub fn theme(&self) -> Theme {
+        let preset = self.accessibility.map(|preset| preset.theme().to_string());
+        let Some(name) = self.theme.as_ref().or(preset.as_ref()) else {
+            return Theme::classic();
+        };
+        Theme::load(name).unwrap_or_else(|err| {
+            eprintln!("Warning: {}", err);
+            Theme::classic()
+        })
+    }
+
+    pub fn accessibility(&self) -> Accessibility {
+        Accessibility {
+            labels: self.accessibility.is_some(),
+            reduced_motion: self.reduced_motion,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_stay_inside_their_directory() {
+        let dir = PathBuf::from("themes");
+        assert_eq!(named_file(dir.clone(), "dusk"), Ok(PathBuf::from("themes/dusk.ron")));
+        for name in ["", "../config", "..", "a/b", "/etc/passwd", "a\\b"] {
+            assert!(named_file(dir.clone(), name).is_err(), "{:?} was accepted", name);
+        }
+    }
+
+    #[test]
+    fn a_preset_picks_its_theme_glyphs_and_labels() {
+        let config = Config {
+            accessibility: Some(Preset::Colorblind),
+            ..Config::default()
+        };
+
+        assert_eq!(config.theme().name, "colorblind");
+        assert_eq!(config.glyph_set(), GlyphSet::Ascii);
+        assert!(config.accessibility().labels);
+        assert!(!config.accessibility().reduced_motion);
+    }
+
+    #[test]
+    fn a_chosen_theme_and_glyph_set_beat_the_preset() {
+        let config = Config {
+            accessibility: Some(Preset::HighContrast),
+            theme: Some("dark".to_string()),
+            glyphs: Some(GlyphSet::Unicode),
+            reduced_motion: true,
+            ..Config::default()
+        };
+
+        assert_eq!(config.theme().name, "dark");
+        assert_eq!(config.glyph_set(), GlyphSet::Unicode);
+        assert_eq!(
+            config.accessibility(),
+            Accessibility { labels: true, reduced_motion: true }
+        );
+    }
+}
@@ -16,6 +16,13 @@ impl EventHandler {
         }
     }
 
+    /// Ticks every `period` from now on, if that isn't already the pace.
+    pub fn set_tick_period(&mut self, period: Duration) {
+        if self.tick_interval.period() != period {
+            self.tick_interval = interval(period);
+        }
+    }
+
     pub async fn next(&mut self) -> Option<Event> {
         tokio::select! {
             _ = self.tick_interval.tick() => {
@@ -1,3 +1,5 @@
+use serde::{Deserialize, Serialize};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Position {
     pub x: usize,
@@ -10,7 +12,7 @@ impl Position {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Direction {
     Up,
     Down,
@@ -19,7 +21,23 @@ pub enum Direction {
 }
 
 impl Direction {
-    pub fn to_delta(&self) -> (i32, i32) {
+    pub const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+
+    pub fn opposite(self) -> Self {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+
+    pub fn to_delta(self) -> (i32, i32) {
         match self {
             Direction::Up => (0, -1),
             Direction::Down => (0, 1),
@@ -33,6 +51,21 @@ impl Direction {
 pub struct PlayerStats {
     pub max_bombs: usize,
     pub bomb_range: usize,
+    /// Tiles a bomber walks per second, however fast its keys repeat.
+    pub speed: f32,
+    /// Seconds until the next step. Runs slightly negative to carry the
+    /// rest of a tick over, so the pace holds at any tick rate.
+    pub step_cooldown: f32,
+    /// Walking into a bomb sends it sliding.
+    pub kick: bool,
+    /// New bombs wait for a detonate instead of a fuse.
+    pub remote: bool,
+    /// Seconds left in which blasts and enemies can't hurt the bomber.
+    pub invulnerable: f32,
+}
+
+impl PlayerStats {
+    pub const BASE_SPEED: f32 = 6.0;
 }
 
 impl Default for PlayerStats {
@@ -40,6 +73,11 @@ impl Default for PlayerStats {
         Self {
             max_bombs: 1,
             bomb_range: 1,
+            speed: Self::BASE_SPEED,
+            step_cooldown: 0.0,
+            kick: false,
+            remote: false,
+            invulnerable: 0.0,
         }
     }
 }
@@ -49,14 +87,26 @@ pub struct BombData {
     pub timer: f32,
     pub range: usize,
     pub owner_id: usize,
+    /// The fuse doesn't burn until the owner detonates.
+    pub remote: bool,
+    /// Where a kicked bomb is heading.
+    pub sliding: Option<Direction>,
+    /// Seconds until a sliding bomb moves on.
+    pub slide_cooldown: f32,
 }
 
 impl BombData {
+    /// Seconds a kicked bomb takes per tile.
+    pub const SLIDE_INTERVAL: f32 = 0.1;
+
     pub fn new(range: usize, owner_id: usize) -> Self {
         Self {
             timer: 3.0,
             range,
             owner_id,
+            remote: false,
+            sliding: None,
+            slide_cooldown: 0.0,
         }
     }
 }
@@ -64,11 +114,16 @@ impl BombData {
 #[derive(Debug, Clone)]
 pub struct ExplosionData {
     pub timer: f32,
+    /// Bomber whose bomb caused the blast, credited with what it destroys.
+    pub owner_id: Option<usize>,
 }
 
 impl ExplosionData {
-    pub fn new() -> Self {
-        Self { timer: 0.5 }
+    pub fn new(owner_id: Option<usize>) -> Self {
+        Self {
+            timer: 0.5,
+            owner_id,
+        }
     }
 }
 
@@ -76,13 +131,138 @@ impl ExplosionData {
 pub enum ItemType {
     Fire,
     Bomb,
+    Kick,
+    Remote,
 }
 
 impl ItemType {
-    pub fn to_char(&self) -> &str {
+    /// The name shown in toasts and the message log.
+    pub fn name(self) -> &'static str {
+        match self {
+            ItemType::Fire => "Fire",
+            ItemType::Bomb => "Bomb",
+            ItemType::Kick => "Kick",
+            ItemType::Remote => "Remote",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EnemyKind {
+    Walker,
+    Chaser,
+    Ghost,
+    Tank,
+    Splitter,
+    Fragment,
+}
+
+impl EnemyKind {
+    pub fn from_char(ch: char) -> Option<Self> {
+        match ch {
+            'E' => Some(EnemyKind::Walker),
+            'C' => Some(EnemyKind::Chaser),
+            'G' => Some(EnemyKind::Ghost),
+            'T' => Some(EnemyKind::Tank),
+            'S' => Some(EnemyKind::Splitter),
+            _ => None,
+        }
+    }
+
+    /// The name shown in toasts and the message log.
+    pub fn name(self) -> &'static str {
+        match self {
+            EnemyKind::Walker => "Walker",
+            EnemyKind::Chaser => "Chaser",
+            EnemyKind::Ghost => "Ghost",
+            EnemyKind::Tank => "Tank",
+            EnemyKind::Splitter => "Splitter",
+            EnemyKind::Fragment => "Fragment",
+        }
+    }
+
+    /// Seconds between two steps.
+    pub fn move_interval(self) -> f32 {
+        match self {
+            EnemyKind::Walker => 0.5,
+            EnemyKind::Chaser => 0.2,
+            EnemyKind::Ghost => 0.4,
+            EnemyKind::Tank => 0.6,
+            EnemyKind::Splitter => 0.4,
+            EnemyKind::Fragment => 0.3,
+        }
+    }
+
+    pub fn max_hp(self) -> u32 {
+        match self {
+            EnemyKind::Tank => 3,
+            _ => 1,
+        }
+    }
+
+    pub fn score(self) -> u32 {
+        match self {
+            EnemyKind::Walker => 100,
+            EnemyKind::Chaser => 300,
+            EnemyKind::Ghost => 400,
+            EnemyKind::Tank => 800,
+            EnemyKind::Splitter => 200,
+            EnemyKind::Fragment => 50,
+        }
+    }
+
+    pub fn passes_breakable(self) -> bool {
+        matches!(self, EnemyKind::Ghost)
+    }
+
+    pub fn ai_strategy(self) -> AiStrategy {
+        match self {
+            EnemyKind::Walker | EnemyKind::Fragment => AiStrategy::RandomWalk,
+            EnemyKind::Chaser => AiStrategy::Hunt { sight_radius: 8 },
+            EnemyKind::Ghost => AiStrategy::Hunt { sight_radius: 5 },
+            EnemyKind::Tank | EnemyKind::Splitter => AiStrategy::Patrol,
+        }
+    }
+
+    /// Kind and number of enemies spawned when this one is destroyed.
+    pub fn splits_into(self) -> Option<(EnemyKind, usize)> {
         match self {
-            ItemType::Fire => "🔥",
-            ItemType::Bomb => "💣",
+            EnemyKind::Splitter => Some((EnemyKind::Fragment, 2)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AiStrategy {
+    RandomWalk,
+    /// Follows corridors, turning only at intersections and dead ends.
+    Patrol,
+    /// Paths to the player when within `sight_radius` tiles, patrols otherwise.
+    Hunt { sight_radius: usize },
+}
+
+#[derive(Debug, Clone)]
+pub struct EnemyData {
+    pub kind: EnemyKind,
+    pub strategy: AiStrategy,
+    pub facing: Option<Direction>,
+    pub hp: u32,
+    /// Time left during which further blasts are ignored, so a single
+    /// explosion only costs one hit point.
+    pub hit_cooldown: f32,
+}
+
+impl EnemyData {
+    pub const HIT_COOLDOWN: f32 = 0.6;
+
+    pub fn new(kind: EnemyKind) -> Self {
+        Self {
+            kind,
+            strategy: kind.ai_strategy(),
+            facing: None,
+            hp: kind.max_hp(),
+            hit_cooldown: 0.0,
         }
     }
 }
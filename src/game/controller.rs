@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    components::Direction, entity::Entity, state::GameState, systems::danger::DangerMap,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    Idle,
+    Move(Direction),
+    PlaceBomb,
+    /// Sets off the bomber's remote bombs.
+    Detonate,
+}
+
+/// What a controller gets to see when it is asked for its next action.
+pub struct Observation<'a> {
+    pub state: &'a GameState,
+    pub danger: &'a DangerMap,
+    pub entity_id: usize,
+}
+
+impl<'a> Observation<'a> {
+    pub fn entity(&self) -> Option<&'a Entity> {
+        self.state.entities.iter().find(|e| e.id == self.entity_id)
+    }
+}
+
+/// Drives an entity. Registered per entity with `GameState::set_controller`
+/// and consulted every time the entity is ready to act. Implementors also
+/// need `Clone` so game states, controllers included, can be snapshotted.
+pub trait Controller: ControllerClone + Send {
+    fn decide(&mut self, observation: &Observation) -> Action;
+
+    /// Seconds between decisions; `None` uses the entity's own pace.
+    fn think_interval(&self) -> Option<f32> {
+        None
+    }
+}
+
+pub trait ControllerClone {
+    fn clone_box(&self) -> Box<dyn Controller>;
+}
+
+impl<T: Controller + Clone + 'static> ControllerClone for T {
+    fn clone_box(&self) -> Box<dyn Controller> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn Controller> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+#[derive(Clone)]
+pub struct ControllerSlot {
+    pub controller: Box<dyn Controller>,
+    pub timer: f32,
+}
+
+impl ControllerSlot {
+    pub fn new(controller: Box<dyn Controller>) -> Self {
+        Self {
+            controller,
+            timer: 0.0,
+        }
+    }
+}
+
+/// Seconds between two decisions of the controller driving `entity`.
+pub fn think_interval(entity: &Entity) -> f32 {
+    entity
+        .enemy_kind()
+        .map(|kind| kind.move_interval())
+        .unwrap_or(0.2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Bombs on every third decision.
+    #[derive(Clone)]
+    struct Counter {
+        calls: usize,
+    }
+
+    impl Controller for Counter {
+        fn decide(&mut self, _: &Observation) -> Action {
+            self.calls += 1;
+            if self.calls.is_multiple_of(3) {
+                Action::PlaceBomb
+            } else {
+                Action::Idle
+            }
+        }
+    }
+
+    fn decisions(controller: &mut dyn Controller, state: &GameState, count: usize) -> Vec<Action> {
+        let danger = state.danger_map();
+        let observation = Observation {
+            state,
+            danger: &danger,
+            entity_id: state.player_id,
+        };
+        (0..count).map(|_| controller.decide(&observation)).collect()
+    }
+
+    #[test]
+    fn a_cloned_controller_carries_on_from_where_it_was() {
+        let state = GameState::new(1);
+        let mut original: Box<dyn Controller> = Box::new(Counter { calls: 0 });
+        decisions(original.as_mut(), &state, 2);
+
+        let mut snapshot = original.clone();
+        let ahead = decisions(original.as_mut(), &state, 4);
+        let replayed = decisions(snapshot.as_mut(), &state, 4);
+
+        assert_eq!(ahead, replayed);
+        assert_eq!(ahead[0], Action::PlaceBomb);
+    }
+
+    #[test]
+    fn a_snapshot_keeps_its_controllers_apart_from_the_original() {
+        let mut state = GameState::new(3);
+        for _ in 0..20 {
+            state.tick(0.05);
+        }
+        let mut snapshot = state.clone();
+        for _ in 0..100 {
+            state.tick(0.05);
+        }
+        let ahead = state.checksum();
+
+        for _ in 0..100 {
+            snapshot.tick(0.05);
+        }
+        assert_eq!(snapshot.checksum(), ahead);
+    }
+}
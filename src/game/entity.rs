@@ -1,4 +1,6 @@
-use super::components::{BombData, ExplosionData, ItemType, PlayerStats, Position};
+use super::components::{
+    BombData, EnemyData, EnemyKind, ExplosionData, ItemType, PlayerStats, Position,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EntityType {
@@ -18,6 +20,7 @@ pub struct Entity {
     pub bomb_data: Option<BombData>,
     pub explosion_data: Option<ExplosionData>,
     pub item_type: Option<ItemType>,
+    pub enemy_data: Option<EnemyData>,
     pub is_alive: bool,
 }
 
@@ -31,11 +34,12 @@ impl Entity {
             bomb_data: None,
             explosion_data: None,
             item_type: None,
+            enemy_data: None,
             is_alive: true,
         }
     }
 
-    pub fn new_enemy(id: usize, x: usize, y: usize) -> Self {
+    pub fn new_enemy(id: usize, x: usize, y: usize, kind: EnemyKind) -> Self {
         Self {
             id,
             entity_type: EntityType::Enemy,
@@ -44,6 +48,7 @@ impl Entity {
             bomb_data: None,
             explosion_data: None,
             item_type: None,
+            enemy_data: Some(EnemyData::new(kind)),
             is_alive: true,
         }
     }
@@ -57,19 +62,21 @@ impl Entity {
             bomb_data: Some(BombData::new(range, owner_id)),
             explosion_data: None,
             item_type: None,
+            enemy_data: None,
             is_alive: true,
         }
     }
 
-    pub fn new_explosion(id: usize, x: usize, y: usize) -> Self {
+    pub fn new_explosion(id: usize, x: usize, y: usize, owner_id: Option<usize>) -> Self {
         Self {
             id,
             entity_type: EntityType::Explosion,
             position: Position::new(x, y),
             player_stats: None,
             bomb_data: None,
-            explosion_data: Some(ExplosionData::new()),
+            explosion_data: Some(ExplosionData::new(owner_id)),
             item_type: None,
+            enemy_data: None,
             is_alive: true,
         }
     }
@@ -83,23 +90,25 @@ impl Entity {
             bomb_data: None,
             explosion_data: None,
             item_type: Some(item_type),
+            enemy_data: None,
             is_alive: true,
         }
     }
 
-    pub fn to_char(&self) -> &str {
-        match self.entity_type {
-            EntityType::Player => "🧑",
-            EntityType::Enemy => "👾",
-            EntityType::Bomb => "💣",
-            EntityType::Explosion => "💥",
-            EntityType::Item => {
-                if let Some(item_type) = &self.item_type {
-                    item_type.to_char()
-                } else {
-                    "?"
-                }
-            }
-        }
+    pub fn enemy_kind(&self) -> Option<EnemyKind> {
+        self.enemy_data.as_ref().map(|e| e.kind)
+    }
+
+    pub fn passes_breakable(&self) -> bool {
+        self.enemy_kind()
+            .map(|kind| kind.passes_breakable())
+            .unwrap_or(false)
+    }
+
+    /// Whether a bomber is still in its shield after coming back.
+    pub fn is_shielded(&self) -> bool {
+        self.player_stats
+            .as_ref()
+            .is_some_and(|stats| stats.invulnerable > 0.0)
     }
 }
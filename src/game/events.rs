@@ -0,0 +1,25 @@
+use super::components::{EnemyKind, ItemType};
+
+/// Seconds an event is kept on the state after it happened. Anything that
+/// reads events looks at least this often, and keeping them a while lets a
+/// rolled back and replayed state still report what happened in between.
+pub const EVENT_MEMORY: f32 = 1.0;
+
+/// Something worth telling the players about, stamped with the round time
+/// it happened at. Bombers are named by entity id.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GameEvent {
+    pub at: f32,
+    pub kind: EventKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventKind {
+    ItemCollected { player: usize, item: ItemType },
+    EnemyDefeated { kind: EnemyKind, score: u32, by: Option<usize> },
+    /// A bomb set off `bombs` bombs in all, itself included.
+    Chain { bombs: usize, by: usize },
+    BomberDefeated { victim: usize, by: Option<usize> },
+    /// The story hero went down and came back, with `left` lives to go.
+    LifeLost { left: u32 },
+}
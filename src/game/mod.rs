@@ -1,5 +1,8 @@
 pub mod components;
+pub mod controller;
 pub mod entity;
+pub mod events;
+pub mod mode;
 pub mod stage;
 pub mod state;
 pub mod systems;
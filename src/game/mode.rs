@@ -0,0 +1,245 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    stage::{loader::battle_stages, StageConfig},
+    state::GameState,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameMode {
+    Story,
+    Battle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "easy" => Some(Difficulty::Easy),
+            "normal" => Some(Difficulty::Normal),
+            "hard" => Some(Difficulty::Hard),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BomberSlot {
+    Human,
+    Cpu(Difficulty),
+}
+
+/// Seconds into a round that sudden death may be set to start at.
+pub const SUDDEN_DEATH_AFTER: std::ops::RangeInclusive<u32> = 10..=300;
+
+/// How a battle match is played, chosen on the settings screen and shared
+/// with every peer in networked play.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BattleRules {
+    /// P1 and P3 against P2 and P4 instead of everyone for themselves.
+    pub teams: bool,
+    pub best_of: u32,
+    pub start_bombs: usize,
+    pub start_fire: usize,
+    /// Percentage of destroyed blocks that leave an item behind.
+    pub item_chance: u32,
+    /// Walls close in from the edges once a round runs long.
+    pub sudden_death: bool,
+    /// Seconds into a round before sudden death starts.
+    pub sudden_death_after: u32,
+    /// Index into `battle_stages()`.
+    pub stage: usize,
+}
+
+impl Default for BattleRules {
+    fn default() -> Self {
+        Self {
+            teams: false,
+            best_of: 3,
+            start_bombs: 1,
+            start_fire: 1,
+            item_chance: 30,
+            sudden_death: true,
+            sudden_death_after: 60,
+            stage: 0,
+        }
+    }
+}
+
+/// One line of the match settings screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleField {
+    Teams,
+    Rounds,
+    Bombs,
+    Fire,
+    Items,
+    SuddenDeath,
+    SuddenDeathAfter,
+    Stage,
+}
+
+impl RuleField {
+    pub const ALL: [RuleField; 8] = [
+        RuleField::Teams,
+        RuleField::Rounds,
+        RuleField::Bombs,
+        RuleField::Fire,
+        RuleField::Items,
+        RuleField::SuddenDeath,
+        RuleField::SuddenDeathAfter,
+        RuleField::Stage,
+    ];
+}
+
+impl BattleRules {
+    /// Steps a setting up or down, wrapping toggles and the stage list and
+    /// clamping numbers to sensible bounds.
+    pub fn adjust(&mut self, field: RuleField, step: i32) {
+        let clamp = |value: usize, min: usize, max: usize| {
+            (value as i64 + step as i64).clamp(min as i64, max as i64) as usize
+        };
+        match field {
+            RuleField::Teams => self.teams = !self.teams,
+            RuleField::Rounds => self.best_of = clamp(self.best_of as usize, 1, 99) as u32,
+            RuleField::Bombs => self.start_bombs = clamp(self.start_bombs, 1, 8),
+            RuleField::Fire => self.start_fire = clamp(self.start_fire, 1, 8),
+            RuleField::Items => {
+                self.item_chance = (self.item_chance as i32 + step * 10).clamp(0, 100) as u32
+            }
+            RuleField::SuddenDeath => self.sudden_death = !self.sudden_death,
+            RuleField::SuddenDeathAfter => {
+                self.sudden_death_after = (self.sudden_death_after as i32 + step * 10)
+                    .clamp(*SUDDEN_DEATH_AFTER.start() as i32, *SUDDEN_DEATH_AFTER.end() as i32)
+                    as u32
+            }
+            RuleField::Stage => {
+                let count = battle_stages().len() as i64;
+                self.stage = (self.stage as i64 + step as i64).rem_euclid(count) as usize;
+            }
+        }
+    }
+
+    /// Number of sides that can win a round: teams or single players.
+    pub fn sides(&self, players: usize) -> usize {
+        if self.teams { players.min(2) } else { players }
+    }
+
+    pub fn side_of(&self, slot: usize) -> usize {
+        if self.teams { slot % 2 } else { slot }
+    }
+}
+
+/// Everything needed to (re)start a game.
+#[derive(Debug, Clone)]
+pub enum GameSetup {
+    Story,
+    Battle { slots: Vec<BomberSlot>, rules: BattleRules },
+}
+
+impl GameSetup {
+    /// Builds the initial state. The same setup and seed always produce
+    /// the same game, which networked play relies on.
+    pub fn build(&self, seed: u64) -> GameState {
+        match self {
+            GameSetup::Story => GameState::new(seed),
+            GameSetup::Battle { rules, .. } => {
+                let mut stages = battle_stages();
+                let stage = stages.swap_remove(rules.stage.min(stages.len() - 1));
+                self.build_on(stage, seed)
+            }
+        }
+    }
+
+    /// Like `build`, on a stage of the player's choosing.
+    pub fn build_on(&self, stage: StageConfig, seed: u64) -> GameState {
+        match self {
+            GameSetup::Story => GameState::from_stage(stage, seed),
+            GameSetup::Battle { slots, rules } => GameState::new_battle(stage, slots, rules.clone(), seed),
+        }
+    }
+
+    /// Player slot driven by each local keyboard player, in binding order.
+    pub fn human_slots(&self) -> Vec<usize> {
+        match self {
+            GameSetup::Story => vec![0],
+            GameSetup::Battle { slots, .. } => slots
+                .iter()
+                .enumerate()
+                .filter(|(_, slot)| **slot == BomberSlot::Human)
+                .map(|(index, _)| index)
+                .collect(),
+        }
+    }
+}
+
+/// What one bomber did during a round.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RoundStats {
+    pub kills: u32,
+    pub suicides: u32,
+    pub items: u32,
+}
+
+/// Round wins across a best-of-N battle match, per side (player or team),
+/// and the scoreboard of every finished round.
+#[derive(Debug, Clone)]
+pub struct MatchState {
+    pub best_of: u32,
+    pub round: u32,
+    pub wins: Vec<u32>,
+    pub history: Vec<Vec<RoundStats>>,
+}
+
+impl MatchState {
+    pub fn new(best_of: u32, sides: usize) -> Self {
+        Self {
+            best_of,
+            round: 1,
+            wins: vec![0; sides],
+            history: Vec::new(),
+        }
+    }
+
+    /// Each player's stats summed over the rounds played so far.
+    pub fn totals(&self) -> Vec<RoundStats> {
+        let players = self.history.iter().map(|round| round.len()).max().unwrap_or(0);
+        (0..players)
+            .map(|slot| {
+                self.history
+                    .iter()
+                    .filter_map(|round| round.get(slot))
+                    .fold(RoundStats::default(), |total, stats| RoundStats {
+                        kills: total.kills + stats.kills,
+                        suicides: total.suicides + stats.suicides,
+                        items: total.items + stats.items,
+                    })
+            })
+            .collect()
+    }
+
+    pub fn wins_needed(&self) -> u32 {
+        self.best_of / 2 + 1
+    }
+
+    pub fn record_round(&mut self, winner: Option<usize>, stats: &[RoundStats]) {
+        if let Some(wins) = winner.and_then(|side| self.wins.get_mut(side)) {
+            *wins += 1;
+        }
+        self.history.push(stats.to_vec());
+    }
+
+    pub fn next_round(&mut self) {
+        self.round += 1;
+    }
+
+    pub fn champion(&self) -> Option<usize> {
+        self.wins.iter().position(|&wins| wins >= self.wins_needed())
+    }
+}
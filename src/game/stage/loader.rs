@@ -1,4 +1,15 @@
+use std::fs;
+use std::path::PathBuf;
+
 use super::StageConfig;
+use crate::config::{config_dir, named_file};
+
+/// Stage files shipped in `assets/stages`.
+const STAGE_FILES: [(&str, &str); 3] = [
+    ("stage1", include_str!("../../../assets/stages/stage1.ron")),
+    ("stage2", include_str!("../../../assets/stages/stage2.ron")),
+    ("battle1", include_str!("../../../assets/stages/battle1.ron")),
+];
 
 pub fn get_default_stage() -> StageConfig {
     StageConfig {
@@ -18,5 +29,228 @@ pub fn get_default_stage() -> StageConfig {
             "#E  X   X   E #".to_string(),
             "###############".to_string(),
         ],
+        enemies: Vec::new(),
+        sight: None,
+    }
+}
+
+/// Arenas offered on the match settings screen, in menu order.
+pub fn battle_stages() -> Vec<StageConfig> {
+    vec![
+        get_battle_stage(),
+        open_field_stage(),
+        fortress_stage(),
+        grand_arena_stage(),
+        catacombs_stage(),
+    ]
+}
+
+pub fn get_battle_stage() -> StageConfig {
+    StageConfig {
+        name: "Battle Arena".to_string(),
+        width: 15,
+        height: 11,
+        layout: vec![
+            "###############".to_string(),
+            "#1 XXXXXXXXX 2#".to_string(),
+            "# #X#X#X#X#X# #".to_string(),
+            "#XXXXXXXXXXXXX#".to_string(),
+            "#X#X#X#X#X#X#X#".to_string(),
+            "#XXXXXX XXXXXX#".to_string(),
+            "#X#X#X#X#X#X#X#".to_string(),
+            "#XXXXXXXXXXXXX#".to_string(),
+            "# #X#X#X#X#X# #".to_string(),
+            "#3 XXXXXXXXX 4#".to_string(),
+            "###############".to_string(),
+        ],
+        enemies: Vec::new(),
+        sight: None,
+    }
+}
+
+fn open_field_stage() -> StageConfig {
+    StageConfig {
+        name: "Open Field".to_string(),
+        width: 15,
+        height: 11,
+        layout: vec![
+            "###############".to_string(),
+            "#1   X   X   2#".to_string(),
+            "# # # #X# # # #".to_string(),
+            "#  X   X   X  #".to_string(),
+            "# #X# # # #X# #".to_string(),
+            "#X   X   X   X#".to_string(),
+            "# #X# # # #X# #".to_string(),
+            "#  X   X   X  #".to_string(),
+            "# # # #X# # # #".to_string(),
+            "#3   X   X   4#".to_string(),
+            "###############".to_string(),
+        ],
+        enemies: Vec::new(),
+        sight: None,
+    }
+}
+
+fn fortress_stage() -> StageConfig {
+    StageConfig {
+        name: "Fortress".to_string(),
+        width: 15,
+        height: 11,
+        layout: vec![
+            "###############".to_string(),
+            "#1 XXX   XXX 2#".to_string(),
+            "# ##X## ##X## #".to_string(),
+            "#XX#XXX XXX#XX#".to_string(),
+            "#X X #XXX# X X#".to_string(),
+            "#XXXXX # XXXXX#".to_string(),
+            "#X X #XXX# X X#".to_string(),
+            "#XX#XXX XXX#XX#".to_string(),
+            "# ##X## ##X## #".to_string(),
+            "#3 XXX   XXX 4#".to_string(),
+            "###############".to_string(),
+        ],
+        enemies: Vec::new(),
+        sight: None,
+    }
+}
+
+/// Twice the size of the others; bigger than most terminals at full zoom,
+/// so the board scrolls to follow the bombers.
+fn grand_arena_stage() -> StageConfig {
+    StageConfig {
+        name: "Grand Arena".to_string(),
+        width: 31,
+        height: 21,
+        layout: vec![
+            "###############################".to_string(),
+            "#1  XX XXXX XXX  XXXX XXXX   2#".to_string(),
+            "# #X#X# #X#X#X# # #X#X#X#X# # #".to_string(),
+            "# X XXXX XXXX X XX XXXX XXXX  #".to_string(),
+            "#X#X#X#X# #X#X# #X# #X#X#X#X# #".to_string(),
+            "#XXXX XXXX XXXX XXXX XXXX XXXX#".to_string(),
+            "# #X#X#X#X# #X# #X#X# #X#X#X#X#".to_string(),
+            "#X XXXX XXXX XX X XXXX XXXX XX#".to_string(),
+            "#X# #X#X#X#X# # #X#X#X# #X#X#X#".to_string(),
+            "#XXX XXXX XXXX  XXX XXXX XXXX #".to_string(),
+            "# # # # # # # # # # # # # # # #".to_string(),
+            "# XXXX XXXX XXX  XXXX XXXX XXX#".to_string(),
+            "#X#X#X# #X#X#X# # #X#X#X#X# #X#".to_string(),
+            "#XX XXXX XXXX X XX XXXX XXXX X#".to_string(),
+            "#X#X#X#X# #X#X# #X# #X#X#X#X# #".to_string(),
+            "#XXXX XXXX XXXX XXXX XXXX XXXX#".to_string(),
+            "# #X#X#X#X# #X# #X#X# #X#X#X#X#".to_string(),
+            "#  XXXX XXXX XX X XXXX XXXX X #".to_string(),
+            "# # #X#X#X#X# # #X#X#X# #X#X# #".to_string(),
+            "#3   XXXX XXXX  XXX XXXX XX  4#".to_string(),
+            "###############################".to_string(),
+        ],
+        enemies: Vec::new(),
+        sight: None,
+    }
+}
+
+/// A dark maze: bombers see three tiles around themselves, and walls
+/// block the view, so blasts are often the first sign of a rival.
+fn catacombs_stage() -> StageConfig {
+    StageConfig {
+        name: "Catacombs".to_string(),
+        width: 15,
+        height: 11,
+        layout: vec![
+            "###############".to_string(),
+            "#1  X# X #X  2#".to_string(),
+            "# ##X#X#X#X## #".to_string(),
+            "#X   X   X   X#".to_string(),
+            "##X###X#X###X##".to_string(),
+            "#X  X  #  X  X#".to_string(),
+            "##X###X#X###X##".to_string(),
+            "#X   X   X   X#".to_string(),
+            "# ##X#X#X#X## #".to_string(),
+            "#3  X #X #X  4#".to_string(),
+            "###############".to_string(),
+        ],
+        enemies: Vec::new(),
+        sight: Some(3),
+    }
+}
+
+/// How a built-in stage is named on the command line: "Grand Arena" is
+/// `grand-arena`.
+fn slug(name: &str) -> String {
+    name.split_whitespace()
+        .map(|word| word.to_ascii_lowercase())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn built_in_stages() -> Vec<StageConfig> {
+    let mut stages = vec![get_default_stage()];
+    stages.extend(battle_stages());
+    stages
+}
+
+fn user_dir() -> PathBuf {
+    config_dir().join("stages")
+}
+
+/// A built-in stage, a shipped stage file, or `stages/<name>.ron` from
+/// the config directory.
+pub fn load_stage(name: &str) -> Result<StageConfig, String> {
+    if let Some(stage) = built_in_stages().into_iter().find(|stage| slug(&stage.name) == name) {
+        return Ok(stage);
+    }
+    if let Some((_, source)) = STAGE_FILES.iter().find(|(file, _)| *file == name) {
+        return parse_stage(source, name);
+    }
+    let path = named_file(user_dir(), name)?;
+    let text = fs::read_to_string(&path).map_err(|_| {
+        format!("unknown stage: {} (available: {})", name, stage_names().join(", "))
+    })?;
+    parse_stage(&text, &path.display().to_string())
+}
+
+fn parse_stage(source: &str, origin: &str) -> Result<StageConfig, String> {
+    let stage: StageConfig = ron::from_str(source).map_err(|err| format!("{}: {}", origin, err))?;
+    stage.validate().map_err(|err| format!("{}: {}", origin, err))?;
+    Ok(stage)
+}
+
+/// Names `load_stage` accepts: built-in stages, shipped files, then the
+/// config directory.
+pub fn stage_names() -> Vec<String> {
+    let mut names: Vec<String> = built_in_stages().iter().map(|stage| slug(&stage.name)).collect();
+    names.extend(STAGE_FILES.iter().map(|(file, _)| file.to_string()));
+    let mut user: Vec<String> = fs::read_dir(user_dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ron"))
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+        .filter(|name| !names.contains(name))
+        .collect();
+    user.sort();
+    names.extend(user);
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_stage_that_ships_is_valid() {
+        for stage in built_in_stages() {
+            stage.validate().unwrap_or_else(|err| panic!("{}: {}", stage.name, err));
+        }
+        for (name, _) in STAGE_FILES {
+            load_stage(name).unwrap();
+        }
+    }
+
+    #[test]
+    fn stage_names_cannot_leave_the_stage_directory() {
+        assert_eq!(load_stage("../config").unwrap_err(), "invalid name: ../config");
+        assert!(load_stage("/etc/passwd").is_err());
     }
 }
@@ -2,11 +2,133 @@ pub mod loader;
 
 use serde::{Deserialize, Serialize};
 
+use super::components::{AiStrategy, EnemyKind, Position};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StageConfig {
     pub name: String,
     pub width: usize,
     pub height: usize,
     pub layout: Vec<String>,
+    #[serde(default)]
+    pub enemies: Vec<EnemySpawn>,
+    /// Makes the stage dark: bombers see only this many tiles around
+    /// themselves, and whatever bombs and blasts light up.
+    #[serde(default)]
+    pub sight: Option<usize>,
+}
+
+impl StageConfig {
+    /// Bomber spawn points, `1`-`4` in the layout. `P` counts as `1`.
+    pub fn spawn_points(&self) -> Vec<Position> {
+        let mut spawns: Vec<(char, Position)> = Vec::new();
+        for (y, row) in self.layout.iter().enumerate() {
+            for (x, ch) in row.chars().enumerate() {
+                let slot = if ch == 'P' { '1' } else { ch };
+                if ('1'..='4').contains(&slot) && !spawns.iter().any(|(c, _)| *c == slot) {
+                    spawns.push((slot, Position::new(x, y)));
+                }
+            }
+        }
+        spawns.sort_by_key(|(slot, _)| *slot);
+        spawns.into_iter().map(|(_, position)| position).collect()
+    }
+
+    /// Checks what the file format can't: that the layout is a rectangle
+    /// of the declared size with somewhere for a bomber to start, and that
+    /// every listed enemy starts inside the map on a tile it can stand on.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.layout.len() != self.height {
+            return Err(format!("height is {} but the layout has {} rows", self.height, self.layout.len()));
+        }
+        for (y, row) in self.layout.iter().enumerate() {
+            let width = row.chars().count();
+            if width != self.width {
+                return Err(format!("width is {} but row {} is {} wide", self.width, y, width));
+            }
+        }
+        if self.spawn_points().is_empty() {
+            return Err("no bomber spawn (P or 1-4) in the layout".to_string());
+        }
+        for spawn in &self.enemies {
+            let tile = self.layout.get(spawn.y).and_then(|row| row.chars().nth(spawn.x));
+            let blocked = match tile {
+                None => return Err(format!("{} at ({}, {}) is outside the map", spawn.kind.name(), spawn.x, spawn.y)),
+                Some('#') => true,
+                Some('X') => !spawn.kind.passes_breakable(),
+                Some(_) => false,
+            };
+            if blocked {
+                return Err(format!("{} at ({}, {}) starts inside a wall", spawn.kind.name(), spawn.x, spawn.y));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnemySpawn {
+    pub kind: EnemyKind,
+    pub x: usize,
+    pub y: usize,
+    #[serde(default)]
+    pub ai: Option<AiStrategy>,
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stage(enemies: Vec<EnemySpawn>) -> StageConfig {
+        StageConfig {
+            name: "Test".to_string(),
+            width: 5,
+            height: 3,
+            layout: vec!["#####".to_string(), "#P X#".to_string(), "#####".to_string()],
+            enemies,
+            sight: None,
+        }
+    }
+
+    fn spawn(kind: EnemyKind, x: usize, y: usize) -> EnemySpawn {
+        EnemySpawn { kind, x, y, ai: None }
+    }
+
+    #[test]
+    fn the_layout_must_be_a_rectangle_of_the_declared_size() {
+        assert!(stage(Vec::new()).validate().is_ok());
+        let mut ragged = stage(Vec::new());
+        ragged.layout[1].push('#');
+        assert!(ragged.validate().is_err());
+        let mut short = stage(Vec::new());
+        short.layout.pop();
+        assert!(short.validate().is_err());
+        let mut narrow = stage(Vec::new());
+        narrow.width = 4;
+        assert!(narrow.validate().is_err());
+    }
+
+    #[test]
+    fn a_stage_needs_a_bomber_spawn() {
+        let mut empty = stage(Vec::new());
+        empty.layout[1] = "#  X#".to_string();
+        assert!(empty.validate().is_err());
+        empty.layout[1] = "#3 X#".to_string();
+        assert!(empty.validate().is_ok());
+    }
+
+    #[test]
+    fn enemies_must_start_inside_the_map() {
+        assert!(stage(vec![spawn(EnemyKind::Walker, 2, 1)]).validate().is_ok());
+        assert!(stage(vec![spawn(EnemyKind::Walker, 9, 1)]).validate().is_err());
+        assert!(stage(vec![spawn(EnemyKind::Walker, 2, 3)]).validate().is_err());
+    }
+
+    #[test]
+    fn only_ghosts_start_in_breakable_walls() {
+        assert!(stage(vec![spawn(EnemyKind::Walker, 0, 0)]).validate().is_err());
+        assert!(stage(vec![spawn(EnemyKind::Walker, 3, 1)]).validate().is_err());
+        assert!(stage(vec![spawn(EnemyKind::Ghost, 3, 1)]).validate().is_ok());
+        assert!(stage(vec![spawn(EnemyKind::Ghost, 4, 1)]).validate().is_err());
+    }
+}
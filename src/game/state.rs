@@ -1,64 +1,243 @@
+use std::collections::BTreeMap;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
 use super::{
-    components::{Direction, ItemType},
+    components::{BombData, Direction, EnemyKind, ItemType, PlayerStats, Position},
+    controller::{think_interval, Action, Controller, ControllerSlot, Observation},
     entity::{Entity, EntityType},
+    events::{EventKind, GameEvent, EVENT_MEMORY},
+    mode::{BattleRules, BomberSlot, GameMode, RoundStats},
     stage::{loader::get_default_stage, StageConfig},
-    systems::ai::{check_player_enemy_collision, update_enemy_ai},
+    systems::ai::{check_player_enemy_collision, controller_for, update_enemy_cooldowns},
     systems::bomb::{update_bombs, update_explosions},
-    world::World,
+    systems::bomber::BomberController,
+    systems::danger::DangerMap,
+    systems::sudden_death::update_sudden_death,
+    world::{Tile, World},
 };
 
+/// Rounding slack when comparing step cooldowns against zero.
+const STEP_TOLERANCE: f32 = 1e-4;
+
+/// Lives the story hero starts with, counting the first.
+pub const STORY_LIVES: u32 = 3;
+
+/// Seconds a revived hero can't be hurt, to get away from what got it.
+const RESPAWN_SHIELD: f32 = 2.0;
+
+#[derive(Clone)]
 pub struct GameState {
     pub world: World,
     pub entities: Vec<Entity>,
     next_entity_id: usize,
     pub player_id: usize,
-    enemy_move_timer: f32,
+    pub players: Vec<usize>,
+    /// Who plays each player slot, as set up for the round. Unlike the
+    /// controllers, this stays put when a CPU bomber dies.
+    pub slots: Vec<BomberSlot>,
+    pub mode: GameMode,
+    pub score: u32,
+    /// Lives the story hero has left, counting the one being played.
+    pub lives: u32,
+    /// Where the story hero comes back after losing a life.
+    pub spawn: Position,
+    pub rules: BattleRules,
+    /// Kills, suicides and items per player slot this round.
+    pub round_stats: Vec<RoundStats>,
+    /// Seconds since the round started.
+    pub elapsed: f32,
+    /// Sight radius on a dark stage; `None` when the stage is lit.
+    pub sight: Option<usize>,
+    /// Tiles the sudden death walls have filled so far.
+    pub sudden_death_step: usize,
+    /// What happened in the last `EVENT_MEMORY` seconds, oldest first.
+    pub events: Vec<GameEvent>,
+    pub rng: StdRng,
+    controllers: BTreeMap<usize, ControllerSlot>,
 }
 
 impl GameState {
-    pub fn new() -> Self {
-        Self::from_stage(get_default_stage())
+    pub fn new(seed: u64) -> Self {
+        Self::from_stage(get_default_stage(), seed)
     }
 
-    pub fn from_stage(stage: StageConfig) -> Self {
-        let layout: Vec<&str> = stage.layout.iter().map(|s| s.as_str()).collect();
-        let world = World::from_layout(&layout);
-        let mut entities = Vec::new();
-        let mut next_entity_id = 0;
-        let mut player_id = 0;
+    pub fn from_stage(stage: StageConfig, seed: u64) -> Self {
+        let mut state = Self::empty(&stage, GameMode::Story, seed);
 
-        for (y, row) in layout.iter().enumerate() {
+        // Battle stages mark `1`-`4` instead of `P`; the hero takes the first.
+        if let Some(spawn) = stage.spawn_points().first() {
+            state.player_id = state.add_player(spawn.x, spawn.y);
+            state.spawn = Position::new(spawn.x, spawn.y);
+        }
+        for (y, row) in stage.layout.iter().enumerate() {
             for (x, ch) in row.chars().enumerate() {
-                match ch {
-                    'P' => {
-                        player_id = next_entity_id;
-                        entities.push(Entity::new_player(next_entity_id, x, y));
-                        next_entity_id += 1;
-                    }
-                    'E' => {
-                        entities.push(Entity::new_enemy(next_entity_id, x, y));
-                        next_entity_id += 1;
-                    }
-                    _ => {}
+                if let Some(kind) = EnemyKind::from_char(ch) {
+                    state.add_enemy(x, y, kind);
+                }
+            }
+        }
+
+        for spawn in &stage.enemies {
+            let id = state.add_enemy(spawn.x, spawn.y, spawn.kind);
+            if let Some(ai) = spawn.ai {
+                if let Some(data) = state
+                    .entities
+                    .iter_mut()
+                    .find(|e| e.id == id)
+                    .and_then(|e| e.enemy_data.as_mut())
+                {
+                    data.strategy = ai;
                 }
+                let seed = state.rng.random();
+                state.set_controller(id, controller_for(ai, seed));
             }
         }
 
+        state
+    }
+
+    pub fn new_battle(
+        stage: StageConfig,
+        slots: &[BomberSlot],
+        rules: BattleRules,
+        seed: u64,
+    ) -> Self {
+        let mut state = Self::empty(&stage, GameMode::Battle, seed);
+        state.rules = rules;
+        let start = state.starting_stats();
+
+        for (slot, spawn) in slots.iter().zip(stage.spawn_points()) {
+            let id = state.add_player(spawn.x, spawn.y);
+            if let Some(stats) = state
+                .entities
+                .iter_mut()
+                .find(|e| e.id == id)
+                .and_then(|e| e.player_stats.as_mut())
+            {
+                *stats = start.clone();
+            }
+            state.slots.push(*slot);
+            if let BomberSlot::Cpu(difficulty) = slot {
+                let seed = state.rng.random();
+                state.set_controller(id, Box::new(BomberController::new(*difficulty, seed)));
+            }
+        }
+
+        state.player_id = slots
+            .iter()
+            .position(|slot| *slot == BomberSlot::Human)
+            .and_then(|index| state.players.get(index).copied())
+            .or_else(|| state.players.first().copied())
+            .unwrap_or(0);
+
+        state
+    }
+
+    fn empty(stage: &StageConfig, mode: GameMode, seed: u64) -> Self {
+        let layout: Vec<&str> = stage.layout.iter().map(|s| s.as_str()).collect();
         Self {
-            world,
-            entities,
-            next_entity_id,
-            player_id,
-            enemy_move_timer: 0.0,
+            world: World::from_layout(&layout),
+            entities: Vec::new(),
+            next_entity_id: 0,
+            player_id: 0,
+            players: Vec::new(),
+            slots: Vec::new(),
+            mode,
+            score: 0,
+            lives: STORY_LIVES,
+            spawn: Position::new(0, 0),
+            rules: BattleRules::default(),
+            round_stats: Vec::new(),
+            elapsed: 0.0,
+            sudden_death_step: 0,
+            events: Vec::new(),
+            sight: stage.sight,
+            rng: StdRng::seed_from_u64(seed),
+            controllers: BTreeMap::new(),
         }
     }
 
-    pub fn get_player(&self) -> Option<&Entity> {
-        self.entities.iter().find(|e| e.id == self.player_id)
+    pub fn set_controller(&mut self, entity_id: usize, controller: Box<dyn Controller>) {
+        self.controllers.insert(entity_id, ControllerSlot::new(controller));
+    }
+
+    pub fn is_cpu(&self, slot: usize) -> bool {
+        matches!(self.slots.get(slot), Some(BomberSlot::Cpu(_)))
+    }
+
+    pub fn apply_action(&mut self, entity_id: usize, action: Action) -> bool {
+        match action {
+            Action::Idle => false,
+            Action::Move(direction) => {
+                if !self.take_step(entity_id) {
+                    return false;
+                }
+                let moved = self.move_entity(entity_id, direction);
+                if moved
+                    && let Some(enemy) = self
+                        .entities
+                        .iter_mut()
+                        .find(|e| e.id == entity_id)
+                        .and_then(|e| e.enemy_data.as_mut())
+                {
+                    enemy.facing = Some(direction);
+                }
+                moved
+            }
+            Action::PlaceBomb => self.place_bomb(entity_id),
+            Action::Detonate => self.detonate(entity_id),
+        }
+    }
+
+    /// What every bomber starts with, before any power-ups.
+    pub fn starting_stats(&self) -> PlayerStats {
+        match self.mode {
+            GameMode::Story => PlayerStats::default(),
+            GameMode::Battle => PlayerStats {
+                max_bombs: self.rules.start_bombs,
+                bomb_range: self.rules.start_fire,
+                ..PlayerStats::default()
+            },
+        }
+    }
+
+    /// Whether a bomber's speed lets it take a step now. Anything without
+    /// a speed stat steps whenever it is told to.
+    pub fn ready_to_step(&self, entity_id: usize) -> bool {
+        self.entities
+            .iter()
+            .find(|e| e.id == entity_id)
+            .and_then(|e| e.player_stats.as_ref())
+            .is_none_or(|stats| stats.step_cooldown <= STEP_TOLERANCE)
+    }
+
+    /// Starts the wait for the next step if a step may be taken now.
+    fn take_step(&mut self, entity_id: usize) -> bool {
+        if !self.ready_to_step(entity_id) {
+            return false;
+        }
+        if let Some(stats) = self
+            .entities
+            .iter_mut()
+            .find(|e| e.id == entity_id)
+            .and_then(|e| e.player_stats.as_mut())
+        {
+            stats.step_cooldown += 1.0 / stats.speed;
+        }
+        true
     }
 
-    pub fn get_player_mut(&mut self) -> Option<&mut Entity> {
-        self.entities.iter_mut().find(|e| e.id == self.player_id)
+    fn update_player_timers(&mut self, delta_time: f32) {
+        for stats in self.entities.iter_mut().filter_map(|e| e.player_stats.as_mut()) {
+            stats.step_cooldown = (stats.step_cooldown - delta_time).max(-delta_time);
+            stats.invulnerable = (stats.invulnerable - delta_time).max(0.0);
+        }
+    }
+
+    pub fn get_player(&self) -> Option<&Entity> {
+        self.entities.iter().find(|e| e.id == self.player_id)
     }
 
     pub fn move_entity(&mut self, entity_id: usize, direction: Direction) -> bool {
@@ -71,21 +250,56 @@ impl GameState {
             let (dx, dy) = direction.to_delta();
             let new_x = (entity.position.x as i32 + dx) as usize;
             let new_y = (entity.position.y as i32 + dy) as usize;
+            let passes_breakable = entity.passes_breakable();
+            let kicks = entity.player_stats.as_ref().is_some_and(|stats| stats.kick);
 
-            if self.can_move_to(new_x, new_y, entity_id) {
-                if let Some(entity) = self.entities.iter_mut().find(|e| e.id == entity_id) {
-                    entity.position.x = new_x;
-                    entity.position.y = new_y;
-                    return true;
-                }
+            if self.can_move_to(new_x, new_y, entity_id, passes_breakable)
+                && let Some(entity) = self.entities.iter_mut().find(|e| e.id == entity_id)
+            {
+                entity.position.x = new_x;
+                entity.position.y = new_y;
+                return true;
+            }
+            if kicks {
+                self.kick_bomb(new_x, new_y, direction);
             }
         }
 
         false
     }
 
-    fn can_move_to(&self, x: usize, y: usize, entity_id: usize) -> bool {
-        if !self.world.is_walkable(x, y) {
+    /// Sends the bomb on a tile sliding, if there is one there.
+    fn kick_bomb(&mut self, x: usize, y: usize, direction: Direction) -> bool {
+        let Some(bomb) = self
+            .entities
+            .iter_mut()
+            .find(|e| e.entity_type == EntityType::Bomb && e.is_alive && e.position == Position::new(x, y))
+            .and_then(|e| e.bomb_data.as_mut())
+        else {
+            return false;
+        };
+        bomb.sliding = Some(direction);
+        bomb.slide_cooldown = BombData::SLIDE_INTERVAL;
+        true
+    }
+
+    pub fn is_passable(&self, x: usize, y: usize, passes_breakable: bool) -> bool {
+        self.is_tile_passable(x, y, passes_breakable) && !self.has_bomb_at(x, y)
+    }
+
+    pub fn has_bomb_at(&self, x: usize, y: usize) -> bool {
+        self.entities.iter().any(|e| {
+            e.entity_type == EntityType::Bomb && e.is_alive && e.position.x == x && e.position.y == y
+        })
+    }
+
+    fn is_tile_passable(&self, x: usize, y: usize, passes_breakable: bool) -> bool {
+        self.world.is_walkable(x, y)
+            || (passes_breakable && self.world.get_tile(x, y) == Some(Tile::Breakable))
+    }
+
+    fn can_move_to(&self, x: usize, y: usize, entity_id: usize, passes_breakable: bool) -> bool {
+        if !self.is_tile_passable(x, y, passes_breakable) {
             return false;
         }
 
@@ -103,65 +317,206 @@ impl GameState {
         true
     }
 
-    pub fn place_bomb(&mut self) -> bool {
-        if let Some(player) = self.get_player() {
-            if !player.is_alive {
-                return false;
-            }
+    pub fn place_bomb(&mut self, owner_id: usize) -> bool {
+        let Some(owner) = self
+            .entities
+            .iter()
+            .find(|e| e.id == owner_id && e.is_alive)
+        else {
+            return false;
+        };
+        let Some(stats) = owner.player_stats.as_ref() else {
+            return false;
+        };
 
-            let x = player.position.x;
-            let y = player.position.y;
-            let range = player
-                .player_stats
-                .as_ref()
-                .map(|s| s.bomb_range)
-                .unwrap_or(1);
+        let x = owner.position.x;
+        let y = owner.position.y;
+        let range = stats.bomb_range;
+        let remote = stats.remote;
 
-            let active_bombs = self
-                .entities
-                .iter()
-                .filter(|e| {
-                    e.entity_type == EntityType::Bomb
-                        && e.is_alive
-                        && e.bomb_data.as_ref().map(|b| b.owner_id == self.player_id).unwrap_or(false)
-                })
-                .count();
-
-            let max_bombs = self
-                .get_player()
-                .and_then(|p| p.player_stats.as_ref())
-                .map(|s| s.max_bombs)
-                .unwrap_or(1);
-
-            if active_bombs >= max_bombs {
-                return false;
+        if self.active_bombs(owner_id) >= stats.max_bombs || self.has_bomb_at(x, y) {
+            return false;
+        }
+
+        let mut bomb = Entity::new_bomb(self.next_entity_id, x, y, range, owner_id);
+        if let Some(data) = &mut bomb.bomb_data {
+            data.remote = remote;
+        }
+        self.next_entity_id += 1;
+        self.entities.push(bomb);
+        true
+    }
+
+    /// Lights the owner's remote bombs so they go off on this tick's fuse
+    /// check. `false` when it has none waiting.
+    pub fn detonate(&mut self, owner_id: usize) -> bool {
+        let mut any = false;
+        for bomb in self
+            .entities
+            .iter_mut()
+            .filter(|e| e.is_alive)
+            .filter_map(|e| e.bomb_data.as_mut())
+            .filter(|bomb| bomb.owner_id == owner_id && bomb.remote)
+        {
+            bomb.remote = false;
+            bomb.timer = 0.0;
+            any = true;
+        }
+        any
+    }
+
+    pub fn active_bombs(&self, owner_id: usize) -> usize {
+        self.entities
+            .iter()
+            .filter(|e| {
+                e.entity_type == EntityType::Bomb
+                    && e.is_alive
+                    && e.bomb_data.as_ref().map(|b| b.owner_id == owner_id).unwrap_or(false)
+            })
+            .count()
+    }
+
+    pub fn add_player(&mut self, x: usize, y: usize) -> usize {
+        let id = self.next_entity_id;
+        self.entities.push(Entity::new_player(id, x, y));
+        self.next_entity_id += 1;
+        self.players.push(id);
+        self.round_stats.push(RoundStats::default());
+        id
+    }
+
+    pub fn player_slot(&self, entity_id: usize) -> Option<usize> {
+        self.players.iter().position(|id| *id == entity_id)
+    }
+
+    /// Whether two bombers fight on the same side in a team battle.
+    pub fn are_teammates(&self, a: usize, b: usize) -> bool {
+        match (self.player_slot(a), self.player_slot(b)) {
+            (Some(a), Some(b)) => self.rules.teams && self.rules.side_of(a) == self.rules.side_of(b),
+            _ => false,
+        }
+    }
+
+    /// Credits a blast death to the bomber whose bomb caused it.
+    pub fn record_death(&mut self, victim: usize, killer: Option<usize>) {
+        let Some(killer) = killer.and_then(|id| self.player_slot(id)) else {
+            return;
+        };
+        let Some(stats) = self.round_stats.get_mut(killer) else {
+            return;
+        };
+        if self.players.get(killer) == Some(&victim) {
+            stats.suicides += 1;
+        } else {
+            stats.kills += 1;
+        }
+    }
+
+    /// `Some(winner)` once a battle round is decided, where the winner is
+    /// the side (player or team) left standing, or `None` for a draw.
+    pub fn round_result(&self) -> Option<Option<usize>> {
+        let mut sides = self
+            .alive_players()
+            .into_iter()
+            .filter_map(|id| self.player_slot(id))
+            .map(|slot| self.rules.side_of(slot));
+        let first = sides.next();
+        if sides.all(|side| Some(side) == first) {
+            Some(first)
+        } else {
+            None
+        }
+    }
+
+    /// Fingerprint of the simulation state, compared between peers to
+    /// detect desyncs. Stable across processes running the same build.
+    pub fn checksum(&self) -> u64 {
+        let mut hash = Fnv1a::new();
+
+        for y in 0..self.world.height() {
+            for x in 0..self.world.width() {
+                hash.write(self.world.get_tile(x, y).map(|t| t as u64).unwrap_or(u64::MAX));
             }
+        }
 
-            for entity in &self.entities {
-                if entity.is_alive
-                    && entity.position.x == x
-                    && entity.position.y == y
-                    && entity.entity_type == EntityType::Bomb
-                {
-                    return false;
-                }
+        for entity in &self.entities {
+            hash.write(entity.id as u64);
+            hash.write(entity.entity_type as u64);
+            hash.write(entity.position.x as u64);
+            hash.write(entity.position.y as u64);
+            hash.write(entity.is_alive as u64);
+            if let Some(stats) = &entity.player_stats {
+                hash.write(stats.max_bombs as u64);
+                hash.write(stats.bomb_range as u64);
+                hash.write(stats.step_cooldown.to_bits() as u64);
+                hash.write(stats.kick as u64);
+                hash.write(stats.remote as u64);
+                hash.write(stats.invulnerable.to_bits() as u64);
+            }
+            if let Some(bomb) = &entity.bomb_data {
+                hash.write(bomb.timer.to_bits() as u64);
+                hash.write(bomb.range as u64);
+                hash.write(bomb.owner_id as u64);
+                hash.write(bomb.remote as u64);
+                hash.write(bomb.sliding.map_or(u64::MAX, |direction| direction as u64));
+                hash.write(bomb.slide_cooldown.to_bits() as u64);
             }
+            if let Some(explosion) = &entity.explosion_data {
+                hash.write(explosion.timer.to_bits() as u64);
+            }
+            if let Some(item_type) = entity.item_type {
+                hash.write(item_type as u64);
+            }
+            if let Some(enemy) = &entity.enemy_data {
+                hash.write(enemy.hp as u64);
+                hash.write(enemy.hit_cooldown.to_bits() as u64);
+            }
+        }
 
-            let bomb = Entity::new_bomb(self.next_entity_id, x, y, range, self.player_id);
-            self.next_entity_id += 1;
-            self.entities.push(bomb);
-            true
-        } else {
-            false
+        hash.write(self.score as u64);
+        hash.write(self.lives as u64);
+        hash.finish()
+    }
+
+    pub fn eliminate_player(&mut self, slot: usize) {
+        if let Some(&id) = self.players.get(slot)
+            && let Some(player) = self.entities.iter_mut().find(|e| e.id == id)
+        {
+            player.is_alive = false;
         }
     }
 
-    pub fn add_explosion(&mut self, x: usize, y: usize) {
-        let explosion = Entity::new_explosion(self.next_entity_id, x, y);
+    pub fn alive_players(&self) -> Vec<usize> {
+        self.players
+            .iter()
+            .copied()
+            .filter(|id| self.entities.iter().any(|e| e.id == *id && e.is_alive))
+            .collect()
+    }
+
+    pub fn danger_map(&self) -> DangerMap {
+        DangerMap::compute(self)
+    }
+
+    pub fn add_explosion(&mut self, x: usize, y: usize, owner_id: Option<usize>) {
+        let explosion = Entity::new_explosion(self.next_entity_id, x, y, owner_id);
         self.next_entity_id += 1;
         self.entities.push(explosion);
     }
 
+    pub fn add_enemy(&mut self, x: usize, y: usize, kind: EnemyKind) -> usize {
+        let id = self.next_entity_id;
+        self.entities.push(Entity::new_enemy(id, x, y, kind));
+        self.next_entity_id += 1;
+        let seed = self.rng.random();
+        self.set_controller(id, controller_for(kind.ai_strategy(), seed));
+        id
+    }
+
+    pub fn push_event(&mut self, kind: EventKind) {
+        self.events.push(GameEvent { at: self.elapsed, kind });
+    }
+
     pub fn add_item(&mut self, x: usize, y: usize, item_type: ItemType) {
         let item = Entity::new_item(self.next_entity_id, x, y, item_type);
         self.next_entity_id += 1;
@@ -169,62 +524,291 @@ impl GameState {
     }
 
     fn collect_items(&mut self) {
-        let player_pos = if let Some(player) = self.get_player() {
-            if player.is_alive {
-                Some((player.position.x, player.position.y))
-            } else {
-                None
-            }
-        } else {
-            None
-        };
-
-        if let Some((px, py)) = player_pos {
-            let mut items_to_collect = Vec::new();
+        let mut items_to_collect = Vec::new();
 
+        for player in &self.entities {
+            if player.entity_type != EntityType::Player || !player.is_alive {
+                continue;
+            }
             for entity in &self.entities {
                 if entity.entity_type == EntityType::Item
                     && entity.is_alive
-                    && entity.position.x == px
-                    && entity.position.y == py
+                    && entity.position == player.position
+                    && let Some(item_type) = entity.item_type
                 {
-                    if let Some(item_type) = entity.item_type {
-                        items_to_collect.push((entity.id, item_type));
-                    }
+                    items_to_collect.push((player.id, entity.id, item_type));
                 }
             }
+        }
+
+        for (player_id, item_id, item_type) in items_to_collect {
+            if let Some(stats) = self
+                .player_slot(player_id)
+                .and_then(|slot| self.round_stats.get_mut(slot))
+            {
+                stats.items += 1;
+            }
 
-            for (item_id, item_type) in items_to_collect {
-                if let Some(player) = self.get_player_mut() {
-                    if let Some(stats) = &mut player.player_stats {
-                        match item_type {
-                            ItemType::Fire => {
-                                stats.bomb_range += 1;
-                            }
-                            ItemType::Bomb => {
-                                stats.max_bombs += 1;
-                            }
-                        }
+            if let Some(player) = self.entities.iter_mut().find(|e| e.id == player_id)
+                && let Some(stats) = &mut player.player_stats
+            {
+                match item_type {
+                    ItemType::Fire => {
+                        stats.bomb_range += 1;
+                    }
+                    ItemType::Bomb => {
+                        stats.max_bombs += 1;
+                    }
+                    ItemType::Kick => {
+                        stats.kick = true;
+                    }
+                    ItemType::Remote => {
+                        stats.remote = true;
                     }
                 }
+            }
 
-                if let Some(item) = self.entities.iter_mut().find(|e| e.id == item_id) {
-                    item.is_alive = false;
-                }
+            if let Some(item) = self.entities.iter_mut().find(|e| e.id == item_id) {
+                item.is_alive = false;
             }
+            self.push_event(EventKind::ItemCollected {
+                player: player_id,
+                item: item_type,
+            });
         }
     }
 
-    pub fn tick(&mut self, delta_time: f32) {
-        self.enemy_move_timer += delta_time;
-        if self.enemy_move_timer >= 0.3 {
-            update_enemy_ai(self);
-            self.enemy_move_timer = 0.0;
+    fn update_controllers(&mut self, delta_time: f32) {
+        let entities = &self.entities;
+        self.controllers
+            .retain(|id, _| entities.iter().any(|e| e.id == *id && e.is_alive));
+
+        let mut ready = Vec::new();
+        for (id, slot) in &mut self.controllers {
+            let Some(entity) = self.entities.iter().find(|e| e.id == *id) else {
+                continue;
+            };
+            slot.timer += delta_time;
+            let interval = slot
+                .controller
+                .think_interval()
+                .unwrap_or_else(|| think_interval(entity));
+            if slot.timer >= interval {
+                slot.timer = 0.0;
+                ready.push(*id);
+            }
+        }
+
+        if ready.is_empty() {
+            return;
+        }
+
+        let mut danger = self.danger_map();
+        for id in ready {
+            let Some(mut slot) = self.controllers.remove(&id) else {
+                continue;
+            };
+            let action = slot.controller.decide(&Observation {
+                state: self,
+                danger: &danger,
+                entity_id: id,
+            });
+            self.controllers.insert(id, slot);
+
+            if self.apply_action(id, action) && action == Action::PlaceBomb {
+                danger = self.danger_map();
+            }
         }
+    }
+
+    pub fn tick(&mut self, delta_time: f32) {
+        self.elapsed += delta_time;
+        let elapsed = self.elapsed;
+        self.events.retain(|event| elapsed - event.at < EVENT_MEMORY);
+        update_enemy_cooldowns(self, delta_time);
+        self.update_player_timers(delta_time);
+        self.update_controllers(delta_time);
 
         update_bombs(self, delta_time);
         update_explosions(self, delta_time);
         self.collect_items();
         check_player_enemy_collision(self);
+        update_sudden_death(self);
+        self.revive_hero();
+    }
+
+    /// Brings a fallen story hero back at the start, shielded for a
+    /// moment, while it has lives to spare.
+    fn revive_hero(&mut self) {
+        if self.mode != GameMode::Story {
+            return;
+        }
+        let player_id = self.player_id;
+        let Some(hero) = self.entities.iter_mut().find(|e| e.id == player_id && !e.is_alive) else {
+            return;
+        };
+        if self.lives <= 1 {
+            self.lives = 0;
+            return;
+        }
+        hero.is_alive = true;
+        hero.position = self.spawn;
+        if let Some(stats) = &mut hero.player_stats {
+            stats.invulnerable = RESPAWN_SHIELD;
+            stats.step_cooldown = 0.0;
+        }
+        self.lives -= 1;
+        self.push_event(EventKind::LifeLost { left: self.lives });
+    }
+}
+
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+
+    fn write(&mut self, value: u64) {
+        for byte in value.to_le_bytes() {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{mode::Difficulty, stage::loader::battle_stages};
+
+    const TICK: f32 = 0.05;
+
+    /// The story hero alone at the left end of a walled corridor.
+    fn corridor() -> GameState {
+        let stage = StageConfig {
+            name: "corridor".to_string(),
+            width: 7,
+            height: 3,
+            layout: ["#######", "#P    #", "#######"].map(String::from).to_vec(),
+            enemies: Vec::new(),
+            sight: None,
+        };
+        GameState::from_stage(stage, 1)
+    }
+
+    fn hero(state: &mut GameState) -> &mut Entity {
+        let id = state.player_id;
+        state.entities.iter_mut().find(|e| e.id == id).unwrap()
+    }
+
+    fn bomb(state: &GameState) -> &Entity {
+        state.entities.iter().find(|e| e.entity_type == EntityType::Bomb).unwrap()
+    }
+
+    fn run(state: &mut GameState, seconds: f32) {
+        for _ in 0..(seconds / TICK).round() as usize {
+            state.tick(TICK);
+        }
+    }
+
+    #[test]
+    fn a_dead_cpu_is_still_a_cpu() {
+        let slots = [BomberSlot::Human, BomberSlot::Cpu(Difficulty::Normal)];
+        let mut state = GameState::new_battle(battle_stages().remove(0), &slots, BattleRules::default(), 7);
+        let cpu = state.players[1];
+        if let Some(entity) = state.entities.iter_mut().find(|e| e.id == cpu) {
+            entity.is_alive = false;
+        }
+        state.tick(0.05);
+
+        assert!(!state.is_cpu(0));
+        assert!(state.is_cpu(1));
+    }
+
+    #[test]
+    fn a_remote_bomb_waits_for_its_owner() {
+        let mut state = corridor();
+        let id = state.player_id;
+        hero(&mut state).player_stats.as_mut().unwrap().remote = true;
+        assert!(state.place_bomb(id));
+        hero(&mut state).position = Position::new(5, 1);
+        run(&mut state, 5.0);
+        assert!(bomb(&state).is_alive);
+
+        assert!(state.apply_action(id, Action::Detonate));
+        state.tick(TICK);
+        assert!(!bomb(&state).is_alive);
+        assert!(state.entities.iter().any(|e| e.entity_type == EntityType::Explosion && e.is_alive));
+        assert!(!state.detonate(id));
+    }
+
+    #[test]
+    fn a_kicked_bomb_slides_until_the_wall() {
+        let mut state = corridor();
+        let id = state.player_id;
+        hero(&mut state).position = Position::new(2, 1);
+        state.place_bomb(id);
+        hero(&mut state).position = Position::new(1, 1);
+
+        assert!(!state.move_entity(id, Direction::Right));
+        assert!(bomb(&state).bomb_data.as_ref().unwrap().sliding.is_none());
+
+        hero(&mut state).player_stats.as_mut().unwrap().kick = true;
+        state.move_entity(id, Direction::Right);
+        run(&mut state, 1.0);
+        let bomb = bomb(&state);
+        assert_eq!(bomb.position, Position::new(5, 1));
+        assert!(bomb.bomb_data.as_ref().unwrap().sliding.is_none());
+    }
+
+    #[test]
+    fn the_story_hero_comes_back_until_out_of_lives() {
+        let mut state = corridor();
+        hero(&mut state).position = Position::new(4, 1);
+        hero(&mut state).is_alive = false;
+        state.tick(TICK);
+
+        let revived = hero(&mut state);
+        assert!(revived.is_alive);
+        assert_eq!(revived.position, Position::new(1, 1));
+        assert!(revived.is_shielded());
+        assert_eq!(state.lives, STORY_LIVES - 1);
+        assert!(state.events.iter().any(|e| e.kind == EventKind::LifeLost { left: STORY_LIVES - 1 }));
+
+        for _ in 1..STORY_LIVES {
+            hero(&mut state).is_alive = false;
+            state.tick(TICK);
+        }
+        assert!(!hero(&mut state).is_alive);
+        assert_eq!(state.lives, 0);
+    }
+
+    #[test]
+    fn a_shielded_hero_survives_a_blast() {
+        let mut state = corridor();
+        let id = state.player_id;
+        hero(&mut state).player_stats.as_mut().unwrap().invulnerable = 5.0;
+        state.place_bomb(id);
+        run(&mut state, 3.5);
+
+        assert!(hero(&mut state).is_alive);
+        assert_eq!(state.lives, STORY_LIVES);
+    }
+
+    #[test]
+    fn ability_items_are_kept() {
+        let mut state = corridor();
+        state.add_item(1, 1, ItemType::Kick);
+        state.add_item(1, 1, ItemType::Remote);
+        state.tick(TICK);
+
+        let stats = hero(&mut state).player_stats.clone().unwrap();
+        assert!(stats.kick);
+        assert!(stats.remote);
     }
 }
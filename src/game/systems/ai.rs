@@ -1,58 +1,266 @@
 use crate::game::{
-    components::Direction,
+    components::{AiStrategy, Direction, Position},
+    controller::{Action, Controller, Observation},
     entity::EntityType,
     state::GameState,
+    systems::pathfinding::{next_step_to, step},
 };
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::seq::IndexedRandom;
+use rand::{Rng, SeedableRng};
 
-pub fn update_enemy_ai(game_state: &mut GameState) {
-    let enemy_ids: Vec<usize> = game_state
-        .entities
-        .iter()
-        .filter(|e| e.entity_type == EntityType::Enemy && e.is_alive)
-        .map(|e| e.id)
-        .collect();
+pub fn update_enemy_cooldowns(game_state: &mut GameState, delta_time: f32) {
+    for entity in &mut game_state.entities {
+        if entity.entity_type == EntityType::Enemy
+            && entity.is_alive
+            && let Some(enemy) = &mut entity.enemy_data
+        {
+            enemy.hit_cooldown = (enemy.hit_cooldown - delta_time).max(0.0);
+        }
+    }
+}
+
+pub fn controller_for(strategy: AiStrategy, seed: u64) -> Box<dyn Controller> {
+    let rng = StdRng::seed_from_u64(seed);
+    match strategy {
+        AiStrategy::RandomWalk => Box::new(RandomWalk { rng }),
+        AiStrategy::Patrol => Box::new(Patrol { rng }),
+        AiStrategy::Hunt { sight_radius } => Box::new(Hunt { sight_radius, rng }),
+    }
+}
+
+#[derive(Clone)]
+pub struct RandomWalk {
+    rng: StdRng,
+}
+
+impl Controller for RandomWalk {
+    fn decide(&mut self, observation: &Observation) -> Action {
+        let Some(info) = EnemyInfo::observe(observation) else {
+            return Action::Idle;
+        };
+        if let Some(action) = flee(observation, &info) {
+            return action;
+        }
+
+        let direction = match self.rng.random_range(0..4) {
+            0 => Direction::Up,
+            1 => Direction::Down,
+            2 => Direction::Left,
+            _ => Direction::Right,
+        };
+
+        if is_safe_step(observation, &info, direction) {
+            Action::Move(direction)
+        } else {
+            Action::Idle
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Patrol {
+    rng: StdRng,
+}
+
+impl Controller for Patrol {
+    fn decide(&mut self, observation: &Observation) -> Action {
+        let Some(info) = EnemyInfo::observe(observation) else {
+            return Action::Idle;
+        };
+        flee(observation, &info).unwrap_or_else(|| patrol(observation, &info, &mut self.rng))
+    }
+}
+
+#[derive(Clone)]
+pub struct Hunt {
+    sight_radius: usize,
+    rng: StdRng,
+}
+
+impl Controller for Hunt {
+    fn decide(&mut self, observation: &Observation) -> Action {
+        let Some(info) = EnemyInfo::observe(observation) else {
+            return Action::Idle;
+        };
+        if let Some(action) = flee(observation, &info) {
+            return action;
+        }
+
+        let state = observation.state;
+        let danger = observation.danger;
+        let position = info.position;
+        // Every living bomber in sight; the search finds the nearest by path.
+        let targets: Vec<Position> = state
+            .alive_players()
+            .into_iter()
+            .filter_map(|id| state.entities.iter().find(|e| e.id == id))
+            .map(|p| p.position)
+            .filter(|p| p.x.abs_diff(position.x) + p.y.abs_diff(position.y) <= self.sight_radius)
+            .collect();
+
+        let direction = (!targets.is_empty())
+            .then(|| {
+                next_step_to(
+                    state,
+                    position,
+                    self.sight_radius * 2,
+                    |p| {
+                        state.is_passable(p.x, p.y, info.passes_breakable)
+                            && !danger.is_unsafe(p.x, p.y, info.safety_window)
+                    },
+                    |p| targets.contains(&p),
+                )
+            })
+            .flatten();
+
+        match direction {
+            Some(direction) => Action::Move(direction),
+            None => patrol(observation, &info, &mut self.rng),
+        }
+    }
+}
 
-    for enemy_id in enemy_ids {
-        random_walk_ai(game_state, enemy_id);
+struct EnemyInfo {
+    position: Position,
+    facing: Option<Direction>,
+    passes_breakable: bool,
+    /// Time needed to step onto a tile and off it again.
+    safety_window: f32,
+}
+
+impl EnemyInfo {
+    fn observe(observation: &Observation) -> Option<Self> {
+        let entity = observation.entity()?;
+        let enemy = entity.enemy_data.as_ref()?;
+        Some(Self {
+            position: entity.position,
+            facing: enemy.facing,
+            passes_breakable: entity.passes_breakable(),
+            safety_window: enemy.kind.move_interval() * 2.0,
+        })
     }
 }
 
-fn random_walk_ai(game_state: &mut GameState, enemy_id: usize) {
-    let mut rng = rand::rng();
-    let direction = match rng.random_range(0..4) {
-        0 => Direction::Up,
-        1 => Direction::Down,
-        2 => Direction::Left,
-        _ => Direction::Right,
+/// Steps an enemy standing in a blast line towards the nearest tile that
+/// will not explode before it could leave again. `None` if it is not in danger.
+fn flee(observation: &Observation, info: &EnemyInfo) -> Option<Action> {
+    let state = observation.state;
+    let danger = observation.danger;
+    if !danger.is_unsafe(info.position.x, info.position.y, info.safety_window) {
+        return None;
+    }
+
+    let direction = next_step_to(
+        state,
+        info.position,
+        8,
+        |p| state.is_passable(p.x, p.y, info.passes_breakable),
+        |p| !danger.is_unsafe(p.x, p.y, info.safety_window),
+    );
+    Some(direction.map(Action::Move).unwrap_or(Action::Idle))
+}
+
+/// Follows the current corridor, picking a new direction only at
+/// intersections and turning back only at dead ends.
+fn patrol(observation: &Observation, info: &EnemyInfo, rng: &mut StdRng) -> Action {
+    let open: Vec<Direction> = Direction::ALL
+        .into_iter()
+        .filter(|&d| is_safe_step(observation, info, d))
+        .collect();
+
+    let forward: Vec<Direction> = open
+        .iter()
+        .copied()
+        .filter(|&d| Some(d.opposite()) != info.facing)
+        .collect();
+
+    let direction = match info.facing {
+        Some(facing) if forward.len() == 1 && forward[0] == facing => Some(facing),
+        _ if !forward.is_empty() => forward.choose(rng).copied(),
+        _ => open.first().copied(),
     };
 
-    game_state.move_entity(enemy_id, direction);
+    direction.map(Action::Move).unwrap_or(Action::Idle)
+}
+
+fn is_safe_step(observation: &Observation, info: &EnemyInfo, direction: Direction) -> bool {
+    step(info.position, direction)
+        .map(|p| {
+            observation.state.is_passable(p.x, p.y, info.passes_breakable)
+                && !observation.danger.is_unsafe(p.x, p.y, info.safety_window)
+        })
+        .unwrap_or(false)
 }
 
 pub fn check_player_enemy_collision(game_state: &mut GameState) {
-    let player_pos = if let Some(player) = game_state.get_player() {
-        if player.is_alive {
-            Some((player.position.x, player.position.y))
-        } else {
-            None
+    let enemy_positions: Vec<Position> = game_state
+        .entities
+        .iter()
+        .filter(|e| e.entity_type == EntityType::Enemy && e.is_alive)
+        .map(|e| e.position)
+        .collect();
+
+    for entity in &mut game_state.entities {
+        if entity.entity_type == EntityType::Player
+            && entity.is_alive
+            && !entity.is_shielded()
+            && enemy_positions.contains(&entity.position)
+        {
+            entity.is_alive = false;
         }
-    } else {
-        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{
+        components::EnemyKind,
+        mode::{BattleRules, BomberSlot},
+        stage::StageConfig,
     };
 
-    if let Some((px, py)) = player_pos {
-        for entity in &game_state.entities {
-            if entity.entity_type == EntityType::Enemy
-                && entity.is_alive
-                && entity.position.x == px
-                && entity.position.y == py
-            {
-                if let Some(player) = game_state.get_player_mut() {
-                    player.is_alive = false;
-                }
-                break;
-            }
-        }
+    /// Two bombers at the ends of a corridor and a hunter nearer the second.
+    fn corridor() -> (GameState, usize) {
+        let stage = StageConfig {
+            name: "corridor".to_string(),
+            width: 9,
+            height: 3,
+            layout: ["#########", "#1     2#", "#########"].map(String::from).to_vec(),
+            enemies: Vec::new(),
+            sight: None,
+        };
+        let mut state = GameState::new_battle(stage, &[BomberSlot::Human; 2], BattleRules::default(), 1);
+        let hunter = state.add_enemy(5, 1, EnemyKind::Chaser);
+        (state, hunter)
+    }
+
+    fn hunt(state: &GameState, hunter: usize) -> Action {
+        let danger = state.danger_map();
+        let mut controller = Hunt {
+            sight_radius: 8,
+            rng: StdRng::seed_from_u64(1),
+        };
+        controller.decide(&Observation {
+            state,
+            danger: &danger,
+            entity_id: hunter,
+        })
+    }
+
+    #[test]
+    fn a_hunter_goes_for_the_nearest_bomber() {
+        let (state, hunter) = corridor();
+
+        assert_eq!(hunt(&state, hunter), Action::Move(Direction::Right));
+    }
+
+    #[test]
+    fn a_hunter_ignores_fallen_bombers() {
+        let (mut state, hunter) = corridor();
+        state.eliminate_player(1);
+
+        assert_eq!(hunt(&state, hunter), Action::Move(Direction::Left));
     }
 }
@@ -1,54 +1,109 @@
+use rand::Rng;
+
 use crate::game::{
-    components::ItemType,
+    components::{BombData, EnemyData, ItemType, Position},
     entity::EntityType,
+    events::EventKind,
     state::GameState,
     world::Tile,
 };
 
 pub fn update_bombs(game_state: &mut GameState, delta_time: f32) {
+    slide_bombs(game_state, delta_time);
     let mut explosions_to_create = Vec::new();
 
     for entity in &mut game_state.entities {
-        if entity.entity_type == EntityType::Bomb && entity.is_alive {
-            if let Some(bomb_data) = &mut entity.bomb_data {
+        if entity.entity_type == EntityType::Bomb
+            && entity.is_alive
+            && let Some(bomb_data) = &mut entity.bomb_data
+        {
+            if !bomb_data.remote {
                 bomb_data.timer -= delta_time;
+            }
 
-                if bomb_data.timer <= 0.0 {
-                    let x = entity.position.x;
-                    let y = entity.position.y;
-                    let range = bomb_data.range;
-                    entity.is_alive = false;
+            if bomb_data.timer <= 0.0 {
+                let x = entity.position.x;
+                let y = entity.position.y;
+                let range = bomb_data.range;
+                let owner_id = bomb_data.owner_id;
+                entity.is_alive = false;
 
-                    explosions_to_create.push((x, y, range));
-                }
+                explosions_to_create.push((x, y, range, owner_id));
             }
         }
     }
 
-    for (x, y, range) in explosions_to_create {
-        create_explosion(game_state, x, y, range);
+    for (x, y, range, owner_id) in explosions_to_create {
+        create_explosion(game_state, x, y, range, owner_id);
     }
 }
 
-pub fn update_explosions(game_state: &mut GameState, delta_time: f32) {
+/// Moves kicked bombs on a tile at a time until a wall, a breakable or
+/// anything standing there stops them.
+fn slide_bombs(game_state: &mut GameState, delta_time: f32) {
+    let mut ready = Vec::new();
     for entity in &mut game_state.entities {
-        if entity.entity_type == EntityType::Explosion && entity.is_alive {
-            if let Some(explosion_data) = &mut entity.explosion_data {
-                explosion_data.timer -= delta_time;
+        if entity.entity_type == EntityType::Bomb
+            && entity.is_alive
+            && let Some(bomb_data) = &mut entity.bomb_data
+            && let Some(direction) = bomb_data.sliding
+        {
+            bomb_data.slide_cooldown -= delta_time;
+            if bomb_data.slide_cooldown <= 0.0 {
+                ready.push((entity.id, entity.position, direction));
+            }
+        }
+    }
 
-                if explosion_data.timer <= 0.0 {
-                    entity.is_alive = false;
-                }
+    for (id, position, direction) in ready {
+        let (dx, dy) = direction.to_delta();
+        let next = Position::new(
+            (position.x as i32 + dx) as usize,
+            (position.y as i32 + dy) as usize,
+        );
+        let blocked = !game_state.world.is_walkable(next.x, next.y)
+            || game_state.entities.iter().any(|e| {
+                e.is_alive && e.position == next && e.entity_type != EntityType::Explosion
+            });
+        let Some(bomb) = game_state.entities.iter_mut().find(|e| e.id == id) else {
+            continue;
+        };
+        if let Some(bomb_data) = &mut bomb.bomb_data {
+            if blocked {
+                bomb_data.sliding = None;
+            } else {
+                bomb_data.slide_cooldown += BombData::SLIDE_INTERVAL;
             }
         }
+        if !blocked {
+            bomb.position = next;
+        }
     }
 }
 
-fn create_explosion(game_state: &mut GameState, x: usize, y: usize, range: usize) {
-    let mut pending = vec![(x, y, range)];
+pub fn update_explosions(game_state: &mut GameState, delta_time: f32) {
+    for entity in &mut game_state.entities {
+        if entity.entity_type == EntityType::Explosion
+            && entity.is_alive
+            && let Some(explosion_data) = &mut entity.explosion_data
+        {
+            explosion_data.timer -= delta_time;
+
+            if explosion_data.timer <= 0.0 {
+                entity.is_alive = false;
+            }
+        }
+    }
+}
+
+fn create_explosion(game_state: &mut GameState, x: usize, y: usize, range: usize, owner_id: usize) {
+    let mut pending = vec![(x, y, range, owner_id)];
+    let mut bombs = 0;
+    let item_chance = game_state.rules.item_chance as f32 / 100.0;
 
-    while let Some((cx, cy, crange)) = pending.pop() {
-        game_state.add_explosion(cx, cy);
+    while let Some((cx, cy, crange, cowner)) = pending.pop() {
+        bombs += 1;
+        game_state.add_explosion(cx, cy, Some(cowner));
 
         let directions = [(0, -1), (0, 1), (-1, 0), (1, 0)];
 
@@ -62,20 +117,21 @@ fn create_explosion(game_state: &mut GameState, x: usize, y: usize, range: usize
                         Tile::Wall => break,
                         Tile::Breakable => {
                             game_state.world.set_tile(new_x, new_y, Tile::Empty);
-                            game_state.add_explosion(new_x, new_y);
-
-                            if rand::random::<f32>() < 0.3 {
-                                let item_type = if rand::random::<bool>() {
-                                    ItemType::Fire
-                                } else {
-                                    ItemType::Bomb
+                            game_state.add_explosion(new_x, new_y, Some(cowner));
+
+                            if game_state.rng.random::<f32>() < item_chance {
+                                let item_type = match game_state.rng.random_range(0..10) {
+                                    0..=3 => ItemType::Fire,
+                                    4..=7 => ItemType::Bomb,
+                                    8 => ItemType::Kick,
+                                    _ => ItemType::Remote,
                                 };
                                 game_state.add_item(new_x, new_y, item_type);
                             }
                             break;
                         }
                         Tile::Empty => {
-                            game_state.add_explosion(new_x, new_y);
+                            game_state.add_explosion(new_x, new_y, Some(cowner));
                         }
                     }
                 } else {
@@ -87,36 +143,73 @@ fn create_explosion(game_state: &mut GameState, x: usize, y: usize, range: usize
         let chain = damage_entities_in_explosions(game_state);
         pending.extend(chain);
     }
+
+    if bombs > 1 {
+        game_state.push_event(EventKind::Chain { bombs, by: owner_id });
+    }
 }
 
-fn damage_entities_in_explosions(game_state: &mut GameState) -> Vec<(usize, usize, usize)> {
-    let explosion_positions: Vec<(usize, usize)> = game_state
+fn damage_entities_in_explosions(game_state: &mut GameState) -> Vec<(usize, usize, usize, usize)> {
+    let explosion_positions: Vec<(usize, usize, Option<usize>)> = game_state
         .entities
         .iter()
         .filter(|e| e.entity_type == EntityType::Explosion && e.is_alive)
-        .map(|e| (e.position.x, e.position.y))
+        .map(|e| {
+            let owner = e.explosion_data.as_ref().and_then(|data| data.owner_id);
+            (e.position.x, e.position.y, owner)
+        })
         .collect();
 
     let mut chain_explosions = Vec::new();
+    let mut splits = Vec::new();
+    let mut deaths = Vec::new();
+    let mut defeated = Vec::new();
 
     for entity in &mut game_state.entities {
         if !entity.is_alive {
             continue;
         }
 
-        for (ex, ey) in &explosion_positions {
+        for (ex, ey, owner) in &explosion_positions {
             if entity.position.x != *ex || entity.position.y != *ey {
                 continue;
             }
 
             match entity.entity_type {
-                EntityType::Player | EntityType::Enemy => {
+                EntityType::Player if entity.is_shielded() => {}
+                EntityType::Player => {
                     entity.is_alive = false;
+                    deaths.push((entity.id, *owner));
+                }
+                EntityType::Enemy => {
+                    if let Some(enemy) = &mut entity.enemy_data
+                        && enemy.hit_cooldown <= 0.0
+                    {
+                        enemy.hp = enemy.hp.saturating_sub(1);
+                        enemy.hit_cooldown = EnemyData::HIT_COOLDOWN;
+                        if enemy.hp == 0 {
+                            entity.is_alive = false;
+                            game_state.score += enemy.kind.score();
+                            defeated.push(EventKind::EnemyDefeated {
+                                kind: enemy.kind,
+                                score: enemy.kind.score(),
+                                by: *owner,
+                            });
+                            if let Some(split) = enemy.kind.splits_into() {
+                                splits.push((entity.position.x, entity.position.y, split));
+                            }
+                        }
+                    }
                 }
                 EntityType::Bomb => {
                     entity.is_alive = false;
                     if let Some(bomb_data) = &entity.bomb_data {
-                        chain_explosions.push((entity.position.x, entity.position.y, bomb_data.range));
+                        chain_explosions.push((
+                            entity.position.x,
+                            entity.position.y,
+                            bomb_data.range,
+                            bomb_data.owner_id,
+                        ));
                     }
                 }
                 _ => {}
@@ -125,5 +218,27 @@ fn damage_entities_in_explosions(game_state: &mut GameState) -> Vec<(usize, usiz
         }
     }
 
+    for (victim, killer) in deaths {
+        game_state.record_death(victim, killer);
+        game_state.push_event(EventKind::BomberDefeated { victim, by: killer });
+    }
+    for event in defeated {
+        game_state.push_event(event);
+    }
+
+    for (x, y, (kind, count)) in splits {
+        for _ in 0..count {
+            let id = game_state.add_enemy(x, y, kind);
+            if let Some(enemy) = game_state
+                .entities
+                .iter_mut()
+                .find(|e| e.id == id)
+                .and_then(|e| e.enemy_data.as_mut())
+            {
+                enemy.hit_cooldown = EnemyData::HIT_COOLDOWN;
+            }
+        }
+    }
+
     chain_explosions
 }
@@ -0,0 +1,253 @@
+use rand::rngs::StdRng;
+use rand::seq::IndexedRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::game::{
+    components::{Direction, Position},
+    controller::{Action, Controller, Observation},
+    entity::EntityType,
+    mode::Difficulty,
+    systems::danger::blast_tiles,
+    systems::pathfinding::{next_step_to, step},
+    world::Tile,
+};
+
+/// Drives a CPU bomber: flees blasts, bombs blocks and opponents when it has
+/// a way out, detonates remote bombs once clear of them, picks up items and
+/// otherwise roams towards something to bomb.
+#[derive(Clone)]
+pub struct BomberController {
+    difficulty: Difficulty,
+    rng: StdRng,
+}
+
+impl BomberController {
+    pub fn new(difficulty: Difficulty, seed: u64) -> Self {
+        Self {
+            difficulty,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    fn blunder_chance(&self) -> f32 {
+        match self.difficulty {
+            Difficulty::Easy => 0.25,
+            Difficulty::Normal => 0.08,
+            Difficulty::Hard => 0.0,
+        }
+    }
+
+    fn seeks_items(&self) -> bool {
+        !matches!(self.difficulty, Difficulty::Easy)
+    }
+
+    fn hunts_opponents(&self) -> bool {
+        matches!(self.difficulty, Difficulty::Hard)
+    }
+}
+
+impl Controller for BomberController {
+    fn think_interval(&self) -> Option<f32> {
+        Some(match self.difficulty {
+            Difficulty::Easy => 0.4,
+            Difficulty::Normal => 0.28,
+            Difficulty::Hard => 0.18,
+        })
+    }
+
+    fn decide(&mut self, observation: &Observation) -> Action {
+        let Some(me) = observation.entity() else {
+            return Action::Idle;
+        };
+        let Some(stats) = me.player_stats.as_ref() else {
+            return Action::Idle;
+        };
+        let state = observation.state;
+        let danger = observation.danger;
+        let position = me.position;
+        let window = self.think_interval().unwrap_or(0.2) * 2.0;
+        let walkable = |p: Position| {
+            state.is_passable(p.x, p.y, false) && !danger.is_unsafe(p.x, p.y, window)
+        };
+
+        if danger.time_until(position.x, position.y).is_some() {
+            return next_step_to(state, position, 10, walkable, |p| {
+                danger.time_until(p.x, p.y).is_none()
+            })
+            .map(Action::Move)
+            .unwrap_or(Action::Idle);
+        }
+
+        // Standing clear of every blast, it sets off the remote bombs it left.
+        let waiting = state.entities.iter().any(|e| {
+            e.is_alive && e.bomb_data.as_ref().is_some_and(|bomb| bomb.owner_id == me.id && bomb.remote)
+        });
+        if waiting {
+            return Action::Detonate;
+        }
+
+        if self.rng.random::<f32>() < self.blunder_chance() {
+            return random_step(position, &walkable, &mut self.rng);
+        }
+
+        let opponents: Vec<Position> = state
+            .entities
+            .iter()
+            .filter(|e| {
+                e.id != me.id
+                    && e.is_alive
+                    && matches!(e.entity_type, EntityType::Player | EntityType::Enemy)
+                    && !state.are_teammates(me.id, e.id)
+            })
+            .map(|e| e.position)
+            .collect();
+        let hits_opponent =
+            |p: Position| blast_tiles(&state.world, p, stats.bomb_range).iter().any(|t| opponents.contains(t));
+        let hits_block = |p: Position| {
+            blast_tiles(&state.world, p, stats.bomb_range)
+                .iter()
+                .any(|t| state.world.get_tile(t.x, t.y) == Some(Tile::Breakable))
+        };
+
+        let can_bomb =
+            state.active_bombs(me.id) < stats.max_bombs && !state.has_bomb_at(position.x, position.y);
+        if can_bomb && (hits_opponent(position) || hits_block(position)) {
+            let blast = blast_tiles(&state.world, position, stats.bomb_range);
+            let escape = next_step_to(state, position, 8, walkable, |p| {
+                !blast.contains(&p) && danger.time_until(p.x, p.y).is_none()
+            });
+            if escape.is_some() {
+                return Action::PlaceBomb;
+            }
+        }
+
+        let safe_spot = |p: Position| danger.time_until(p.x, p.y).is_none();
+
+        if self.seeks_items() {
+            let items: Vec<Position> = state
+                .entities
+                .iter()
+                .filter(|e| e.entity_type == EntityType::Item && e.is_alive)
+                .map(|e| e.position)
+                .collect();
+            if let Some(direction) =
+                next_step_to(state, position, 12, walkable, |p| items.contains(&p))
+            {
+                return Action::Move(direction);
+            }
+        }
+
+        if self.hunts_opponents()
+            && let Some(direction) = next_step_to(state, position, 20, walkable, |p| {
+                safe_spot(p) && hits_opponent(p)
+            })
+        {
+            return Action::Move(direction);
+        }
+
+        next_step_to(state, position, 20, walkable, |p| {
+            safe_spot(p) && (hits_block(p) || hits_opponent(p))
+        })
+        .map(Action::Move)
+        .unwrap_or_else(|| random_step(position, &walkable, &mut self.rng))
+    }
+}
+
+fn random_step(
+    position: Position,
+    walkable: &impl Fn(Position) -> bool,
+    rng: &mut StdRng,
+) -> Action {
+    let open: Vec<Direction> = Direction::ALL
+        .into_iter()
+        .filter(|&d| step(position, d).map(walkable).unwrap_or(false))
+        .collect();
+    open.choose(rng)
+        .copied()
+        .map(Action::Move)
+        .unwrap_or(Action::Idle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{
+        mode::{BattleRules, BomberSlot},
+        stage::StageConfig,
+        GameState,
+    };
+
+    /// A human and a hard CPU, with the CPU's bombs at range 1.
+    fn arena(layout: &[&str]) -> (GameState, usize) {
+        let stage = StageConfig {
+            name: "arena".to_string(),
+            width: layout[0].len(),
+            height: layout.len(),
+            layout: layout.iter().map(|row| row.to_string()).collect(),
+            enemies: Vec::new(),
+            sight: None,
+        };
+        let slots = [BomberSlot::Human, BomberSlot::Cpu(Difficulty::Hard)];
+        let state = GameState::new_battle(stage, &slots, BattleRules::default(), 1);
+        let cpu = state.players[1];
+        (state, cpu)
+    }
+
+    fn decide(state: &GameState, cpu: usize) -> Action {
+        let danger = state.danger_map();
+        BomberController::new(Difficulty::Hard, 1).decide(&Observation {
+            state,
+            danger: &danger,
+            entity_id: cpu,
+        })
+    }
+
+    fn move_to(state: &mut GameState, id: usize, x: usize, y: usize) {
+        if let Some(entity) = state.entities.iter_mut().find(|e| e.id == id) {
+            entity.position = Position::new(x, y);
+        }
+    }
+
+    #[test]
+    fn a_cpu_flees_a_blast_line() {
+        let (mut state, cpu) = arena(&["########", "#1    2#", "########"]);
+        let human = state.players[0];
+        move_to(&mut state, human, 3, 1);
+        state.place_bomb(human);
+        move_to(&mut state, human, 1, 1);
+        move_to(&mut state, cpu, 4, 1);
+
+        assert_eq!(decide(&state, cpu), Action::Move(Direction::Right));
+    }
+
+    #[test]
+    fn a_cpu_bombs_an_opponent_only_with_a_way_out() {
+        let layout = ["#####", "#1 2#", "### #", "### #", "#####"];
+        let (mut state, cpu) = arena(&layout);
+        let human = state.players[0];
+        move_to(&mut state, human, 2, 1);
+        assert_eq!(decide(&state, cpu), Action::PlaceBomb);
+
+        state.world.set_tile(1, 1, Tile::Wall);
+        state.world.set_tile(3, 2, Tile::Wall);
+        assert_ne!(decide(&state, cpu), Action::PlaceBomb);
+    }
+
+    #[test]
+    fn a_cpu_detonates_its_remote_bombs_once_clear() {
+        let (mut state, cpu) = arena(&["########", "#1    2#", "########"]);
+        if let Some(stats) = state
+            .entities
+            .iter_mut()
+            .find(|e| e.id == cpu)
+            .and_then(|e| e.player_stats.as_mut())
+        {
+            stats.remote = true;
+        }
+        state.place_bomb(cpu);
+        assert_ne!(decide(&state, cpu), Action::Detonate);
+
+        move_to(&mut state, cpu, 3, 1);
+        assert_eq!(decide(&state, cpu), Action::Detonate);
+    }
+}
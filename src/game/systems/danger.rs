@@ -0,0 +1,191 @@
+use crate::game::{
+    components::Position,
+    entity::EntityType,
+    state::GameState,
+    world::{Tile, World},
+};
+
+/// Seconds until each tile is swept by a blast, taking chain reactions into
+/// account. Tiles that are burning right now are at `0.0`.
+#[derive(Debug, Clone)]
+pub struct DangerMap {
+    width: usize,
+    height: usize,
+    cells: Vec<Option<f32>>,
+}
+
+impl DangerMap {
+    pub fn compute(game_state: &GameState) -> Self {
+        let width = game_state.world.width();
+        let height = game_state.world.height();
+        let mut map = Self {
+            width,
+            height,
+            cells: vec![None; width * height],
+        };
+
+        let mut bombs: Vec<(Position, Vec<Position>, f32)> = game_state
+            .entities
+            .iter()
+            .filter(|e| e.entity_type == EntityType::Bomb && e.is_alive)
+            .filter_map(|e| {
+                let bomb = e.bomb_data.as_ref()?;
+                let tiles = blast_tiles(&game_state.world, e.position, bomb.range);
+                Some((e.position, tiles, bomb.timer.max(0.0)))
+            })
+            .collect();
+
+        // A bomb caught in another bomb's blast goes off no later than that bomb.
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for i in 0..bombs.len() {
+                for j in 0..bombs.len() {
+                    if i != j && bombs[j].2 > bombs[i].2 && bombs[i].1.contains(&bombs[j].0) {
+                        bombs[j].2 = bombs[i].2;
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        for (_, tiles, timer) in &bombs {
+            for tile in tiles {
+                map.mark(*tile, *timer);
+            }
+        }
+
+        for entity in &game_state.entities {
+            if entity.entity_type == EntityType::Explosion && entity.is_alive {
+                map.mark(entity.position, 0.0);
+            }
+        }
+
+        map
+    }
+
+    pub fn time_until(&self, x: usize, y: usize) -> Option<f32> {
+        if x < self.width && y < self.height {
+            self.cells[y * self.width + x]
+        } else {
+            None
+        }
+    }
+
+    /// Whether a tile blows up within `window` seconds.
+    pub fn is_unsafe(&self, x: usize, y: usize, window: f32) -> bool {
+        self.time_until(x, y).map(|t| t <= window).unwrap_or(false)
+    }
+
+    fn mark(&mut self, position: Position, time: f32) {
+        if position.x < self.width && position.y < self.height {
+            let cell = &mut self.cells[position.y * self.width + position.x];
+            *cell = Some(cell.map_or(time, |t| t.min(time)));
+        }
+    }
+}
+
+/// Tiles covered by a bomb at `origin`, following the same rules as the
+/// explosion itself: walls stop the blast, breakable blocks are hit and stop it.
+pub fn blast_tiles(world: &World, origin: Position, range: usize) -> Vec<Position> {
+    let mut tiles = vec![origin];
+
+    for (dx, dy) in [(0, -1), (0, 1), (-1, 0), (1, 0)] {
+        for i in 1..=range as isize {
+            let (Some(x), Some(y)) = (
+                origin.x.checked_add_signed(dx * i),
+                origin.y.checked_add_signed(dy * i),
+            ) else {
+                break;
+            };
+
+            match world.get_tile(x, y) {
+                Some(Tile::Empty) => tiles.push(Position::new(x, y)),
+                Some(Tile::Breakable) => {
+                    tiles.push(Position::new(x, y));
+                    break;
+                }
+                Some(Tile::Wall) | None => break,
+            }
+        }
+    }
+
+    tiles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::stage::StageConfig;
+
+    fn state(layout: &[&str]) -> GameState {
+        let stage = StageConfig {
+            name: "test".to_string(),
+            width: layout[0].len(),
+            height: layout.len(),
+            layout: layout.iter().map(|row| row.to_string()).collect(),
+            enemies: Vec::new(),
+            sight: None,
+        };
+        GameState::from_stage(stage, 1)
+    }
+
+    /// Leaves a bomb with the given fuse and range at `(x, y)`.
+    fn bomb(state: &mut GameState, x: usize, y: usize, timer: f32, range: usize) {
+        let bomber = state.add_player(x, y);
+        state.place_bomb(bomber);
+        state.entities.retain(|e| e.id != bomber);
+        if let Some(bomb) = state
+            .entities
+            .iter_mut()
+            .find(|e| e.position == Position::new(x, y))
+            .and_then(|e| e.bomb_data.as_mut())
+        {
+            bomb.timer = timer;
+            bomb.range = range;
+        }
+    }
+
+    #[test]
+    fn a_blast_stops_at_walls_and_breakables() {
+        let mut state = state(&["#########", "#   X   #", "#########"]);
+        bomb(&mut state, 2, 1, 2.0, 3);
+        let map = state.danger_map();
+
+        for x in 1..=4 {
+            assert_eq!(map.time_until(x, 1), Some(2.0), "tile {}", x);
+        }
+        assert_eq!(map.time_until(5, 1), None);
+        assert_eq!(map.time_until(0, 1), None);
+        assert_eq!(map.time_until(2, 0), None);
+    }
+
+    #[test]
+    fn a_chained_bomb_goes_off_with_the_first() {
+        let mut state = state(&["###########", "#         #", "###########"]);
+        bomb(&mut state, 1, 1, 1.0, 2);
+        bomb(&mut state, 3, 1, 3.0, 2);
+        bomb(&mut state, 8, 1, 2.5, 1);
+        let map = state.danger_map();
+
+        assert_eq!(map.time_until(5, 1), Some(1.0));
+        assert_eq!(map.time_until(6, 1), None);
+        assert_eq!(map.time_until(8, 1), Some(2.5));
+        assert!(map.is_unsafe(5, 1, 1.0));
+        assert!(!map.is_unsafe(8, 1, 1.0));
+    }
+
+    #[test]
+    fn burning_tiles_are_dangerous_now() {
+        let mut state = state(&["#######", "#     #", "#######"]);
+        bomb(&mut state, 1, 1, 2.0, 3);
+        state.add_explosion(3, 1, None);
+        state.add_explosion(5, 1, None);
+        let map = state.danger_map();
+
+        assert_eq!(map.time_until(3, 1), Some(0.0));
+        assert_eq!(map.time_until(5, 1), Some(0.0));
+        assert_eq!(map.time_until(2, 1), Some(2.0));
+        assert!(map.is_unsafe(5, 1, 0.0));
+    }
+}
@@ -1,3 +1,8 @@
 pub mod ai;
 pub mod bomb;
+pub mod bomber;
+pub mod danger;
 pub mod movement;
+pub mod pathfinding;
+pub mod sudden_death;
+pub mod visibility;
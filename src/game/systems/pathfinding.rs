@@ -0,0 +1,149 @@
+use std::collections::VecDeque;
+
+use crate::game::{
+    components::{Direction, Position},
+    state::GameState,
+};
+
+pub fn step(position: Position, direction: Direction) -> Option<Position> {
+    let (dx, dy) = direction.to_delta();
+    let x = position.x.checked_add_signed(dx as isize)?;
+    let y = position.y.checked_add_signed(dy as isize)?;
+    Some(Position::new(x, y))
+}
+
+/// Breadth-first search from `from` over tiles accepted by `passable`.
+/// Returns the first step of a shortest path to the nearest tile satisfying
+/// `is_goal`, or `None` if no such tile is reachable within `max_depth` steps.
+pub fn next_step_to(
+    game_state: &GameState,
+    from: Position,
+    max_depth: usize,
+    passable: impl Fn(Position) -> bool,
+    is_goal: impl Fn(Position) -> bool,
+) -> Option<Direction> {
+    let width = game_state.world.width();
+    let height = game_state.world.height();
+    if is_goal(from) || from.x >= width || from.y >= height {
+        return None;
+    }
+
+    let mut first_step: Vec<Option<Direction>> = vec![None; width * height];
+    let mut visited = vec![false; width * height];
+    let mut queue = VecDeque::new();
+
+    visited[from.y * width + from.x] = true;
+    queue.push_back((from, 0));
+
+    while let Some((current, depth)) = queue.pop_front() {
+        if depth >= max_depth {
+            continue;
+        }
+
+        for direction in Direction::ALL {
+            let Some(next) = step(current, direction) else {
+                continue;
+            };
+            if next.x >= width || next.y >= height {
+                continue;
+            }
+            let index = next.y * width + next.x;
+            if visited[index] || !passable(next) {
+                continue;
+            }
+
+            visited[index] = true;
+            let first = if current == from {
+                Some(direction)
+            } else {
+                first_step[current.y * width + current.x]
+            };
+            first_step[index] = first;
+
+            if is_goal(next) {
+                return first;
+            }
+            queue.push_back((next, depth + 1));
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::stage::StageConfig;
+
+    fn state(layout: &[&str]) -> GameState {
+        let stage = StageConfig {
+            name: "test".to_string(),
+            width: layout[0].len(),
+            height: layout.len(),
+            layout: layout.iter().map(|row| row.to_string()).collect(),
+            enemies: Vec::new(),
+            sight: None,
+        };
+        GameState::from_stage(stage, 1)
+    }
+
+    const LOOP: [&str; 5] = ["#######", "#     #", "# ### #", "#     #", "#######"];
+
+    fn walk(state: &GameState, from: Position, to: Position) -> Option<Direction> {
+        next_step_to(state, from, 20, |p| state.is_passable(p.x, p.y, false), |p| p == to)
+    }
+
+    #[test]
+    fn the_first_step_is_on_a_shortest_path() {
+        let state = state(&LOOP);
+
+        assert_eq!(walk(&state, Position::new(1, 1), Position::new(5, 1)), Some(Direction::Right));
+        assert_eq!(walk(&state, Position::new(1, 1), Position::new(1, 3)), Some(Direction::Down));
+    }
+
+    #[test]
+    fn an_unreachable_target_gives_no_step() {
+        let state = state(&["#######", "#  #  #", "#######"]);
+
+        assert_eq!(walk(&state, Position::new(1, 1), Position::new(5, 1)), None);
+    }
+
+    #[test]
+    fn a_target_already_reached_gives_no_step() {
+        let state = state(&LOOP);
+
+        assert_eq!(walk(&state, Position::new(3, 1), Position::new(3, 1)), None);
+    }
+
+    #[test]
+    fn a_bomb_in_the_way_is_walked_around() {
+        let mut state = state(&LOOP);
+        let bomber = state.add_player(2, 1);
+        state.place_bomb(bomber);
+        state.entities.retain(|e| e.id != bomber);
+
+        assert_eq!(walk(&state, Position::new(1, 1), Position::new(5, 1)), Some(Direction::Down));
+    }
+
+    #[test]
+    fn a_bomb_blocking_the_only_way_gives_no_step() {
+        let mut state = state(&["#######", "#     #", "#######"]);
+        let bomber = state.add_player(3, 1);
+        state.place_bomb(bomber);
+        state.entities.retain(|e| e.id != bomber);
+
+        assert_eq!(walk(&state, Position::new(1, 1), Position::new(5, 1)), None);
+    }
+
+    #[test]
+    fn targets_beyond_the_depth_are_not_found() {
+        let state = state(&["#######", "#     #", "#######"]);
+        let passable = |p: Position| state.is_passable(p.x, p.y, false);
+
+        assert_eq!(next_step_to(&state, Position::new(1, 1), 3, passable, |p| p.x == 5), None);
+        assert_eq!(
+            next_step_to(&state, Position::new(1, 1), 4, passable, |p| p.x == 5),
+            Some(Direction::Right)
+        );
+    }
+}
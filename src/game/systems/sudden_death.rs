@@ -0,0 +1,135 @@
+use crate::game::{
+    components::Position,
+    entity::EntityType,
+    events::EventKind,
+    mode::GameMode,
+    state::GameState,
+    world::{Tile, World},
+};
+
+/// Seconds between two falling walls.
+const FILL_INTERVAL: f32 = 0.25;
+
+/// Once a round has run for `sudden_death_after` seconds, fills the arena
+/// with walls in a spiral from the edges inwards, crushing whatever stands
+/// on the next tile.
+pub fn update_sudden_death(game_state: &mut GameState) {
+    if game_state.mode != GameMode::Battle || !game_state.rules.sudden_death {
+        return;
+    }
+    let overtime = game_state.elapsed - game_state.rules.sudden_death_after as f32;
+    if overtime < 0.0 {
+        return;
+    }
+
+    let due = (overtime / FILL_INTERVAL) as usize + 1;
+    while game_state.sudden_death_step < due {
+        game_state.sudden_death_step += 1;
+        let next = closing_order(&game_state.world)
+            .into_iter()
+            .find(|p| game_state.world.get_tile(p.x, p.y) != Some(Tile::Wall));
+        let Some(position) = next else {
+            return;
+        };
+
+        game_state.world.set_tile(position.x, position.y, Tile::Wall);
+        let mut crushed = Vec::new();
+        for entity in &mut game_state.entities {
+            if entity.is_alive && entity.position == position {
+                entity.is_alive = false;
+                if entity.entity_type == EntityType::Player {
+                    crushed.push(entity.id);
+                }
+            }
+        }
+        for victim in crushed {
+            game_state.record_death(victim, None);
+            game_state.push_event(EventKind::BomberDefeated { victim, by: None });
+        }
+    }
+}
+
+/// Every tile inside the outer wall, ring by ring from the outside in.
+fn closing_order(world: &World) -> Vec<Position> {
+    let (width, height) = (world.width(), world.height());
+    let mut order = Vec::new();
+    let mut ring = 1;
+
+    while ring * 2 < width && ring * 2 < height {
+        let (left, top) = (ring, ring);
+        let (right, bottom) = (width - 1 - ring, height - 1 - ring);
+        order.extend((left..=right).map(|x| Position::new(x, top)));
+        order.extend((top + 1..=bottom).map(|y| Position::new(right, y)));
+        if bottom > top {
+            order.extend((left..right).rev().map(|x| Position::new(x, bottom)));
+        }
+        if right > left {
+            order.extend((top + 1..bottom).rev().map(|y| Position::new(left, y)));
+        }
+        ring += 1;
+    }
+
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{
+        events::GameEvent,
+        mode::{BattleRules, BomberSlot},
+        stage::StageConfig,
+    };
+
+    /// Two bombers in a one-row arena, with sudden death after ten seconds.
+    fn corridor() -> GameState {
+        let stage = StageConfig {
+            name: "corridor".to_string(),
+            width: 5,
+            height: 3,
+            layout: ["#####", "#1 2#", "#####"].map(String::from).to_vec(),
+            enemies: Vec::new(),
+            sight: None,
+        };
+        let rules = BattleRules {
+            sudden_death_after: 10,
+            ..BattleRules::default()
+        };
+        GameState::new_battle(stage, &[BomberSlot::Human; 2], rules, 1)
+    }
+
+    #[test]
+    fn walls_wait_for_the_configured_time() {
+        let mut state = corridor();
+        state.elapsed = 9.9;
+        update_sudden_death(&mut state);
+
+        assert_eq!(state.world.get_tile(1, 1), Some(Tile::Empty));
+        assert_eq!(state.alive_players().len(), 2);
+    }
+
+    #[test]
+    fn a_crushed_bomber_is_reported_like_any_other_death() {
+        let mut state = corridor();
+        let victim = state.players[0];
+        state.elapsed = 10.0;
+        update_sudden_death(&mut state);
+
+        assert_eq!(state.world.get_tile(1, 1), Some(Tile::Wall));
+        assert_eq!(state.alive_players(), [state.players[1]]);
+        let reported = state.events.iter().any(|GameEvent { kind, .. }| {
+            *kind == EventKind::BomberDefeated { victim, by: None }
+        });
+        assert!(reported);
+    }
+
+    #[test]
+    fn nothing_closes_in_when_sudden_death_is_off() {
+        let mut state = corridor();
+        state.rules.sudden_death = false;
+        state.elapsed = 100.0;
+        update_sudden_death(&mut state);
+
+        assert_eq!(state.world.get_tile(1, 1), Some(Tile::Empty));
+    }
+}
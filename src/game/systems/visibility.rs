@@ -0,0 +1,229 @@
+use crate::game::{
+    components::Position,
+    entity::EntityType,
+    state::GameState,
+    world::{Tile, World},
+};
+
+/// How far around itself a lit bomb lets you see on a dark stage.
+const BOMB_GLOW: usize = 1;
+/// How far around every burning tile a blast lets you see.
+const BLAST_GLOW: usize = 2;
+
+/// Tiles some viewer can see right now on a dark stage: those within the
+/// stage's sight radius of a viewer, plus the glow of bombs and blasts, as
+/// long as no wall stands in the way.
+#[derive(Debug, Clone)]
+pub struct Visibility {
+    width: usize,
+    height: usize,
+    cells: Vec<bool>,
+}
+
+impl Visibility {
+    /// `None` when everything is visible: on a lit stage, or once none of
+    /// the viewers is left alive.
+    pub fn compute(game_state: &GameState, viewers: &[usize]) -> Option<Self> {
+        let sight = game_state.sight?;
+        if !game_state.entities.iter().any(|e| e.is_alive && viewers.contains(&e.id)) {
+            return None;
+        }
+        let world = &game_state.world;
+        let mut visibility = Self {
+            width: world.width(),
+            height: world.height(),
+            cells: vec![false; world.width() * world.height()],
+        };
+
+        for entity in game_state.entities.iter().filter(|e| e.is_alive) {
+            let radius = match entity.entity_type {
+                EntityType::Player if viewers.contains(&entity.id) => sight,
+                EntityType::Bomb => BOMB_GLOW,
+                EntityType::Explosion => BLAST_GLOW,
+                _ => continue,
+            };
+            visibility.light(world, entity.position, radius);
+        }
+
+        Some(visibility)
+    }
+
+    pub fn is_visible(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height && self.cells[y * self.width + x]
+    }
+
+    fn light(&mut self, world: &World, from: Position, radius: usize) {
+        let reach = |a: usize, b: usize| a.saturating_sub(radius)..=(a + radius).min(b - 1);
+        for y in reach(from.y, self.height) {
+            for x in reach(from.x, self.width) {
+                let (dx, dy) = (x.abs_diff(from.x), y.abs_diff(from.y));
+                if dx * dx + dy * dy <= radius * radius + radius
+                    && line_of_sight(world, from, Position::new(x, y))
+                {
+                    self.cells[y * self.width + x] = true;
+                }
+            }
+        }
+    }
+}
+
+/// Whether nothing but the end points of the line between two tiles is a
+/// wall, so a wall itself can be seen but nothing behind it.
+fn line_of_sight(world: &World, from: Position, to: Position) -> bool {
+    let (mut x, mut y) = (from.x as i32, from.y as i32);
+    let (x1, y1) = (to.x as i32, to.y as i32);
+    let (dx, dy) = ((x1 - x).abs(), -(y1 - y).abs());
+    let (sx, sy) = ((x1 - x).signum(), (y1 - y).signum());
+    let mut error = dx + dy;
+    loop {
+        if (x, y) == (x1, y1) {
+            return true;
+        }
+        if (x, y) != (from.x as i32, from.y as i32)
+            && world.get_tile(x as usize, y as usize) == Some(Tile::Wall)
+        {
+            return false;
+        }
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            x += sx;
+        }
+        if doubled <= dx {
+            error += dx;
+            y += sy;
+        }
+    }
+}
+
+/// Terrain as it looked when last seen, drawn outside the light. Nothing
+/// that moves is remembered.
+#[derive(Debug, Clone, Default)]
+pub struct TerrainMemory {
+    width: usize,
+    tiles: Vec<Option<Tile>>,
+}
+
+impl TerrainMemory {
+    pub fn clear(&mut self) {
+        self.tiles.clear();
+    }
+
+    pub fn update(&mut self, world: &World, visibility: &Visibility) {
+        if self.width != world.width() || self.tiles.len() != world.width() * world.height() {
+            self.width = world.width();
+            self.tiles = vec![None; world.width() * world.height()];
+        }
+        for y in 0..world.height() {
+            for x in 0..world.width() {
+                if visibility.is_visible(x, y) {
+                    self.tiles[y * self.width + x] = world.get_tile(x, y);
+                }
+            }
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<Tile> {
+        if x < self.width {
+            self.tiles.get(y * self.width + x).copied().flatten()
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::stage::StageConfig;
+
+    /// A dark story stage with the hero at its `P`.
+    fn dark(layout: &[&str], sight: usize) -> GameState {
+        let stage = StageConfig {
+            name: "dark".to_string(),
+            width: layout[0].len(),
+            height: layout.len(),
+            layout: layout.iter().map(|row| row.to_string()).collect(),
+            enemies: Vec::new(),
+            sight: Some(sight),
+        };
+        GameState::from_stage(stage, 1)
+    }
+
+    fn seen(state: &GameState) -> Visibility {
+        Visibility::compute(state, &[state.player_id]).unwrap()
+    }
+
+    #[test]
+    fn walls_block_the_view_but_are_seen_themselves() {
+        let state = dark(&["#########", "#P #    #", "#########"], 5);
+        let visibility = seen(&state);
+
+        assert!(visibility.is_visible(2, 1));
+        assert!(visibility.is_visible(3, 1));
+        assert!(!visibility.is_visible(4, 1));
+        assert!(!visibility.is_visible(5, 1));
+    }
+
+    #[test]
+    fn breakables_do_not_block_the_view() {
+        let state = dark(&["#########", "#P X    #", "#########"], 5);
+        let visibility = seen(&state);
+
+        assert!(visibility.is_visible(3, 1));
+        assert!(visibility.is_visible(6, 1));
+        assert!(!visibility.is_visible(7, 1));
+    }
+
+    #[test]
+    fn bombs_and_blasts_light_up_around_them() {
+        let mut state = dark(&["###############", "#P            #", "###############"], 1);
+        let bomber = state.add_player(6, 1);
+        state.place_bomb(bomber);
+        state.entities.retain(|e| e.id != bomber);
+        state.add_explosion(11, 1, None);
+        let visibility = seen(&state);
+
+        assert!(visibility.is_visible(2, 1));
+        assert!(!visibility.is_visible(4, 1));
+        for x in 5..=7 {
+            assert!(visibility.is_visible(x, 1), "bomb glow at {}", x);
+        }
+        assert!(!visibility.is_visible(8, 1));
+        for x in 9..=13 {
+            assert!(visibility.is_visible(x, 1), "blast glow at {}", x);
+        }
+    }
+
+    #[test]
+    fn everything_shows_on_a_lit_stage_or_with_no_viewer_left() {
+        let mut state = dark(&["#####", "#P  #", "#####"], 2);
+        state.sight = None;
+        assert!(Visibility::compute(&state, &[state.player_id]).is_none());
+
+        state.sight = Some(2);
+        state.eliminate_player(0);
+        assert!(Visibility::compute(&state, &[state.player_id]).is_none());
+    }
+
+    #[test]
+    fn terrain_memory_keeps_what_was_seen() {
+        let mut state = dark(&["###########", "#P  X     #", "###########"], 3);
+        let mut memory = TerrainMemory::default();
+        memory.update(&state.world, &seen(&state));
+        assert_eq!(memory.get(4, 1), Some(Tile::Breakable));
+        assert_eq!(memory.get(8, 1), None);
+
+        // The block breaks out of sight; the memory still shows it.
+        let id = state.player_id;
+        if let Some(hero) = state.entities.iter_mut().find(|e| e.id == id) {
+            hero.position = Position::new(9, 1);
+        }
+        state.world.set_tile(4, 1, Tile::Empty);
+        memory.update(&state.world, &seen(&state));
+
+        assert_eq!(memory.get(4, 1), Some(Tile::Breakable));
+        assert_eq!(memory.get(8, 1), Some(Tile::Empty));
+        assert_eq!(memory.get(1, 1), Some(Tile::Empty));
+    }
+}
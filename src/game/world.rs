@@ -1,5 +1,3 @@
-use std::fmt;
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Tile {
     Empty,
@@ -11,16 +9,9 @@ impl Tile {
     pub fn is_walkable(&self) -> bool {
         matches!(self, Tile::Empty)
     }
-
-    pub fn to_char(&self) -> &str {
-        match self {
-            Tile::Empty => "  ",
-            Tile::Wall => "🧱",
-            Tile::Breakable => "📦",
-        }
-    }
 }
 
+#[derive(Clone)]
 pub struct World {
     width: usize,
     height: usize,
@@ -30,12 +21,14 @@ pub struct World {
 impl World {
     pub fn from_layout(layout: &[&str]) -> Self {
         let height = layout.len();
-        let width = if height > 0 { layout[0].len() } else { 0 };
+        let width = layout.first().map_or(0, |row| row.chars().count());
 
         let mut tiles = vec![vec![Tile::Empty; width]; height];
 
+        // Stage files are validated to be rectangular; anything past the
+        // first row's width is dropped rather than indexed.
         for (y, row) in layout.iter().enumerate() {
-            for (x, ch) in row.chars().enumerate() {
+            for (x, ch) in row.chars().take(width).enumerate() {
                 tiles[y][x] = match ch {
                     '#' => Tile::Wall,
                     'X' => Tile::Breakable,
@@ -79,15 +72,3 @@ impl World {
             .unwrap_or(false)
     }
 }
-
-impl fmt::Display for World {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for row in &self.tiles {
-            for tile in row {
-                write!(f, "{}", tile.to_char())?;
-            }
-            writeln!(f)?;
-        }
-        Ok(())
-    }
-}
@@ -0,0 +1,324 @@
+use std::collections::VecDeque;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+
+use crate::game::{
+    components::{Direction, Position},
+    controller::Action,
+    systems::pathfinding::step,
+    GameState,
+};
+use crate::keys::{KeyMap, PlayerBindings};
+
+/// Largest count prefix, so a stray run of digits can't queue for minutes.
+const MAX_COUNT: usize = 99;
+/// Keys read as counts, motions or `.` when no binding claims them.
+const VIM_KEYS: &str = "0123456789wb$.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Motion {
+    /// A movement or bomb key.
+    Act(Action),
+    /// `w`: along the way the bomber last walked, to the next crossing.
+    NextCrossing,
+    /// `b`: back the other way to the previous crossing.
+    PreviousCrossing,
+    /// `0`: left to the end of the corridor.
+    CorridorStart,
+    /// `$`: right to the end of the corridor.
+    CorridorEnd,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Command {
+    count: Option<usize>,
+    motion: Motion,
+}
+
+/// Vim flavour for the player at the keyboard: count prefixes (`3l`),
+/// motions (`w` `b` `0` `$`) and `.` to repeat. A command is turned into
+/// actions up front and queued; the app feeds them to the game one at a
+/// time, as fast as the bomber's speed allows.
+#[derive(Debug, Default)]
+pub struct InputQueue {
+    count: Option<usize>,
+    queue: VecDeque<Action>,
+    last: Option<Command>,
+    /// Where the last movement key pointed, which `w` and `b` follow.
+    facing: Option<Direction>,
+}
+
+impl InputQueue {
+    /// Reads a key press. Returns whether the key was taken here; a single
+    /// movement or bomb key without a count is left to the caller so it
+    /// acts at once, but still counts as the command `.` repeats. Bindings
+    /// are checked before the Vim keys, so binding `w` to a move turns off
+    /// the `w` motion; see `shadowed_vim_keys`.
+    pub fn press(
+        &mut self,
+        key: &KeyEvent,
+        bindings: &PlayerBindings,
+        state: &GameState,
+        entity_id: usize,
+    ) -> bool {
+        if key.kind == KeyEventKind::Repeat {
+            return false;
+        }
+        let motion = match (bindings.action(key), key.code) {
+            (Some(action), _) => Motion::Act(action),
+            (None, KeyCode::Char(digit @ '0'..='9')) if digit != '0' || self.count.is_some() => {
+                let count = self.count.unwrap_or(0) * 10 + digit as usize - '0' as usize;
+                self.count = Some(count.min(MAX_COUNT));
+                return true;
+            }
+            (None, KeyCode::Char('w')) => Motion::NextCrossing,
+            (None, KeyCode::Char('b')) => Motion::PreviousCrossing,
+            (None, KeyCode::Char('0')) => Motion::CorridorStart,
+            (None, KeyCode::Char('$')) => Motion::CorridorEnd,
+            (None, KeyCode::Char('.')) => {
+                let count = self.count.take();
+                let Some(last) = self.last else {
+                    return true;
+                };
+                let command = Command {
+                    count: count.or(last.count),
+                    motion: last.motion,
+                };
+                self.queue = command_actions(command, self.facing, state, entity_id);
+                return true;
+            }
+            _ => {
+                self.count = None;
+                return false;
+            }
+        };
+
+        let command = Command {
+            count: self.count.take(),
+            motion,
+        };
+        self.last = Some(command);
+        if let Motion::Act(Action::Move(direction)) = motion {
+            self.facing = Some(direction);
+        }
+        if command.count.is_none() && matches!(motion, Motion::Act(_)) {
+            self.queue.clear();
+            return false;
+        }
+        self.queue = command_actions(command, self.facing, state, entity_id);
+        true
+    }
+
+    pub fn next(&mut self) -> Option<Action> {
+        self.queue.pop_front()
+    }
+
+    /// The count typed so far, shown like Vim's `showcmd`.
+    pub fn pending(&self) -> Option<usize> {
+        self.count
+    }
+
+    pub fn clear(&mut self) {
+        self.count = None;
+        self.queue.clear();
+    }
+}
+
+/// Vim keys that a binding takes first while playing, so they never reach
+/// the queue: the first player's controls, and the quit and pause keys the
+/// app checks before either.
+pub fn shadowed_vim_keys(keys: &KeyMap) -> Vec<char> {
+    let bindings = keys.player(0);
+    VIM_KEYS
+        .chars()
+        .filter(|c| {
+            let key = KeyEvent::new(KeyCode::Char(*c), KeyModifiers::NONE);
+            bindings.action(&key).is_some() || keys.is_quit(&key) || keys.is_pause(&key)
+        })
+        .collect()
+}
+
+fn command_actions(
+    command: Command,
+    facing: Option<Direction>,
+    state: &GameState,
+    entity_id: usize,
+) -> VecDeque<Action> {
+    let repeat = command.count.unwrap_or(1);
+    let Some(mut position) = state
+        .entities
+        .iter()
+        .find(|e| e.id == entity_id && e.is_alive)
+        .map(|e| e.position)
+    else {
+        return VecDeque::new();
+    };
+
+    let (direction, to_crossing, repeat) = match command.motion {
+        Motion::Act(action) => return std::iter::repeat_n(action, repeat).collect(),
+        Motion::NextCrossing => (facing.unwrap_or(Direction::Right), true, repeat),
+        Motion::PreviousCrossing => (facing.unwrap_or(Direction::Right).opposite(), true, repeat),
+        Motion::CorridorStart => (Direction::Left, false, 1),
+        Motion::CorridorEnd => (Direction::Right, false, 1),
+    };
+    let mut actions = VecDeque::new();
+    for _ in 0..repeat {
+        let steps = run(state, position, direction, to_crossing);
+        if steps == 0 {
+            break;
+        }
+        for _ in 0..steps {
+            position = step(position, direction).unwrap_or(position);
+            actions.push_back(Action::Move(direction));
+        }
+    }
+    actions
+}
+
+/// Tiles from `from` that can be walked in `direction` before something
+/// blocks the way, or before the first crossing when `to_crossing` is set.
+fn run(state: &GameState, from: Position, direction: Direction, to_crossing: bool) -> usize {
+    let passable = |p: Position| state.is_passable(p.x, p.y, false);
+    let mut position = from;
+    let mut steps = 0;
+    while let Some(next) = step(position, direction).filter(|p| passable(*p)) {
+        position = next;
+        steps += 1;
+        let crossing = Direction::ALL
+            .into_iter()
+            .filter(|d| *d != direction && *d != direction.opposite())
+            .any(|side| step(position, side).is_some_and(passable));
+        if to_crossing && crossing {
+            break;
+        }
+    }
+    steps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::stage::StageConfig;
+    use crate::keys::KeyBinding;
+
+    /// A corridor with side passages at every odd column, the bomber in
+    /// the middle of it at (4, 1).
+    fn setup() -> (InputQueue, PlayerBindings, GameState, usize) {
+        let stage = StageConfig {
+            name: "Corridor".to_string(),
+            width: 9,
+            height: 5,
+            layout: ["#########", "#   P   #", "# # # # #", "#       #", "#########"]
+                .map(String::from)
+                .to_vec(),
+            enemies: Vec::new(),
+            sight: None,
+        };
+        let state = GameState::from_stage(stage, 1);
+        let player = state.player_id;
+        (InputQueue::default(), PlayerBindings::for_player(0), state, player)
+    }
+
+    fn type_keys(input: &mut InputQueue, keys: &str, bindings: &PlayerBindings, state: &GameState, player: usize) {
+        for c in keys.chars() {
+            input.press(&KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE), bindings, state, player);
+        }
+    }
+
+    fn drain(input: &mut InputQueue) -> Vec<Action> {
+        std::iter::from_fn(|| input.next()).collect()
+    }
+
+    fn moves(direction: Direction, count: usize) -> Vec<Action> {
+        vec![Action::Move(direction); count]
+    }
+
+    #[test]
+    fn a_count_repeats_the_next_key() {
+        let (mut input, bindings, state, player) = setup();
+        type_keys(&mut input, "3", &bindings, &state, player);
+        assert_eq!(input.pending(), Some(3));
+        type_keys(&mut input, "l", &bindings, &state, player);
+        assert_eq!(input.pending(), None);
+        assert_eq!(drain(&mut input), moves(Direction::Right, 3));
+    }
+
+    #[test]
+    fn a_plain_key_is_left_to_the_caller() {
+        let (mut input, bindings, state, player) = setup();
+        let key = KeyEvent::new(KeyCode::Char('l'), KeyModifiers::NONE);
+        assert!(!input.press(&key, &bindings, &state, player));
+        assert_eq!(input.next(), None);
+    }
+
+    #[test]
+    fn counts_stop_at_the_limit() {
+        let (mut input, bindings, state, player) = setup();
+        type_keys(&mut input, "12345", &bindings, &state, player);
+        assert_eq!(input.pending(), Some(MAX_COUNT));
+        type_keys(&mut input, "j", &bindings, &state, player);
+        assert_eq!(drain(&mut input).len(), MAX_COUNT);
+    }
+
+    #[test]
+    fn zero_is_a_motion_unless_a_count_has_started() {
+        let (mut input, bindings, state, player) = setup();
+        type_keys(&mut input, "0", &bindings, &state, player);
+        assert_eq!(drain(&mut input), moves(Direction::Left, 3));
+        type_keys(&mut input, "$", &bindings, &state, player);
+        assert_eq!(drain(&mut input), moves(Direction::Right, 3));
+
+        type_keys(&mut input, "10", &bindings, &state, player);
+        assert_eq!(input.pending(), Some(10));
+        assert_eq!(input.next(), None);
+    }
+
+    #[test]
+    fn word_motions_stop_at_crossings() {
+        let (mut input, bindings, state, player) = setup();
+        type_keys(&mut input, "w", &bindings, &state, player);
+        assert_eq!(drain(&mut input), moves(Direction::Right, 1));
+        type_keys(&mut input, "2w", &bindings, &state, player);
+        assert_eq!(drain(&mut input), moves(Direction::Right, 3));
+        type_keys(&mut input, "b", &bindings, &state, player);
+        assert_eq!(drain(&mut input), moves(Direction::Left, 1));
+
+        // `w` follows the last movement key.
+        type_keys(&mut input, "h", &bindings, &state, player);
+        type_keys(&mut input, "w", &bindings, &state, player);
+        assert_eq!(drain(&mut input), moves(Direction::Left, 1));
+    }
+
+    #[test]
+    fn dot_repeats_with_the_last_count_or_a_new_one() {
+        let (mut input, bindings, state, player) = setup();
+        type_keys(&mut input, "3l", &bindings, &state, player);
+        drain(&mut input);
+        type_keys(&mut input, ".", &bindings, &state, player);
+        assert_eq!(drain(&mut input), moves(Direction::Right, 3));
+        type_keys(&mut input, "2.", &bindings, &state, player);
+        assert_eq!(drain(&mut input), moves(Direction::Right, 2));
+
+        type_keys(&mut input, "k.", &bindings, &state, player);
+        assert_eq!(drain(&mut input), moves(Direction::Up, 1));
+    }
+
+    #[test]
+    fn bindings_shadow_vim_keys() {
+        assert!(shadowed_vim_keys(&KeyMap::default()).is_empty());
+
+        let keys = KeyMap {
+            players: vec![PlayerBindings {
+                up: vec![KeyBinding::char('w')],
+                ..PlayerBindings::for_player(0)
+            }],
+            pause: vec![KeyBinding::char('.')],
+            ..KeyMap::default()
+        };
+        assert_eq!(shadowed_vim_keys(&keys), vec!['w', '.']);
+
+        let (mut input, _, state, player) = setup();
+        type_keys(&mut input, "3w", &keys.players[0], &state, player);
+        assert_eq!(drain(&mut input), moves(Direction::Up, 3));
+    }
+}
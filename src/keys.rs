@@ -0,0 +1,425 @@
+use std::fmt;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+
+use crate::game::{components::Direction, controller::Action};
+
+/// A key with the modifiers that must be held with it, written in the
+/// config as `"h"`, `"Space"`, `"Up"`, `"F2"` or `"Ctrl+b"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    pub const fn plain(code: KeyCode) -> Self {
+        Self {
+            code,
+            modifiers: KeyModifiers::NONE,
+        }
+    }
+
+    pub const fn char(c: char) -> Self {
+        Self::plain(KeyCode::Char(c))
+    }
+
+    /// Shift is already part of a typed character (`W` rather than `w`),
+    /// so it is only compared for other keys.
+    pub fn matches(&self, key: &KeyEvent) -> bool {
+        let mut modifiers = key.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        if matches!(key.code, KeyCode::Char(_)) {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        key.code == self.code && modifiers == self.modifiers
+    }
+}
+
+impl TryFrom<String> for KeyBinding {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, String> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = text.as_str();
+        while let Some((modifier, key)) = rest.split_once('+').filter(|(_, key)| !key.is_empty()) {
+            modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier `{}` in `{}`", modifier, text)),
+            };
+            rest = key;
+        }
+
+        let code = match rest.to_ascii_lowercase().as_str() {
+            "space" => KeyCode::Char(' '),
+            "enter" => KeyCode::Enter,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "esc" | "escape" => KeyCode::Esc,
+            "backspace" => KeyCode::Backspace,
+            "delete" => KeyCode::Delete,
+            "insert" => KeyCode::Insert,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            name if name.len() > 1
+                && let Some(number) = name.strip_prefix('f').and_then(|n| n.parse().ok()) =>
+            {
+                KeyCode::F(number)
+            }
+            _ => {
+                let mut chars = rest.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => return Err(format!("unknown key `{}`", text)),
+                }
+            }
+        };
+
+        // "Shift+w" is typed as `W`.
+        let code = match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            code => code,
+        };
+        Ok(Self { code, modifiers })
+    }
+}
+
+impl From<KeyBinding> for String {
+    /// The name `try_from` reads back, unlike `Display`, which draws arrows.
+    fn from(binding: KeyBinding) -> Self {
+        let key = match binding.code {
+            KeyCode::Char(' ') => "Space".to_string(),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::F(number) => format!("F{}", number),
+            code => format!("{:?}", code),
+        };
+        format!("{}{}", modifier_prefix(binding.modifiers), key)
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", modifier_prefix(self.modifiers), key_name(self.code))
+    }
+}
+
+/// `"Ctrl+Alt+"` and so on, in the order the config is written.
+fn modifier_prefix(modifiers: KeyModifiers) -> String {
+    [
+        (KeyModifiers::CONTROL, "Ctrl+"),
+        (KeyModifiers::ALT, "Alt+"),
+        (KeyModifiers::SHIFT, "Shift+"),
+    ]
+    .into_iter()
+    .filter(|(modifier, _)| modifiers.contains(*modifier))
+    .map(|(_, name)| name)
+    .collect()
+}
+
+/// Keys for one local player sharing the keyboard. Each action can have
+/// several keys; the first one is shown in the HUD.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerBindings {
+    pub up: Vec<KeyBinding>,
+    pub down: Vec<KeyBinding>,
+    pub left: Vec<KeyBinding>,
+    pub right: Vec<KeyBinding>,
+    pub bomb: Vec<KeyBinding>,
+    /// Sets off remote bombs. Configs written before it existed leave it
+    /// out, so an empty list means the player's default key.
+    #[serde(default)]
+    pub detonate: Vec<KeyBinding>,
+}
+
+impl PlayerBindings {
+    pub fn for_player(index: usize) -> Self {
+        match index {
+            0 => Self::new('k', 'j', 'h', 'l', KeyCode::Char(' '), 'x'),
+            1 => Self::new('w', 's', 'a', 'd', KeyCode::Tab, 'e'),
+            2 => Self {
+                up: vec![KeyBinding::plain(KeyCode::Up)],
+                down: vec![KeyBinding::plain(KeyCode::Down)],
+                left: vec![KeyBinding::plain(KeyCode::Left)],
+                right: vec![KeyBinding::plain(KeyCode::Right)],
+                bomb: vec![KeyBinding::plain(KeyCode::Enter)],
+                detonate: vec![KeyBinding::char('/')],
+            },
+            _ => Self::new('8', '5', '4', '6', KeyCode::Char('0'), '7'),
+        }
+    }
+
+    fn new(up: char, down: char, left: char, right: char, bomb: KeyCode, detonate: char) -> Self {
+        Self {
+            up: vec![KeyBinding::char(up)],
+            down: vec![KeyBinding::char(down)],
+            left: vec![KeyBinding::char(left)],
+            right: vec![KeyBinding::char(right)],
+            bomb: vec![KeyBinding::plain(bomb)],
+            detonate: vec![KeyBinding::char(detonate)],
+        }
+    }
+
+    pub fn action(&self, key: &KeyEvent) -> Option<Action> {
+        let pressed = |keys: &[KeyBinding]| keys.iter().any(|binding| binding.matches(key));
+        if pressed(&self.up) {
+            Some(Action::Move(Direction::Up))
+        } else if pressed(&self.down) {
+            Some(Action::Move(Direction::Down))
+        } else if pressed(&self.left) {
+            Some(Action::Move(Direction::Left))
+        } else if pressed(&self.right) {
+            Some(Action::Move(Direction::Right))
+        } else if pressed(&self.bomb) {
+            Some(Action::PlaceBomb)
+        } else if pressed(&self.detonate) {
+            Some(Action::Detonate)
+        } else {
+            None
+        }
+    }
+
+    pub fn move_keys(&self) -> String {
+        [&self.left, &self.down, &self.up, &self.right]
+            .into_iter()
+            .map(|keys| first_key(keys))
+            .collect()
+    }
+
+    pub fn bomb_key(&self) -> String {
+        first_key(&self.bomb)
+    }
+
+    pub fn detonate_key(&self) -> String {
+        first_key(&self.detonate)
+    }
+
+    pub fn describe(&self) -> String {
+        format!("{}+{}", self.move_keys(), self.bomb_key())
+    }
+}
+
+/// Every rebindable key: the players' controls and the keys that drive
+/// the app itself. Players left out of the config keep their defaults.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyMap {
+    pub players: Vec<PlayerBindings>,
+    pub pause: Vec<KeyBinding>,
+    /// Starts over after a game or match, or starts the next round.
+    pub restart: Vec<KeyBinding>,
+    pub quit: Vec<KeyBinding>,
+    /// Back to the match settings once a match is over.
+    pub menu: Vec<KeyBinding>,
+    /// The danger overlay.
+    pub danger: Vec<KeyBinding>,
+    /// Switches the board between two columns and one column per tile.
+    pub zoom: Vec<KeyBinding>,
+    pub minimap: Vec<KeyBinding>,
+    /// The full-screen map overlay.
+    pub full_map: Vec<KeyBinding>,
+    /// The message log panel.
+    pub log: Vec<KeyBinding>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        Self {
+            players: Vec::new(),
+            pause: vec![KeyBinding::char('p')],
+            restart: vec![KeyBinding::char('r')],
+            quit: vec![KeyBinding::char('q'), KeyBinding::plain(KeyCode::Esc)],
+            menu: vec![KeyBinding::char('s')],
+            danger: vec![KeyBinding::plain(KeyCode::F(3))],
+            zoom: vec![KeyBinding::plain(KeyCode::F(4))],
+            minimap: vec![KeyBinding::plain(KeyCode::F(5))],
+            full_map: vec![KeyBinding::plain(KeyCode::F(6))],
+            log: vec![KeyBinding::plain(KeyCode::F(7))],
+        }
+    }
+}
+
+impl KeyMap {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn player(&self, index: usize) -> PlayerBindings {
+        let defaults = PlayerBindings::for_player(index);
+        match self.players.get(index) {
+            Some(bindings) if bindings.detonate.is_empty() => PlayerBindings {
+                detonate: defaults.detonate,
+                ..bindings.clone()
+            },
+            Some(bindings) => bindings.clone(),
+            None => defaults,
+        }
+    }
+
+    pub fn is_pause(&self, key: &KeyEvent) -> bool {
+        self.pause.iter().any(|binding| binding.matches(key))
+    }
+
+    pub fn is_restart(&self, key: &KeyEvent) -> bool {
+        self.restart.iter().any(|binding| binding.matches(key))
+    }
+
+    pub fn is_quit(&self, key: &KeyEvent) -> bool {
+        self.quit.iter().any(|binding| binding.matches(key))
+    }
+
+    pub fn is_menu(&self, key: &KeyEvent) -> bool {
+        self.menu.iter().any(|binding| binding.matches(key))
+    }
+
+    pub fn is_danger(&self, key: &KeyEvent) -> bool {
+        self.danger.iter().any(|binding| binding.matches(key))
+    }
+
+    pub fn is_zoom(&self, key: &KeyEvent) -> bool {
+        self.zoom.iter().any(|binding| binding.matches(key))
+    }
+
+    pub fn is_minimap(&self, key: &KeyEvent) -> bool {
+        self.minimap.iter().any(|binding| binding.matches(key))
+    }
+
+    pub fn is_full_map(&self, key: &KeyEvent) -> bool {
+        self.full_map.iter().any(|binding| binding.matches(key))
+    }
+
+    pub fn is_log(&self, key: &KeyEvent) -> bool {
+        self.log.iter().any(|binding| binding.matches(key))
+    }
+
+    /// The keys of the view toggles for the HUD, e.g. `F3/F4/F5/F6/F7`, in
+    /// the order danger, zoom, minimap, full map, log.
+    pub fn view_keys(&self) -> String {
+        [&self.danger, &self.zoom, &self.minimap, &self.full_map, &self.log]
+            .into_iter()
+            .map(|keys| first_key(keys))
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// `"<key>: <what it does>"` for overlay footers.
+    pub fn hint(keys: &[KeyBinding], label: &str) -> String {
+        format!("{}: {}", first_key(keys), label)
+    }
+}
+
+fn first_key(keys: &[KeyBinding]) -> String {
+    keys.first().map(ToString::to_string).unwrap_or_else(|| "-".to_string())
+}
+
+pub fn key_name(code: KeyCode) -> String {
+    match code {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Up => "↑".to_string(),
+        KeyCode::Down => "↓".to_string(),
+        KeyCode::Left => "←".to_string(),
+        KeyCode::Right => "→".to_string(),
+        KeyCode::Enter => "Enter".to_string(),
+        KeyCode::Tab => "Tab".to_string(),
+        KeyCode::Esc => "Esc".to_string(),
+        KeyCode::F(number) => format!("F{}", number),
+        other => format!("{:?}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<KeyBinding, String> {
+        KeyBinding::try_from(text.to_string())
+    }
+
+    #[test]
+    fn modifiers_and_named_keys() {
+        assert_eq!(
+            parse("Ctrl+b"),
+            Ok(KeyBinding { code: KeyCode::Char('b'), modifiers: KeyModifiers::CONTROL })
+        );
+        assert_eq!(
+            parse("ctrl+alt+Left"),
+            Ok(KeyBinding { code: KeyCode::Left, modifiers: KeyModifiers::CONTROL | KeyModifiers::ALT })
+        );
+        assert_eq!(parse("F2"), Ok(KeyBinding::plain(KeyCode::F(2))));
+        assert_eq!(parse("f"), Ok(KeyBinding::char('f')));
+        assert_eq!(parse("Space"), Ok(KeyBinding::char(' ')));
+        assert_eq!(parse("Ctrl++").map(|key| key.code), Ok(KeyCode::Char('+')));
+    }
+
+    #[test]
+    fn shift_with_a_letter_is_the_capital() {
+        assert_eq!(parse("Shift+w"), Ok(KeyBinding::char('W')));
+        assert!(KeyBinding::char('W').matches(&KeyEvent::new(KeyCode::Char('W'), KeyModifiers::SHIFT)));
+        assert_eq!(
+            parse("Shift+Tab"),
+            Ok(KeyBinding { code: KeyCode::Tab, modifiers: KeyModifiers::SHIFT })
+        );
+    }
+
+    #[test]
+    fn unknown_keys_and_modifiers_are_errors() {
+        assert!(parse("Hyper+x").is_err());
+        assert!(parse("Up+").is_err());
+        assert!(parse("spacebar").is_err());
+    }
+
+    #[test]
+    fn saved_names_read_back_and_display_draws_arrows() {
+        let bindings = [
+            parse("Ctrl+b").unwrap(),
+            parse("Alt+Up").unwrap(),
+            parse("Space").unwrap(),
+            parse("F12").unwrap(),
+            parse("PageDown").unwrap(),
+            parse("Shift+Tab").unwrap(),
+        ];
+        for binding in bindings {
+            assert_eq!(parse(&String::from(binding)), Ok(binding));
+        }
+        assert_eq!(String::from(bindings[1]), "Alt+Up");
+        assert_eq!(bindings[1].to_string(), "Alt+↑");
+    }
+
+    fn press(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn a_config_without_detonate_keeps_the_default_key() {
+        let keys: KeyMap = ron::from_str(
+            r#"(players: [(up: ["w"], down: ["s"], left: ["a"], right: ["d"], bomb: ["Space"])])"#,
+        )
+        .unwrap();
+        let player = keys.player(0);
+        assert_eq!(player.action(&press(KeyCode::Char('w'))), Some(Action::Move(Direction::Up)));
+        assert_eq!(player.action(&press(KeyCode::Char('x'))), Some(Action::Detonate));
+        assert_eq!(keys.player(1), PlayerBindings::for_player(1));
+    }
+
+    #[test]
+    fn view_toggles_are_rebindable() {
+        let keys: KeyMap = ron::from_str(r#"(minimap: ["m"], log: ["F9", "Ctrl+l"])"#).unwrap();
+        assert!(keys.is_minimap(&press(KeyCode::Char('m'))));
+        assert!(!keys.is_minimap(&press(KeyCode::F(5))));
+        assert!(keys.is_log(&KeyEvent::new(KeyCode::Char('l'), KeyModifiers::CONTROL)));
+        assert!(keys.is_danger(&press(KeyCode::F(3))));
+        assert_eq!(keys.view_keys(), "F3/F4/m/F6/F9");
+    }
+}
@@ -1,34 +1,143 @@
 use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture},
+    event::{
+        DisableMouseCapture, EnableMouseCapture, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{
+        disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
 };
 use ratatui::{
     backend::CrosstermBackend,
     Terminal,
 };
 use std::io;
+use tokio::sync::oneshot;
 
 mod app;
+mod cli;
+mod command;
+mod config;
 mod event;
 mod game;
+mod input;
+mod keys;
+mod net;
 mod render;
+mod ssh;
 
 use app::App;
+use cli::{Command, HostOptions};
+use config::Config;
 use event::EventHandler;
+use net::{
+    client::NetClient,
+    loopback::{LoopbackConfig, LoopbackReport},
+    server::ServerConfig,
+};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let config = Config::load();
+    for key in input::shadowed_vim_keys(&config.keys) {
+        eprintln!("Warning: `{}` is bound in the config, so it no longer works as a Vim key", key);
+    }
+    let mut app = match cli::parse(std::env::args().skip(1)) {
+        Ok(Command::Play(setup)) => App::new(setup),
+        Ok(Command::Host(options)) => {
+            let config = server_config(&options);
+            if options.headless {
+                eprintln!("[server] listening on {}", config.addr);
+                net::server::run(config).await?;
+                return Ok(());
+            }
+            let (host_tx, mut host_rx) = oneshot::channel();
+            tokio::spawn(async move {
+                if let Err(err) = net::server::run(config).await {
+                    let _ = host_tx.send(err.to_string());
+                }
+            });
+            let addr = format!("127.0.0.1:{}", options.port);
+            let mut client = match NetClient::connect_with_retry(&addr, &options.name).await {
+                Ok(client) => client,
+                // Most likely the port was taken, which says more than the
+                // refused connection.
+                Err(err) => return Err(host_rx.try_recv().unwrap_or(err.to_string()).into()),
+            };
+            client.watch_host(host_rx);
+            App::networked(client)
+        }
+        Ok(Command::Join(options)) => match options.bot {
+            Some(difficulty) => {
+                net::client::run_bot(&options.addr, &options.name, difficulty).await?;
+                return Ok(());
+            }
+            None if options.spectate => {
+                App::spectating(NetClient::watch(&options.addr, &options.name).await?)
+            }
+            None => App::networked(NetClient::connect(&options.addr, &options.name).await?),
+        },
+        Ok(Command::Serve(options)) => {
+            ssh::run(ssh::ServeConfig {
+                addr: options.addr,
+                host_key: options.host_key,
+                authorized_keys: options.authorized_keys,
+                lobby: options.lobby.as_ref().map(server_config),
+            })
+            .await?;
+            return Ok(());
+        }
+        Ok(Command::Netsim(config)) => {
+            let report = net::loopback::run(&config);
+            print_netsim_report(&config, &report);
+            if report.desync.is_some() {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Ok(Command::Help) => {
+            println!("{}", cli::USAGE);
+            return Ok(());
+        }
+        Err(err) => {
+            eprintln!("Error: {}\n\n{}", err, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    app.set_theme(config.theme());
+    app.set_accessibility(config.accessibility());
+    app.set_keys(config.keys.clone());
+    app.set_glyphs(config.glyph_set());
+    app.set_can_save(true);
+
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    // Terminals that report key releases let a held key keep a bomber
+    // walking at its own speed instead of the key repeat rate.
+    let key_release = supports_keyboard_enhancement().unwrap_or(false);
+    if key_release {
+        execute!(
+            stdout,
+            PushKeyboardEnhancementFlags(
+                KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                    | KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+            )
+        )?;
+    }
+    app.set_key_release(key_release);
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let app = App::new();
     let event_handler = EventHandler::new();
     let res = run_app(&mut terminal, app, event_handler).await;
 
+    if key_release {
+        execute!(terminal.backend_mut(), PopKeyboardEnhancementFlags)?;
+    }
     disable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
@@ -51,15 +160,61 @@ async fn run_app<B: ratatui::backend::Backend>(
 ) -> io::Result<()> {
     loop {
         terminal.draw(|f| app.render(f))?;
+        event_handler.set_tick_period(app.tick_period());
 
-        if let Some(event) = event_handler.next().await {
-            if !app.handle_event(event) {
-                break;
+        match event_handler.next().await {
+            Some(event) => {
+                if !app.handle_event(event) {
+                    break;
+                }
             }
+            None => app.tick(),
         }
-
-        app.tick();
     }
 
     Ok(())
 }
+
+fn server_config(options: &HostOptions) -> ServerConfig {
+    ServerConfig {
+        addr: format!("0.0.0.0:{}", options.port),
+        max_players: 4,
+        auto_start: options.auto_start,
+        rules: options.rules.clone(),
+        netcode: options.netcode,
+        spectator_delay: (options.spectator_delay as f32 / net::TICK_SECONDS) as u64,
+        verbose: options.headless,
+    }
+}
+
+fn print_netsim_report(config: &LoopbackConfig, report: &LoopbackReport) {
+    println!(
+        "{} peers, {} frames, {}ms +{}ms latency, {:.0}% loss, seed {}",
+        config.players,
+        config.frames,
+        config.link.latency_ms,
+        config.link.jitter_ms,
+        config.link.loss * 100.0,
+        config.seed
+    );
+    println!(
+        "packets: {} sent, {} lost",
+        report.packets_sent, report.packets_lost
+    );
+    for (slot, peer) in report.peers.iter().enumerate() {
+        println!(
+            "peer {}: frame {}, confirmed {}, {} rollbacks ({} frames resimulated, deepest {}), {} stalled ticks",
+            slot + 1,
+            peer.frame,
+            peer.confirmed,
+            peer.stats.rollbacks,
+            peer.stats.resimulated_frames,
+            peer.stats.max_depth,
+            peer.stats.stalled_frames
+        );
+    }
+    match report.desync {
+        Some(frame) => println!("DESYNC at frame {}", frame),
+        None => println!("in sync over {} checked frames", report.checked_frames),
+    }
+}
@@ -0,0 +1,349 @@
+use std::collections::VecDeque;
+use std::io;
+use std::time::Duration;
+
+use tokio::{
+    net::TcpStream,
+    sync::{mpsc, oneshot},
+    time::interval,
+};
+
+use super::{
+    apply_frame,
+    protocol::{ClientMessage, Netcode, ServerMessage, PROTOCOL_VERSION},
+    rollback::RollbackSession,
+    spawn_connection, CHECKSUM_INTERVAL, INPUT_DELAY, TICK_SECONDS,
+};
+use crate::game::{
+    controller::{Action, Controller, Observation},
+    mode::{BattleRules, BomberSlot, Difficulty, GameSetup},
+    systems::bomber::BomberController,
+    GameState,
+};
+
+/// Client side of a networked session. In lockstep it queues local input,
+/// schedules it `INPUT_DELAY` frames ahead and reports checksums of applied
+/// frames; in rollback it drives a `RollbackSession` with that input and
+/// the inputs relayed from the other players.
+pub struct NetClient {
+    outgoing: mpsc::Sender<ClientMessage>,
+    incoming: mpsc::Receiver<ServerMessage>,
+    pub slot: Option<usize>,
+    /// Set once the host accepted us as a spectator, to the delay in ticks.
+    pub spectating: Option<u64>,
+    pub lobby: Vec<Option<String>>,
+    pub desync: Option<(u64, usize)>,
+    pub rejected: Option<String>,
+    pub disconnected: bool,
+    /// Why the server this app hosts stopped, once it has.
+    pub host_error: Option<String>,
+    host: Option<oneshot::Receiver<String>>,
+    queued: VecDeque<Action>,
+    sent_tick: u64,
+    applied_tick: u64,
+    rollback: Option<RollbackSession>,
+}
+
+impl NetClient {
+    pub async fn connect(addr: &str, name: &str) -> io::Result<Self> {
+        Self::open(
+            addr,
+            ClientMessage::Hello {
+                name: name.to_string(),
+                version: PROTOCOL_VERSION,
+            },
+        )
+        .await
+    }
+
+    /// An embedded server binds asynchronously, so its own clients may need
+    /// a few attempts before they can connect.
+    pub async fn connect_with_retry(addr: &str, name: &str) -> io::Result<Self> {
+        let mut attempts = 0;
+        loop {
+            match Self::connect(addr, name).await {
+                Ok(client) => return Ok(client),
+                Err(err) if attempts >= 20 => return Err(err),
+                Err(_) => {
+                    attempts += 1;
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                }
+            }
+        }
+    }
+
+    /// Connects as a spectator, who only receives the delayed match.
+    pub async fn watch(addr: &str, name: &str) -> io::Result<Self> {
+        Self::open(
+            addr,
+            ClientMessage::Watch {
+                name: name.to_string(),
+                version: PROTOCOL_VERSION,
+            },
+        )
+        .await
+    }
+
+    async fn open(addr: &str, hello: ClientMessage) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr).await?;
+        let (outgoing, incoming) = spawn_connection(stream);
+        let mut client = Self {
+            outgoing,
+            incoming,
+            slot: None,
+            spectating: None,
+            lobby: Vec::new(),
+            desync: None,
+            rejected: None,
+            disconnected: false,
+            host_error: None,
+            host: None,
+            queued: VecDeque::new(),
+            sent_tick: 0,
+            applied_tick: 0,
+            rollback: None,
+        };
+        client.send(hello);
+        Ok(client)
+    }
+
+    /// Hears about the failure of a server running in this process, so it
+    /// can be shown in the app rather than printed over it.
+    pub fn watch_host(&mut self, errors: oneshot::Receiver<String>) {
+        self.host = Some(errors);
+    }
+
+    /// Queues `message` for the server. A host that stops reading fills
+    /// the queue, and is then treated as gone.
+    pub fn send(&mut self, message: ClientMessage) {
+        if let Err(mpsc::error::TrySendError::Full(_)) = self.outgoing.try_send(message) {
+            self.disconnected = true;
+        }
+    }
+
+    /// Drains everything the server sent since the last call, keeping
+    /// track of connection-level messages itself.
+    pub fn poll(&mut self) -> Vec<ServerMessage> {
+        if let Some(host) = &mut self.host
+            && let Ok(err) = host.try_recv()
+        {
+            self.host_error = Some(err);
+            self.host = None;
+        }
+        let mut messages = Vec::new();
+        loop {
+            match self.incoming.try_recv() {
+                Ok(message) => {
+                    match &message {
+                        ServerMessage::Welcome { slot } => self.slot = Some(*slot),
+                        ServerMessage::Spectating { delay_ticks } => {
+                            self.spectating = Some(*delay_ticks)
+                        }
+                        ServerMessage::Lobby { players } => self.lobby = players.clone(),
+                        ServerMessage::Rejected { reason } => self.rejected = Some(reason.clone()),
+                        ServerMessage::Desync { tick, slot } => self.desync = Some((*tick, *slot)),
+                        ServerMessage::RoundStart {
+                            seed,
+                            players,
+                            rules,
+                            netcode,
+                            ..
+                        } => {
+                            self.queued.clear();
+                            self.sent_tick = 0;
+                            self.applied_tick = 0;
+                            // Started here rather than by the caller so that
+                            // inputs later in this batch are not lost.
+                            self.rollback = match (netcode, self.slot) {
+                                (Netcode::Rollback, Some(slot)) => Some(RollbackSession::new(
+                                    round_setup(*players, rules).build(*seed),
+                                    *players,
+                                    slot,
+                                )),
+                                _ => None,
+                            };
+                        }
+                        ServerMessage::Input { slot, tick, action } => {
+                            if let Some(session) = &mut self.rollback {
+                                session.add_remote_input(*slot, *tick, *action);
+                            }
+                        }
+                        ServerMessage::PlayerLeft { slot, tick } => {
+                            if let Some(session) = &mut self.rollback {
+                                session.player_left(*slot, *tick);
+                            }
+                        }
+                        ServerMessage::RoundOver { .. } => self.rollback = None,
+                        _ => {}
+                    }
+                    messages.push(message);
+                }
+                Err(mpsc::error::TryRecvError::Empty) => break,
+                Err(mpsc::error::TryRecvError::Disconnected) => {
+                    self.disconnected = true;
+                    break;
+                }
+            }
+        }
+        messages
+    }
+
+    pub fn queue_action(&mut self, action: Action) {
+        self.queued.push_back(action);
+    }
+
+    pub fn has_queued(&self) -> bool {
+        !self.queued.is_empty()
+    }
+
+    /// Sends one input per frame until the client is `INPUT_DELAY` frames
+    /// ahead of the last applied one.
+    pub fn send_inputs(&mut self) {
+        if self.spectating.is_some() {
+            return;
+        }
+        while self.sent_tick < self.applied_tick + INPUT_DELAY {
+            let action = self.queued.pop_front().unwrap_or(Action::Idle);
+            self.send(ClientMessage::Input {
+                tick: self.sent_tick,
+                action,
+            });
+            self.sent_tick += 1;
+        }
+    }
+
+    pub fn rollback(&self) -> Option<&RollbackSession> {
+        self.rollback.as_ref()
+    }
+
+    /// Feeds the next queued action into the rollback session, sends it to
+    /// the host and advances one frame. Returns whether the predicted state
+    /// changed.
+    pub fn advance_rollback(&mut self) -> bool {
+        let Some(session) = &mut self.rollback else {
+            return false;
+        };
+
+        let mut outgoing = Vec::new();
+        if session.needs_local_input() {
+            let action = self.queued.pop_front().unwrap_or(Action::Idle);
+            let tick = session.add_local_input(action);
+            outgoing.push(ClientMessage::Input { tick, action });
+        }
+        let advanced = session.advance();
+        for (tick, checksum) in session.take_checksums() {
+            outgoing.push(ClientMessage::Checksum { tick, checksum });
+        }
+
+        for message in outgoing {
+            self.send(message);
+        }
+        advanced
+    }
+
+    pub fn frame_applied(&mut self, tick: u64, state: &GameState) {
+        self.applied_tick = tick + 1;
+        if self.spectating.is_none() && tick.is_multiple_of(CHECKSUM_INTERVAL) {
+            self.send(ClientMessage::Checksum {
+                tick,
+                checksum: state.checksum(),
+            });
+        }
+    }
+}
+
+pub fn round_setup(players: usize, rules: &BattleRules) -> GameSetup {
+    GameSetup::Battle {
+        slots: vec![BomberSlot::Human; players],
+        rules: rules.clone(),
+    }
+}
+
+/// Headless client driven by the CPU bomber AI, for exercising a host from
+/// other processes. Logs round results to stdout and exits after the match.
+pub async fn run_bot(addr: &str, name: &str, difficulty: Difficulty) -> io::Result<()> {
+    let mut client = NetClient::connect(addr, name).await?;
+    let mut state: Option<GameState> = None;
+    let mut bot = BomberController::new(difficulty, rand::random());
+    let think_frames = (bot.think_interval().unwrap_or(TICK_SECONDS) / TICK_SECONDS).ceil() as u64;
+    let mut ticker = interval(Duration::from_secs_f32(TICK_SECONDS));
+
+    loop {
+        ticker.tick().await;
+
+        for message in client.poll() {
+            match message {
+                ServerMessage::Welcome { slot } => println!("{}: joined as player {}", name, slot + 1),
+                ServerMessage::RoundStart {
+                    seed,
+                    players,
+                    round,
+                    rules,
+                    ..
+                } => {
+                    println!("{}: round {} started", name, round);
+                    state = Some(round_setup(players, &rules).build(seed));
+                }
+                ServerMessage::Frame { tick, actions, left } => {
+                    if let Some(state) = &mut state {
+                        apply_frame(state, &actions, &left);
+                        client.frame_applied(tick, state);
+
+                        let me = client.slot.and_then(|slot| state.players.get(slot).copied());
+                        if let Some(entity_id) = me
+                            && tick.is_multiple_of(think_frames)
+                        {
+                            let danger = state.danger_map();
+                            let action = bot.decide(&Observation {
+                                state,
+                                danger: &danger,
+                                entity_id,
+                            });
+                            client.queue_action(action);
+                        }
+                    }
+                }
+                ServerMessage::RoundOver { winner } => {
+                    println!("{}: round over, winner: {:?}", name, winner.map(|s| s + 1));
+                    state = None;
+                }
+                ServerMessage::MatchOver { champion } => {
+                    println!("{}: match over, champion: {:?}", name, champion.map(|s| s + 1));
+                    return Ok(());
+                }
+                ServerMessage::Desync { tick, slot } => {
+                    println!("{}: desync of player {} at tick {}", name, slot + 1, tick);
+                }
+                ServerMessage::Rejected { reason } => {
+                    return Err(io::Error::other(reason));
+                }
+                ServerMessage::Lobby { .. }
+                | ServerMessage::Spectating { .. }
+                | ServerMessage::Input { .. }
+                | ServerMessage::PlayerLeft { .. } => {}
+            }
+        }
+
+        if client.disconnected {
+            return Err(io::Error::new(io::ErrorKind::ConnectionAborted, "host closed the connection"));
+        }
+        if client.rollback().is_some() {
+            if let Some(session) = client.rollback()
+                && let Some(entity_id) = session.state().players.get(session.local_slot()).copied()
+                && session.needs_local_input()
+                && session.frame().is_multiple_of(think_frames)
+            {
+                let danger = session.state().danger_map();
+                let action = bot.decide(&Observation {
+                    state: session.state(),
+                    danger: &danger,
+                    entity_id,
+                });
+                client.queue_action(action);
+            }
+            client.advance_rollback();
+        } else if state.is_some() {
+            client.send_inputs();
+        }
+    }
+}
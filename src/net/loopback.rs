@@ -0,0 +1,248 @@
+use std::collections::BTreeMap;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::{
+    client::round_setup,
+    rollback::{RollbackSession, RollbackStats, LOCAL_INPUT_DELAY},
+    TICK_SECONDS,
+};
+use crate::game::{
+    controller::{Action, Controller, Observation},
+    mode::{BattleRules, Difficulty},
+    systems::bomber::BomberController,
+};
+
+/// Conditions of the simulated link between every pair of peers.
+#[derive(Debug, Clone)]
+pub struct LinkConfig {
+    pub latency_ms: u32,
+    pub jitter_ms: u32,
+    /// Chance of a packet being dropped, from 0 to 1.
+    pub loss: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct LoopbackConfig {
+    pub players: usize,
+    pub frames: u64,
+    pub link: LinkConfig,
+    pub seed: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct PeerReport {
+    pub frame: u64,
+    pub confirmed: u64,
+    pub stats: RollbackStats,
+}
+
+#[derive(Debug, Clone)]
+pub struct LoopbackReport {
+    pub peers: Vec<PeerReport>,
+    pub packets_sent: u64,
+    pub packets_lost: u64,
+    /// Confirmed frames whose checksum every peer reported.
+    pub checked_frames: usize,
+    /// First confirmed frame on which two peers disagreed.
+    pub desync: Option<u64>,
+}
+
+/// Inputs a peer has not seen acknowledged yet, resent in every packet so
+/// that a single lost packet never loses input, plus the sender's ack.
+struct Packet {
+    from: usize,
+    to: usize,
+    inputs: Vec<(u64, Action)>,
+    ack: Option<u64>,
+}
+
+struct Peer {
+    session: RollbackSession,
+    bot: BomberController,
+    think_frames: u64,
+    sent: BTreeMap<u64, Action>,
+    /// Highest own frame each other peer has acknowledged.
+    acked: Vec<Option<u64>>,
+    /// Highest frame received from each other peer with no gaps before it.
+    received: Vec<Option<u64>>,
+    checksums: BTreeMap<u64, u64>,
+}
+
+/// Runs CPU bombers on rollback sessions that talk over a simulated lossy,
+/// laggy link, all in one process and driven by one seed, then compares
+/// the confirmed checksums of every peer.
+///
+/// The peers talk to each other directly, with their own resends and acks.
+/// Real rollback games relay inputs through the host over TCP instead, so
+/// this checks determinism and rollback under bad timing, not the relay.
+pub fn run(config: &LoopbackConfig) -> LoopbackReport {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let state = round_setup(config.players, &BattleRules::default()).build(rng.random());
+
+    let mut peers: Vec<Peer> = (0..config.players)
+        .map(|slot| {
+            let bot = BomberController::new(Difficulty::Normal, rng.random());
+            let think_frames =
+                (bot.think_interval().unwrap_or(TICK_SECONDS) / TICK_SECONDS).ceil() as u64;
+            Peer {
+                session: RollbackSession::new(state.clone(), config.players, slot),
+                bot,
+                think_frames,
+                sent: BTreeMap::new(),
+                acked: vec![None; config.players],
+                received: vec![None; config.players],
+                checksums: BTreeMap::new(),
+            }
+        })
+        .collect();
+
+    let frame_ms = TICK_SECONDS * 1000.0;
+    let mut in_flight: Vec<(u64, Packet)> = Vec::new();
+    let mut packets_sent = 0;
+    let mut packets_lost = 0;
+
+    for now in 0..config.frames {
+        let (arrived, pending): (Vec<_>, Vec<_>) =
+            in_flight.into_iter().partition(|(at, _)| *at <= now);
+        in_flight = pending;
+        for (_, packet) in arrived {
+            receive(&mut peers[packet.to], packet);
+        }
+
+        for (slot, peer) in peers.iter_mut().enumerate() {
+            if peer.session.needs_local_input() {
+                let action = think(peer);
+                let frame = peer.session.add_local_input(action);
+                peer.sent.insert(frame, action);
+            }
+
+            for to in (0..config.players).filter(|to| *to != slot) {
+                let inputs = peer
+                    .sent
+                    .range(peer.acked[to].map_or(0, |a| a + 1)..)
+                    .map(|(frame, action)| (*frame, *action))
+                    .collect();
+                let packet = Packet {
+                    from: slot,
+                    to,
+                    inputs,
+                    ack: peer.received[to],
+                };
+                packets_sent += 1;
+                if rng.random::<f32>() < config.link.loss {
+                    packets_lost += 1;
+                    continue;
+                }
+                let delay = config.link.latency_ms + rng.random_range(0..=config.link.jitter_ms);
+                let frames = (delay as f32 / frame_ms).ceil() as u64;
+                in_flight.push((now + frames.max(1), packet));
+            }
+
+            peer.session.advance();
+            peer.checksums.extend(peer.session.take_checksums());
+        }
+    }
+
+    let first = &peers[0].checksums;
+    let checked: Vec<u64> = first
+        .keys()
+        .copied()
+        .filter(|frame| peers.iter().all(|p| p.checksums.contains_key(frame)))
+        .collect();
+    let desync = checked
+        .iter()
+        .copied()
+        .find(|frame| peers.iter().any(|p| p.checksums[frame] != first[frame]));
+
+    LoopbackReport {
+        peers: peers
+            .iter()
+            .map(|peer| PeerReport {
+                frame: peer.session.frame(),
+                confirmed: peer.session.confirmed_frame(),
+                stats: peer.session.stats.clone(),
+            })
+            .collect(),
+        packets_sent,
+        packets_lost,
+        checked_frames: checked.len(),
+        desync,
+    }
+}
+
+/// The bot plays on its peer's predicted state, like a human would.
+fn think(peer: &mut Peer) -> Action {
+    let session = &peer.session;
+    if !session.frame().is_multiple_of(peer.think_frames) {
+        return Action::Idle;
+    }
+    let Some(&entity_id) = session.state().players.get(session.local_slot()) else {
+        return Action::Idle;
+    };
+    let danger = session.state().danger_map();
+    peer.bot.decide(&Observation {
+        state: session.state(),
+        danger: &danger,
+        entity_id,
+    })
+}
+
+fn receive(peer: &mut Peer, packet: Packet) {
+    if let Some(ack) = packet.ack
+        && peer.acked[packet.from].is_none_or(|acked| ack > acked)
+    {
+        peer.acked[packet.from] = Some(ack);
+        let local = peer.session.local_slot();
+        let unacked = (0..peer.acked.len())
+            .filter(|slot| *slot != local)
+            .map(|slot| peer.acked[slot].map_or(0, |a| a + 1))
+            .min();
+        if let Some(unacked) = unacked {
+            peer.sent = peer.sent.split_off(&unacked);
+        }
+    }
+
+    for (frame, action) in packet.inputs {
+        peer.session.add_remote_input(packet.from, frame, action);
+        let next = peer.received[packet.from].map_or(LOCAL_INPUT_DELAY, |r| r + 1);
+        if frame == next {
+            peer.received[packet.from] = Some(frame);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(players: usize, seed: u64) -> LoopbackConfig {
+        LoopbackConfig {
+            players,
+            frames: 600,
+            link: LinkConfig {
+                latency_ms: 80,
+                jitter_ms: 20,
+                loss: 0.1,
+            },
+            seed,
+        }
+    }
+
+    #[test]
+    fn peers_stay_in_sync_over_a_lossy_link() {
+        for seed in [1, 2, 3] {
+            let report = run(&config(2, seed));
+            assert!(report.checked_frames > 0, "seed {}: nothing was checked", seed);
+            assert!(report.packets_lost > 0, "seed {}: the link lost nothing", seed);
+            assert_eq!(report.desync, None, "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn four_peers_stay_in_sync() {
+        let report = run(&config(4, 7));
+        assert!(report.peers.iter().all(|peer| peer.stats.rollbacks > 0));
+        assert_eq!(report.desync, None);
+    }
+}
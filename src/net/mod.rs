@@ -0,0 +1,80 @@
+pub mod client;
+pub mod loopback;
+pub mod protocol;
+pub mod rollback;
+pub mod server;
+
+use std::time::Duration;
+
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::{net::TcpStream, sync::mpsc};
+
+use crate::game::{controller::Action, GameState};
+
+pub const DEFAULT_PORT: u16 = 7777;
+/// Simulated seconds per lockstep frame; matches the local tick rate.
+pub const TICK_SECONDS: f32 = 0.05;
+/// Frames a client's input is scheduled ahead, hiding round-trip latency.
+pub const INPUT_DELAY: u64 = 3;
+/// Clients report a state checksum every this many frames.
+pub const CHECKSUM_INTERVAL: u64 = 20;
+/// How far behind the players spectators watch by default, in seconds.
+pub const SPECTATOR_DELAY_SECONDS: u64 = 3;
+/// Messages that may wait to be written to a peer. A spectator joining
+/// mid-round is sent every frame of the round so far, so this allows for a
+/// few minutes of frames; a peer that falls further behind is dropped.
+pub const OUTGOING_QUEUE: usize = 8192;
+/// Messages read from a peer but not yet handled. Once it is full we stop
+/// reading, and TCP makes the peer wait.
+pub const INCOMING_QUEUE: usize = 256;
+/// Pause after a failed `accept`, so that running out of file descriptors
+/// does not turn the accept loop into a busy loop.
+pub const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Advances `state` by one lockstep frame. Every peer calls this with the
+/// same frames in the same order, which keeps their simulations identical.
+pub fn apply_frame(state: &mut GameState, actions: &[Action], left: &[usize]) {
+    for &slot in left {
+        state.eliminate_player(slot);
+    }
+    for (slot, action) in actions.iter().enumerate() {
+        if let Some(&entity_id) = state.players.get(slot) {
+            state.apply_action(entity_id, *action);
+        }
+    }
+    state.tick(TICK_SECONDS);
+}
+
+/// Splits a TCP stream into a pair of message channels. The receiver closes
+/// when the peer disconnects or sends something unreadable. Both channels
+/// are bounded: senders use `try_send` and treat a full queue as a peer
+/// that cannot keep up.
+pub fn spawn_connection<In, Out>(stream: TcpStream) -> (mpsc::Sender<Out>, mpsc::Receiver<In>)
+where
+    In: DeserializeOwned + Send + 'static,
+    Out: Serialize + Send + Sync + 'static,
+{
+    let _ = stream.set_nodelay(true);
+    let (reader, mut writer) = stream.into_split();
+    let (out_tx, mut out_rx) = mpsc::channel::<Out>(OUTGOING_QUEUE);
+    let (in_tx, in_rx) = mpsc::channel::<In>(INCOMING_QUEUE);
+
+    tokio::spawn(async move {
+        while let Some(message) = out_rx.recv().await {
+            if protocol::write_message(&mut writer, &message).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    tokio::spawn(async move {
+        let mut lines = protocol::lines(reader);
+        while let Ok(Some(message)) = protocol::read_message::<In>(&mut lines).await {
+            if in_tx.send(message).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    (out_tx, in_rx)
+}
@@ -0,0 +1,169 @@
+use std::io;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+    net::tcp::OwnedReadHalf,
+};
+
+use crate::game::{controller::Action, mode::BattleRules};
+
+pub const PROTOCOL_VERSION: u32 = 8;
+/// Longest message line a peer may send. Real messages are a few hundred
+/// bytes; anything past this is garbage or an attempt to exhaust memory.
+pub const MAX_LINE_BYTES: usize = 64 * 1024;
+
+/// How a round keeps the peers in sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Netcode {
+    /// The host gathers every input and sends out whole frames; clients
+    /// never simulate ahead of it.
+    Lockstep,
+    /// The host relays inputs as they arrive; clients predict missing ones
+    /// and roll back when a prediction turns out wrong.
+    Rollback,
+}
+
+impl Netcode {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "lockstep" => Some(Self::Lockstep),
+            "rollback" => Some(Self::Rollback),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ClientMessage {
+    Hello { name: String, version: u32 },
+    /// Joins as a read-only spectator instead of a player.
+    Watch { name: String, version: u32 },
+    /// Sent by the player in slot 0 to leave the lobby.
+    StartMatch,
+    Input { tick: u64, action: Action },
+    Checksum { tick: u64, checksum: u64 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ServerMessage {
+    Welcome { slot: usize },
+    /// Accepted as a spectator; the game arrives `delay_ticks` late.
+    Spectating { delay_ticks: u64 },
+    Rejected { reason: String },
+    Lobby { players: Vec<Option<String>> },
+    RoundStart {
+        seed: u64,
+        players: usize,
+        round: u32,
+        rules: BattleRules,
+        netcode: Netcode,
+    },
+    /// Every player's input for one simulation tick, plus the slots whose
+    /// connection dropped before it.
+    Frame {
+        tick: u64,
+        actions: Vec<Action>,
+        left: Vec<usize>,
+    },
+    /// Another player's input, relayed as soon as it arrives (rollback).
+    Input { slot: usize, tick: u64, action: Action },
+    /// A player dropped; they are eliminated at `tick` (rollback).
+    PlayerLeft { slot: usize, tick: u64 },
+    /// `winner` is a side: a player slot, or a team in a team battle.
+    RoundOver { winner: Option<usize> },
+    MatchOver { champion: Option<usize> },
+    Desync { tick: u64, slot: usize },
+}
+
+/// Messages travel as one line of RON each.
+pub async fn write_message<W, T>(writer: &mut W, message: &T) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize,
+{
+    let mut line = ron::to_string(message).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await
+}
+
+/// Reads newline-terminated messages, refusing lines longer than
+/// `MAX_LINE_BYTES` instead of buffering them without end.
+pub struct LineReader<R> {
+    reader: BufReader<R>,
+    line: Vec<u8>,
+}
+
+impl<R: AsyncRead + Unpin> LineReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            line: Vec::new(),
+        }
+    }
+
+    /// The next line without its newline, or `None` at the end of the
+    /// stream. A line over the limit is an `InvalidData` error.
+    pub async fn next_line(&mut self) -> io::Result<Option<&[u8]>> {
+        self.line.clear();
+        let limit = MAX_LINE_BYTES as u64 + 1;
+        let read = (&mut self.reader).take(limit).read_until(b'\n', &mut self.line).await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        if self.line.last() == Some(&b'\n') {
+            self.line.pop();
+        } else if self.line.len() > MAX_LINE_BYTES {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "message line too long"));
+        }
+        Ok(Some(&self.line))
+    }
+}
+
+pub async fn read_message<T: DeserializeOwned>(
+    lines: &mut LineReader<OwnedReadHalf>,
+) -> io::Result<Option<T>> {
+    match lines.next_line().await? {
+        Some(line) => ron::de::from_bytes(line)
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+        None => Ok(None),
+    }
+}
+
+pub fn lines(reader: OwnedReadHalf) -> LineReader<OwnedReadHalf> {
+    LineReader::new(reader)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn lines_past_the_limit_are_refused() {
+        let (mut writer, reader) = tokio::io::duplex(1024);
+        tokio::spawn(async move {
+            writer.write_all(b"short\n").await?;
+            writer.write_all(&vec![b'x'; MAX_LINE_BYTES + 10]).await?;
+            writer.write_all(b"\n").await
+        });
+        let mut lines = LineReader::new(reader);
+
+        assert_eq!(lines.next_line().await.unwrap(), Some(&b"short"[..]));
+        let err = lines.next_line().await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn a_line_of_exactly_the_limit_is_read() {
+        let (mut writer, reader) = tokio::io::duplex(1024);
+        tokio::spawn(async move {
+            writer.write_all(&vec![b'x'; MAX_LINE_BYTES]).await?;
+            writer.write_all(b"\n").await
+        });
+        let mut lines = LineReader::new(reader);
+
+        assert_eq!(lines.next_line().await.unwrap().map(<[u8]>::len), Some(MAX_LINE_BYTES));
+        assert_eq!(lines.next_line().await.unwrap(), None);
+    }
+}
@@ -0,0 +1,323 @@
+use std::collections::BTreeMap;
+
+use super::{apply_frame, CHECKSUM_INTERVAL};
+use crate::game::{controller::Action, GameState};
+
+/// Frames the simulation may run ahead of the last fully confirmed frame
+/// before it stalls and waits for remote input.
+pub const MAX_ROLLBACK: u64 = 12;
+/// Frames local input is scheduled ahead; small, to keep rollbacks shallow
+/// without adding the full round-trip delay lockstep needs.
+pub const LOCAL_INPUT_DELAY: u64 = 1;
+
+/// GGPO-style rollback session. Frames are simulated immediately with
+/// predicted input for remote players; when a remote input arrives that
+/// differs from the prediction, the state is restored from the snapshot
+/// taken before that frame and resimulated.
+pub struct RollbackSession {
+    state: GameState,
+    frame: u64,
+    local_slot: usize,
+    snapshots: BTreeMap<u64, GameState>,
+    used: BTreeMap<u64, Vec<Action>>,
+    inputs: Vec<BTreeMap<u64, Action>>,
+    departed: Vec<Option<u64>>,
+    pending_rollback: Option<u64>,
+    confirmed: u64,
+    checksums: Vec<(u64, u64)>,
+    pub stats: RollbackStats,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RollbackStats {
+    pub rollbacks: u64,
+    pub resimulated_frames: u64,
+    pub max_depth: u64,
+    pub stalled_frames: u64,
+}
+
+impl RollbackSession {
+    pub fn new(state: GameState, players: usize, local_slot: usize) -> Self {
+        // Nobody can have sent input for the first frames; the host treats
+        // them as idle too.
+        let mut inputs = vec![BTreeMap::new(); players];
+        for slot_inputs in &mut inputs {
+            for frame in 0..LOCAL_INPUT_DELAY {
+                slot_inputs.insert(frame, Action::Idle);
+            }
+        }
+
+        Self {
+            state,
+            frame: 0,
+            local_slot,
+            snapshots: BTreeMap::new(),
+            used: BTreeMap::new(),
+            inputs,
+            departed: vec![None; players],
+            pending_rollback: None,
+            confirmed: 0,
+            checksums: Vec::new(),
+            stats: RollbackStats::default(),
+        }
+    }
+
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub fn local_slot(&self) -> usize {
+        self.local_slot
+    }
+
+    /// First frame for which some player's input is still unknown.
+    pub fn confirmed_frame(&self) -> u64 {
+        self.confirmed
+    }
+
+    pub fn can_advance(&self) -> bool {
+        self.frame < self.confirmed + MAX_ROLLBACK
+    }
+
+    /// Whether the next `advance` wants a new local input first. Each frame
+    /// takes exactly one, and an input once sent must never change.
+    pub fn needs_local_input(&self) -> bool {
+        self.can_advance()
+            && !self.inputs[self.local_slot].contains_key(&(self.frame + LOCAL_INPUT_DELAY))
+    }
+
+    /// Records the local player's input for the frame `LOCAL_INPUT_DELAY`
+    /// ahead and returns that frame so it can be sent to the other peers.
+    pub fn add_local_input(&mut self, action: Action) -> u64 {
+        let frame = self.frame + LOCAL_INPUT_DELAY;
+        self.inputs[self.local_slot].insert(frame, action);
+        self.update_confirmed();
+        frame
+    }
+
+    pub fn add_remote_input(&mut self, slot: usize, frame: u64, action: Action) {
+        let Some(inputs) = self.inputs.get_mut(slot) else {
+            return;
+        };
+        if frame < self.confirmed || inputs.contains_key(&frame) {
+            return;
+        }
+        inputs.insert(frame, action);
+
+        if let Some(used) = self.used.get(&frame)
+            && used[slot] != action
+        {
+            self.request_rollback(frame);
+        }
+        self.update_confirmed();
+    }
+
+    /// Removes a player from `frame` on; their input counts as idle after.
+    pub fn player_left(&mut self, slot: usize, frame: u64) {
+        if slot >= self.departed.len() || self.departed[slot].is_some() {
+            return;
+        }
+        self.departed[slot] = Some(frame);
+        if frame < self.frame {
+            self.request_rollback(frame);
+        }
+        self.update_confirmed();
+    }
+
+    /// Resolves any pending rollback, then simulates one new frame unless
+    /// the session is too far ahead of confirmed input. Returns whether a
+    /// new frame was simulated.
+    pub fn advance(&mut self) -> bool {
+        if let Some(target) = self.pending_rollback.take()
+            && let Some(snapshot) = self.snapshots.get(&target)
+        {
+            let end = self.frame;
+            let depth = end - target;
+            self.state = snapshot.clone();
+            self.frame = target;
+            self.stats.rollbacks += 1;
+            self.stats.resimulated_frames += depth;
+            self.stats.max_depth = self.stats.max_depth.max(depth);
+            while self.frame < end {
+                self.simulate_frame();
+            }
+        }
+
+        self.record_confirmed();
+
+        if !self.can_advance() {
+            self.stats.stalled_frames += 1;
+            return false;
+        }
+        self.simulate_frame();
+        true
+    }
+
+    /// Checksums of confirmed frames not yet handed out, for desync checks.
+    pub fn take_checksums(&mut self) -> Vec<(u64, u64)> {
+        std::mem::take(&mut self.checksums)
+    }
+
+    fn simulate_frame(&mut self) {
+        let frame = self.frame;
+        self.snapshots.insert(frame, self.state.clone());
+
+        let actions: Vec<Action> = (0..self.inputs.len())
+            .map(|slot| self.input_for(slot, frame).unwrap_or(Action::Idle))
+            .collect();
+        let left: Vec<usize> = (0..self.departed.len())
+            .filter(|slot| self.departed[*slot] == Some(frame))
+            .collect();
+
+        apply_frame(&mut self.state, &actions, &left);
+        self.used.insert(frame, actions);
+        self.frame += 1;
+    }
+
+    /// Known input for `slot` at `frame`. Unknown remote input is predicted
+    /// as idle: keyboard input is sparse, so "nothing pressed" is right far
+    /// more often than repeating the last action.
+    fn input_for(&self, slot: usize, frame: u64) -> Option<Action> {
+        if self.departed[slot].is_some_and(|from| frame >= from) {
+            return Some(Action::Idle);
+        }
+        self.inputs[slot].get(&frame).copied()
+    }
+
+    fn request_rollback(&mut self, frame: u64) {
+        self.pending_rollback = Some(self.pending_rollback.map_or(frame, |f| f.min(frame)));
+    }
+
+    fn update_confirmed(&mut self) {
+        while (0..self.inputs.len()).all(|slot| self.input_for(slot, self.confirmed).is_some()) {
+            self.confirmed += 1;
+        }
+    }
+
+    /// Checksums newly confirmed frames and drops snapshots no rollback
+    /// can reach any more.
+    fn record_confirmed(&mut self) {
+        let settled = self.confirmed.min(self.frame);
+        let first_kept = self.snapshots.keys().next().copied().unwrap_or(settled);
+
+        for frame in first_kept..settled {
+            let after = frame + 1;
+            if frame.is_multiple_of(CHECKSUM_INTERVAL) {
+                let state = self.snapshots.get(&after).unwrap_or(&self.state);
+                self.checksums.push((frame, state.checksum()));
+            }
+        }
+
+        self.snapshots = self.snapshots.split_off(&settled);
+        self.used = self.used.split_off(&settled);
+        for inputs in &mut self.inputs {
+            *inputs = inputs.split_off(&settled);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{
+        mode::{BattleRules, BomberSlot},
+        stage::loader::battle_stages,
+    };
+
+    fn session() -> RollbackSession {
+        let slots = [BomberSlot::Human; 2];
+        let state = GameState::new_battle(battle_stages().remove(0), &slots, BattleRules::default(), 5);
+        RollbackSession::new(state, 2, 0)
+    }
+
+    /// Runs `frames` frames with the local player idle.
+    fn run(session: &mut RollbackSession, frames: usize) {
+        for _ in 0..frames {
+            if session.needs_local_input() {
+                session.add_local_input(Action::Idle);
+            }
+            session.advance();
+        }
+    }
+
+    #[test]
+    fn a_misprediction_rolls_back_to_the_true_state() {
+        let mut late = session();
+        run(&mut late, 5);
+        late.add_remote_input(1, 1, Action::Idle);
+        late.add_remote_input(1, 2, Action::PlaceBomb);
+        run(&mut late, 1);
+
+        let mut on_time = session();
+        on_time.add_remote_input(1, 1, Action::Idle);
+        on_time.add_remote_input(1, 2, Action::PlaceBomb);
+        run(&mut on_time, 6);
+
+        assert_eq!(late.stats.rollbacks, 1);
+        assert_eq!(late.stats.max_depth, 3);
+        assert_eq!(on_time.stats.rollbacks, 0);
+        assert_eq!(late.frame(), on_time.frame());
+        assert_eq!(late.state().checksum(), on_time.state().checksum());
+    }
+
+    #[test]
+    fn a_correct_prediction_needs_no_rollback() {
+        let mut session = session();
+        run(&mut session, 5);
+        for frame in 1..5 {
+            session.add_remote_input(1, frame, Action::Idle);
+        }
+        run(&mut session, 1);
+
+        assert_eq!(session.stats.rollbacks, 0);
+        assert_eq!(session.confirmed_frame(), 5);
+    }
+
+    #[test]
+    fn input_older_than_the_snapshots_is_ignored() {
+        let mut late = session();
+        for frame in 1..4 {
+            late.add_remote_input(1, frame, Action::Idle);
+        }
+        run(&mut late, 6);
+
+        late.add_remote_input(1, 2, Action::PlaceBomb);
+        run(&mut late, 1);
+
+        assert_eq!(late.stats.rollbacks, 0);
+        let mut replay = session();
+        for frame in 1..4 {
+            replay.add_remote_input(1, frame, Action::Idle);
+        }
+        run(&mut replay, 7);
+        assert_eq!(late.state().checksum(), replay.state().checksum());
+    }
+
+    #[test]
+    fn a_player_leaving_mid_prediction_is_replayed_from_that_frame() {
+        let mut session = session();
+        session.add_remote_input(1, 1, Action::Idle);
+        run(&mut session, 5);
+        assert_eq!(session.confirmed_frame(), 2);
+
+        session.player_left(1, 2);
+        run(&mut session, 1);
+
+        assert_eq!(session.stats.rollbacks, 1);
+        assert_eq!(session.state().alive_players().len(), 1);
+        assert!(session.confirmed_frame() > 5);
+    }
+
+    #[test]
+    fn the_session_stalls_when_too_far_ahead() {
+        let mut session = session();
+        run(&mut session, MAX_ROLLBACK as usize + 4);
+
+        assert_eq!(session.frame(), MAX_ROLLBACK + 1);
+        assert!(session.stats.stalled_frames > 0);
+    }
+}
@@ -0,0 +1,863 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::io;
+use std::time::Duration;
+
+use tokio::{
+    net::TcpListener,
+    sync::mpsc,
+    time::{interval, sleep},
+};
+
+use super::{
+    apply_frame,
+    protocol::{ClientMessage, Netcode, ServerMessage, PROTOCOL_VERSION},
+    rollback::LOCAL_INPUT_DELAY,
+    spawn_connection, ACCEPT_BACKOFF, CHECKSUM_INTERVAL, INCOMING_QUEUE, TICK_SECONDS,
+};
+use crate::game::{
+    controller::Action,
+    mode::{BattleRules, BomberSlot, GameSetup, MatchState, RoundStats},
+    GameState,
+};
+
+/// Frames shown between the end of a round and the start of the next.
+const INTERMISSION_FRAMES: u32 = 60;
+/// Furthest ahead of the simulation an input or checksum may be. Honest
+/// clients stay within `INPUT_DELAY` frames in lockstep and `MAX_ROLLBACK`
+/// in rollback; anything beyond is dropped rather than stored.
+const INPUT_WINDOW: u64 = 64;
+/// Ten seconds without the input the round is waiting for, after which the
+/// silent players are dropped so everyone else can carry on.
+const INPUT_TIMEOUT_FRAMES: u32 = 200;
+
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub addr: String,
+    pub max_players: usize,
+    /// Start the match as soon as this many players have joined.
+    pub auto_start: Option<usize>,
+    pub rules: BattleRules,
+    pub netcode: Netcode,
+    /// Ticks spectators lag behind the players.
+    pub spectator_delay: u64,
+    pub verbose: bool,
+}
+
+enum Event {
+    Connected(usize, mpsc::Sender<ServerMessage>),
+    Message(usize, ClientMessage),
+    Disconnected(usize),
+}
+
+struct Peer {
+    tx: mpsc::Sender<ServerMessage>,
+    name: Option<String>,
+    slot: Option<usize>,
+    spectator: bool,
+    /// Set once the peer's outgoing queue overflowed; it is dropped as soon
+    /// as the current event or tick has been handled.
+    lagging: bool,
+}
+
+impl Peer {
+    fn send(&mut self, message: ServerMessage) {
+        if let Err(mpsc::error::TrySendError::Full(_)) = self.tx.try_send(message) {
+            self.lagging = true;
+        }
+    }
+}
+
+/// The match as spectators see it: every message is held back for
+/// `spectator_delay` ticks, and what has been released so far is kept so
+/// that late spectators can catch up.
+#[derive(Default)]
+struct SpectatorFeed {
+    clock: u64,
+    delayed: VecDeque<(u64, ServerMessage)>,
+    lobby: Option<ServerMessage>,
+    /// Round results of the current match plus the frames of the round
+    /// being played.
+    replay: Vec<ServerMessage>,
+}
+
+impl SpectatorFeed {
+    fn push(&mut self, message: ServerMessage, delay: u64) {
+        self.delayed.push_back((self.clock + delay, message));
+    }
+
+    /// Advances the clock and returns the messages that are due.
+    fn release(&mut self) -> Vec<ServerMessage> {
+        self.clock += 1;
+        let mut released = Vec::new();
+        while let Some((at, _)) = self.delayed.front()
+            && *at <= self.clock
+        {
+            let Some((_, message)) = self.delayed.pop_front() else {
+                break;
+            };
+            match &message {
+                ServerMessage::Lobby { .. } => self.lobby = Some(message.clone()),
+                ServerMessage::RoundStart { round, .. } => {
+                    if *round == 1 {
+                        self.replay.clear();
+                    }
+                    self.replay.retain(|m| !matches!(m, ServerMessage::Frame { .. }));
+                    self.replay.push(message.clone());
+                }
+                _ => self.replay.push(message.clone()),
+            }
+            released.push(message);
+        }
+        released
+    }
+
+    /// Everything a spectator joining now needs to reach the current
+    /// delayed point.
+    fn catch_up(&self) -> impl Iterator<Item = &ServerMessage> {
+        self.lobby.iter().chain(&self.replay)
+    }
+}
+
+struct Round {
+    state: GameState,
+    next_tick: u64,
+    inputs: BTreeMap<u64, Vec<Option<Action>>>,
+    /// Tick from which each player counts as gone, once they disconnect.
+    departed: Vec<Option<u64>>,
+    last_input: Vec<Option<u64>>,
+    checksums: BTreeMap<u64, u64>,
+    reported: BTreeMap<u64, Vec<(usize, u64)>>,
+    /// Server ticks spent waiting on the same frame.
+    stalled: u32,
+}
+
+enum Phase {
+    Lobby,
+    Playing(Box<Round>),
+    Intermission(u32),
+}
+
+pub async fn run(config: ServerConfig) -> io::Result<()> {
+    let listener = TcpListener::bind(&config.addr).await?;
+    let (events_tx, mut events_rx) = mpsc::channel(INCOMING_QUEUE);
+
+    tokio::spawn(async move {
+        let mut next_id = 0;
+        loop {
+            // Running out of file descriptors or a connection reset before
+            // we got to it only costs that one connection.
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(err) => {
+                    eprintln!("[server] accept failed: {}", err);
+                    sleep(ACCEPT_BACKOFF).await;
+                    continue;
+                }
+            };
+            let id = next_id;
+            next_id += 1;
+            let (tx, mut rx) = spawn_connection::<ClientMessage, ServerMessage>(stream);
+            if events_tx.send(Event::Connected(id, tx)).await.is_err() {
+                break;
+            }
+            let events_tx = events_tx.clone();
+            tokio::spawn(async move {
+                while let Some(message) = rx.recv().await {
+                    if events_tx.send(Event::Message(id, message)).await.is_err() {
+                        return;
+                    }
+                }
+                let _ = events_tx.send(Event::Disconnected(id)).await;
+            });
+        }
+    });
+
+    let mut server = Server::new(config);
+    let mut ticker = interval(Duration::from_secs_f32(TICK_SECONDS));
+
+    loop {
+        tokio::select! {
+            Some(event) = events_rx.recv() => server.handle_event(event),
+            _ = ticker.tick() => server.tick(),
+        }
+    }
+}
+
+struct Server {
+    config: ServerConfig,
+    peers: BTreeMap<usize, Peer>,
+    slots: Vec<Option<usize>>,
+    phase: Phase,
+    /// Bombers in the current match, which may outnumber the sides in
+    /// `match_state` when playing in teams.
+    players: usize,
+    match_state: MatchState,
+    feed: SpectatorFeed,
+}
+
+impl Server {
+    fn new(config: ServerConfig) -> Self {
+        Self {
+            slots: vec![None; config.max_players],
+            players: config.max_players,
+            match_state: MatchState::new(config.rules.best_of, config.max_players),
+            config,
+            peers: BTreeMap::new(),
+            phase: Phase::Lobby,
+            feed: SpectatorFeed::default(),
+        }
+    }
+
+    fn log(&self, message: &str) {
+        if self.config.verbose {
+            eprintln!("[server] {}", message);
+        }
+    }
+
+    fn handle_event(&mut self, event: Event) {
+        match event {
+            Event::Connected(id, tx) => {
+                self.peers.insert(
+                    id,
+                    Peer {
+                        tx,
+                        name: None,
+                        slot: None,
+                        spectator: false,
+                        lagging: false,
+                    },
+                );
+            }
+            Event::Message(id, message) => self.handle_message(id, message),
+            Event::Disconnected(id) => self.disconnect(id),
+        }
+        self.drop_lagging();
+    }
+
+    /// Drops the peers whose outgoing queue overflowed. Telling the others
+    /// can overflow more queues, so this repeats until none are left.
+    fn drop_lagging(&mut self) {
+        loop {
+            let lagging: Vec<usize> =
+                self.peers.iter().filter(|(_, p)| p.lagging).map(|(id, _)| *id).collect();
+            if lagging.is_empty() {
+                return;
+            }
+            for id in lagging {
+                self.log(&format!("peer {} is not keeping up, dropping it", id));
+                self.disconnect(id);
+            }
+        }
+    }
+
+    fn handle_message(&mut self, id: usize, message: ClientMessage) {
+        match message {
+            ClientMessage::Hello { name, version } => self.join(id, name, version),
+            ClientMessage::Watch { name, version } => self.watch(id, name, version),
+            ClientMessage::StartMatch => {
+                let is_host = self.peers.get(&id).and_then(|p| p.slot) == Some(0);
+                if is_host && matches!(self.phase, Phase::Lobby) && self.player_count() >= 2 {
+                    self.start_match();
+                }
+            }
+            ClientMessage::Input { tick, action } => {
+                let slot = self.peers.get(&id).and_then(|p| p.slot);
+                if let (Some(slot), Phase::Playing(round)) = (slot, &mut self.phase)
+                    && (round.next_tick..round.next_tick + INPUT_WINDOW).contains(&tick)
+                    && slot < round.departed.len()
+                    && round.departed[slot].is_none()
+                {
+                    let players = round.departed.len();
+                    round.inputs.entry(tick).or_insert_with(|| vec![None; players])[slot] =
+                        Some(action);
+                    round.last_input[slot] = Some(tick);
+                    if self.config.netcode == Netcode::Rollback {
+                        self.relay(id, ServerMessage::Input { slot, tick, action });
+                    }
+                }
+            }
+            ClientMessage::Checksum { tick, checksum } => {
+                let slot = self.peers.get(&id).and_then(|p| p.slot);
+                if let (Some(slot), Phase::Playing(round)) = (slot, &mut self.phase)
+                    && tick < round.next_tick + INPUT_WINDOW
+                {
+                    round.reported.entry(tick).or_default().push((slot, checksum));
+                }
+                self.check_desync();
+            }
+        }
+    }
+
+    fn join(&mut self, id: usize, name: String, version: u32) {
+        let Some(peer) = self.peers.get_mut(&id) else {
+            return;
+        };
+        if peer.slot.is_some() || peer.spectator {
+            peer.send(ServerMessage::Rejected {
+                reason: "already joined".to_string(),
+            });
+            return;
+        }
+        if version != PROTOCOL_VERSION {
+            peer.send(ServerMessage::Rejected {
+                reason: format!("protocol version {} != {}", version, PROTOCOL_VERSION),
+            });
+            return;
+        }
+        if !matches!(self.phase, Phase::Lobby) {
+            peer.send(ServerMessage::Rejected {
+                reason: "match already in progress".to_string(),
+            });
+            return;
+        }
+        let Some(slot) = self.slots.iter().position(|s| s.is_none()) else {
+            peer.send(ServerMessage::Rejected {
+                reason: "lobby is full".to_string(),
+            });
+            return;
+        };
+
+        peer.name = Some(name.clone());
+        peer.slot = Some(slot);
+        peer.send(ServerMessage::Welcome { slot });
+        self.slots[slot] = Some(id);
+        self.log(&format!("{} joined as player {}", name, slot + 1));
+        self.broadcast_lobby();
+
+        if self.config.auto_start == Some(self.player_count()) {
+            self.start_match();
+        }
+    }
+
+    fn watch(&mut self, id: usize, name: String, version: u32) {
+        let Some(peer) = self.peers.get_mut(&id) else {
+            return;
+        };
+        if peer.slot.is_some() || peer.spectator {
+            peer.send(ServerMessage::Rejected {
+                reason: "already joined".to_string(),
+            });
+            return;
+        }
+        if version != PROTOCOL_VERSION {
+            peer.send(ServerMessage::Rejected {
+                reason: format!("protocol version {} != {}", version, PROTOCOL_VERSION),
+            });
+            return;
+        }
+
+        peer.name = Some(name.clone());
+        peer.spectator = true;
+        peer.send(ServerMessage::Spectating {
+            delay_ticks: self.config.spectator_delay,
+        });
+        for message in self.feed.catch_up() {
+            peer.send(message.clone());
+        }
+        self.log(&format!("{} is watching", name));
+    }
+
+    fn disconnect(&mut self, id: usize) {
+        let Some(peer) = self.peers.remove(&id) else {
+            return;
+        };
+        let Some(slot) = peer.slot else {
+            return;
+        };
+        self.log(&format!("player {} disconnected", slot + 1));
+        self.slots[slot] = None;
+
+        match &mut self.phase {
+            Phase::Lobby => self.broadcast_lobby(),
+            Phase::Playing(round) => {
+                // Inputs already sent stay valid, so everyone eliminates the
+                // player at the same tick no matter how far ahead they ran.
+                if slot < round.departed.len() {
+                    let tick = round
+                        .last_input[slot]
+                        .map_or(round.next_tick, |last| round.next_tick.max(last + 1));
+                    round.departed[slot] = Some(tick);
+                    if self.config.netcode == Netcode::Rollback {
+                        self.broadcast(ServerMessage::PlayerLeft { slot, tick });
+                    }
+                }
+            }
+            Phase::Intermission(_) => {}
+        }
+
+        if self.player_count() == 0 {
+            self.log("everyone left, back to the lobby");
+            self.phase = Phase::Lobby;
+        }
+    }
+
+    fn player_count(&self) -> usize {
+        self.slots.iter().filter(|s| s.is_some()).count()
+    }
+
+    /// Packs the joined players into slots `0..n` and starts round one.
+    fn start_match(&mut self) {
+        let ids: Vec<usize> = self.slots.iter().flatten().copied().collect();
+        self.slots = vec![None; self.config.max_players];
+        for (slot, id) in ids.iter().enumerate() {
+            self.slots[slot] = Some(*id);
+            if let Some(peer) = self.peers.get_mut(id) {
+                peer.slot = Some(slot);
+                peer.send(ServerMessage::Welcome { slot });
+            }
+        }
+
+        self.players = ids.len();
+        self.match_state = MatchState::new(
+            self.config.rules.best_of,
+            self.config.rules.sides(ids.len()),
+        );
+        self.log(&format!("match started with {} players", ids.len()));
+        self.start_round();
+    }
+
+    fn start_round(&mut self) {
+        let players = self.players;
+        let seed = rand::random();
+        let setup = GameSetup::Battle {
+            slots: vec![BomberSlot::Human; players],
+            rules: self.config.rules.clone(),
+        };
+        let departed: Vec<Option<u64>> = (0..players)
+            .map(|slot| self.slots[slot].is_none().then_some(0))
+            .collect();
+        // Rollback clients start with input scheduled a few frames ahead,
+        // so the frames before that are idle for everyone.
+        let mut inputs = BTreeMap::new();
+        if self.config.netcode == Netcode::Rollback {
+            for tick in 0..LOCAL_INPUT_DELAY {
+                inputs.insert(tick, vec![Some(Action::Idle); players]);
+            }
+        }
+
+        self.broadcast(ServerMessage::RoundStart {
+            seed,
+            players,
+            round: self.match_state.round,
+            rules: self.config.rules.clone(),
+            netcode: self.config.netcode,
+        });
+        self.phase = Phase::Playing(Box::new(Round {
+            state: setup.build(seed),
+            next_tick: 0,
+            inputs,
+            last_input: vec![None; players],
+            departed,
+            checksums: BTreeMap::new(),
+            reported: BTreeMap::new(),
+            stalled: 0,
+        }));
+    }
+
+    fn tick(&mut self) {
+        for message in self.feed.release() {
+            for peer in self.peers.values_mut().filter(|p| p.spectator) {
+                peer.send(message.clone());
+            }
+        }
+
+        match &mut self.phase {
+            Phase::Lobby => {}
+            Phase::Intermission(frames) => {
+                *frames = frames.saturating_sub(1);
+                if *frames == 0 {
+                    self.start_round();
+                }
+            }
+            Phase::Playing(round) => {
+                let waiting_on = round.next_tick;
+                // Lockstep clients are paced by the frames we send, but
+                // rollback clients run on their own clocks, so catch up.
+                while self.step_round() && self.config.netcode == Netcode::Rollback {}
+                self.drop_stalled(waiting_on);
+            }
+        }
+        self.drop_lagging();
+    }
+
+    /// Drops the players whose input the round has been waiting on for
+    /// `INPUT_TIMEOUT_FRAMES`, as if they had disconnected.
+    fn drop_stalled(&mut self, waiting_on: u64) {
+        let Phase::Playing(round) = &mut self.phase else {
+            return;
+        };
+        if round.next_tick != waiting_on {
+            round.stalled = 0;
+            return;
+        }
+        round.stalled += 1;
+        if round.stalled < INPUT_TIMEOUT_FRAMES {
+            return;
+        }
+        round.stalled = 0;
+
+        let inputs = round.inputs.get(&waiting_on);
+        let silent: Vec<usize> = (0..round.departed.len())
+            .filter(|slot| round.departed[*slot].is_none())
+            .filter(|slot| inputs.is_none_or(|i| i[*slot].is_none()))
+            .filter_map(|slot| self.slots[slot])
+            .collect();
+        for id in silent {
+            if let Some(peer) = self.peers.get_mut(&id) {
+                peer.send(ServerMessage::Rejected {
+                    reason: "no input for too long".to_string(),
+                });
+            }
+            self.disconnect(id);
+        }
+    }
+
+    /// Simulates the next tick once every remaining player's input for it
+    /// is in. Returns whether the round is still going afterwards.
+    fn step_round(&mut self) -> bool {
+        let Phase::Playing(round) = &mut self.phase else {
+            return false;
+        };
+
+        let tick = round.next_tick;
+        let gone = |slot: usize| round.departed[slot].is_some_and(|from| tick >= from);
+        let inputs = round.inputs.get(&tick);
+        let ready = (0..round.departed.len())
+            .all(|slot| gone(slot) || inputs.is_some_and(|i| i[slot].is_some()));
+        if !ready {
+            return false;
+        }
+
+        let actions: Vec<Action> = (0..round.departed.len())
+            .map(|slot| {
+                inputs
+                    .and_then(|i| i[slot])
+                    .filter(|_| !gone(slot))
+                    .unwrap_or(Action::Idle)
+            })
+            .collect();
+        let left: Vec<usize> = (0..round.departed.len())
+            .filter(|slot| round.departed[*slot] == Some(tick))
+            .collect();
+        round.inputs.remove(&tick);
+
+        apply_frame(&mut round.state, &actions, &left);
+        if tick.is_multiple_of(CHECKSUM_INTERVAL) {
+            round.checksums.insert(tick, round.state.checksum());
+        }
+        round.next_tick += 1;
+
+        let result = round.state.round_result();
+        let stats = round.state.round_stats.clone();
+
+        let frame = ServerMessage::Frame { tick, actions, left };
+        if self.config.netcode == Netcode::Lockstep {
+            self.broadcast(frame.clone());
+        }
+        self.feed.push(frame, self.config.spectator_delay);
+        self.check_desync();
+        if let Some(winner) = result {
+            self.finish_round(winner, stats);
+        }
+        result.is_none()
+    }
+
+    fn finish_round(&mut self, winner: Option<usize>, stats: Vec<RoundStats>) {
+        self.broadcast(ServerMessage::RoundOver { winner });
+        self.match_state.record_round(winner, &stats);
+        self.log(&format!(
+            "round {} over, winner: {}",
+            self.match_state.round,
+            winner.map(|side| self.side_name(side)).unwrap_or("draw".to_string())
+        ));
+
+        if let Some(champion) = self.match_state.champion() {
+            self.broadcast(ServerMessage::MatchOver {
+                champion: Some(champion),
+            });
+            self.log(&format!("{} wins the match", self.side_name(champion)));
+            self.phase = Phase::Lobby;
+            self.broadcast_lobby();
+        } else {
+            self.match_state.next_round();
+            self.phase = Phase::Intermission(INTERMISSION_FRAMES);
+        }
+    }
+
+    fn side_name(&self, side: usize) -> String {
+        if self.config.rules.teams {
+            format!("team {}", (b'A' + side as u8) as char)
+        } else {
+            format!("player {}", side + 1)
+        }
+    }
+
+    fn check_desync(&mut self) {
+        let Phase::Playing(round) = &mut self.phase else {
+            return;
+        };
+
+        let mut desyncs = Vec::new();
+        let ticks: Vec<u64> = round
+            .reported
+            .keys()
+            .copied()
+            .filter(|t| round.checksums.contains_key(t))
+            .collect();
+        for tick in ticks {
+            let expected = round.checksums[&tick];
+            for (slot, checksum) in round.reported.remove(&tick).unwrap_or_default() {
+                if checksum != expected {
+                    desyncs.push((tick, slot));
+                }
+            }
+        }
+        // Anything left below the simulated frame will never get a checksum
+        // of ours to compare with.
+        let next_tick = round.next_tick;
+        round.reported.retain(|tick, _| *tick >= next_tick);
+
+        for (tick, slot) in desyncs {
+            self.log(&format!("player {} desynced at tick {}", slot + 1, tick));
+            self.broadcast(ServerMessage::Desync { tick, slot });
+        }
+    }
+
+    fn broadcast_lobby(&mut self) {
+        let players = self
+            .slots
+            .iter()
+            .map(|slot| slot.and_then(|id| self.peers.get(&id)).and_then(|p| p.name.clone()))
+            .collect();
+        self.broadcast(ServerMessage::Lobby { players });
+    }
+
+    /// Sends to every player except the peer `from`.
+    fn relay(&mut self, from: usize, message: ServerMessage) {
+        for (_, peer) in self.peers.iter_mut().filter(|(id, p)| **id != from && p.slot.is_some()) {
+            peer.send(message.clone());
+        }
+    }
+
+    /// Sends to every player, and to spectators once the delay has passed.
+    fn broadcast(&mut self, message: ServerMessage) {
+        for peer in self.peers.values_mut().filter(|p| p.slot.is_some()) {
+            peer.send(message.clone());
+        }
+        if !matches!(
+            message,
+            ServerMessage::Desync { .. } | ServerMessage::PlayerLeft { .. }
+        ) {
+            self.feed.push(message, self.config.spectator_delay);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::OUTGOING_QUEUE;
+
+    fn server() -> Server {
+        Server::new(ServerConfig {
+            addr: String::new(),
+            max_players: 4,
+            auto_start: None,
+            rules: BattleRules::default(),
+            netcode: Netcode::Lockstep,
+            spectator_delay: 0,
+            verbose: false,
+        })
+    }
+
+    fn connect(server: &mut Server, id: usize) -> mpsc::Receiver<ServerMessage> {
+        connect_with_queue(server, id, OUTGOING_QUEUE)
+    }
+
+    fn connect_with_queue(
+        server: &mut Server,
+        id: usize,
+        queue: usize,
+    ) -> mpsc::Receiver<ServerMessage> {
+        let (tx, rx) = mpsc::channel(queue);
+        server.handle_event(Event::Connected(id, tx));
+        rx
+    }
+
+    fn hello(server: &mut Server, id: usize) {
+        let name = format!("p{}", id);
+        let hello = ClientMessage::Hello { name, version: PROTOCOL_VERSION };
+        server.handle_event(Event::Message(id, hello));
+    }
+
+    fn input(server: &mut Server, id: usize, tick: u64) {
+        server.handle_message(id, ClientMessage::Input { tick, action: Action::Idle });
+    }
+
+    /// Two players in the first round of a match.
+    fn playing() -> (Server, Vec<mpsc::Receiver<ServerMessage>>) {
+        let mut server = server();
+        let receivers = (0..2).map(|id| connect(&mut server, id)).collect();
+        hello(&mut server, 0);
+        hello(&mut server, 1);
+        server.start_match();
+        (server, receivers)
+    }
+
+    fn round(server: &Server) -> &Round {
+        match &server.phase {
+            Phase::Playing(round) => round,
+            _ => panic!("no round in progress"),
+        }
+    }
+
+    #[test]
+    fn a_second_hello_takes_no_extra_slot() {
+        let mut server = server();
+        let mut rx = connect(&mut server, 0);
+        hello(&mut server, 0);
+        hello(&mut server, 0);
+
+        assert_eq!(server.player_count(), 1);
+        let rejected = std::iter::from_fn(|| rx.try_recv().ok())
+            .any(|message| matches!(message, ServerMessage::Rejected { .. }));
+        assert!(rejected);
+    }
+
+    #[test]
+    fn a_peer_that_stops_reading_is_dropped() {
+        let mut server = server();
+        let _rx = connect(&mut server, 0);
+        let _stuck = connect_with_queue(&mut server, 1, 2);
+        hello(&mut server, 0);
+        hello(&mut server, 1);
+        assert_eq!(server.player_count(), 2);
+
+        // Welcome and a lobby update fill the queue; the next update spills.
+        let _late = connect(&mut server, 2);
+        hello(&mut server, 2);
+
+        assert!(!server.peers.contains_key(&1));
+        assert_eq!(server.player_count(), 2);
+    }
+
+    #[test]
+    fn spectators_cannot_take_a_slot() {
+        let mut server = server();
+        let _rx = connect(&mut server, 0);
+        let name = "watcher".to_string();
+        server.handle_message(0, ClientMessage::Watch { name, version: PROTOCOL_VERSION });
+        hello(&mut server, 0);
+
+        assert_eq!(server.player_count(), 0);
+    }
+
+    #[test]
+    fn inputs_outside_the_window_are_dropped() {
+        let (mut server, _rx) = playing();
+        input(&mut server, 0, INPUT_WINDOW + 5);
+        input(&mut server, 0, 3);
+
+        assert_eq!(round(&server).inputs.keys().copied().collect::<Vec<_>>(), [3]);
+    }
+
+    #[test]
+    fn checksums_that_can_never_be_compared_are_pruned() {
+        let (mut server, _rx) = playing();
+        server.handle_message(0, ClientMessage::Checksum { tick: 1, checksum: 42 });
+        server.handle_message(0, ClientMessage::Checksum { tick: INPUT_WINDOW * 2, checksum: 42 });
+        for tick in 0..3 {
+            input(&mut server, 0, tick);
+            input(&mut server, 1, tick);
+            server.tick();
+        }
+
+        assert_eq!(round(&server).next_tick, 3);
+        assert!(round(&server).reported.is_empty());
+    }
+
+    #[test]
+    fn a_silent_player_is_dropped() {
+        let (mut server, mut receivers) = playing();
+        input(&mut server, 0, 0);
+        for _ in 0..INPUT_TIMEOUT_FRAMES {
+            server.tick();
+        }
+
+        assert_eq!(server.player_count(), 1);
+        assert!(!server.peers.contains_key(&1));
+        let rejected = std::iter::from_fn(|| receivers[1].try_recv().ok())
+            .any(|message| matches!(message, ServerMessage::Rejected { .. }));
+        assert!(rejected);
+    }
+
+    fn frame(tick: u64) -> ServerMessage {
+        ServerMessage::Frame { tick, actions: Vec::new(), left: Vec::new() }
+    }
+
+    fn round_start(round: u32) -> ServerMessage {
+        ServerMessage::RoundStart {
+            seed: 1,
+            players: 2,
+            round,
+            rules: BattleRules::default(),
+            netcode: Netcode::Lockstep,
+        }
+    }
+
+    /// The catch-up a spectator would get now, by message kind.
+    fn catch_up(feed: &SpectatorFeed) -> Vec<String> {
+        feed.catch_up()
+            .map(|message| match message {
+                ServerMessage::Lobby { players } => format!("lobby {}", players.len()),
+                ServerMessage::RoundStart { round, .. } => format!("round {}", round),
+                ServerMessage::Frame { tick, .. } => format!("frame {}", tick),
+                ServerMessage::RoundOver { .. } => "over".to_string(),
+                _ => "other".to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn spectators_get_messages_after_the_delay() {
+        let mut feed = SpectatorFeed::default();
+        feed.push(frame(0), 3);
+        assert!(feed.release().is_empty());
+        assert!(feed.release().is_empty());
+        feed.push(frame(1), 0);
+
+        let released = feed.release();
+        assert_eq!(released.len(), 2);
+        assert!(matches!(released[0], ServerMessage::Frame { tick: 0, .. }));
+        assert!(matches!(released[1], ServerMessage::Frame { tick: 1, .. }));
+    }
+
+    #[test]
+    fn a_new_round_trims_the_catch_up_to_earlier_results() {
+        let mut feed = SpectatorFeed::default();
+        for message in [round_start(1), frame(0), frame(1), ServerMessage::RoundOver { winner: Some(0) }] {
+            feed.push(message, 0);
+        }
+        feed.release();
+        assert_eq!(catch_up(&feed), ["round 1", "frame 0", "frame 1", "over"]);
+
+        feed.push(round_start(2), 0);
+        feed.push(frame(0), 0);
+        feed.release();
+        assert_eq!(catch_up(&feed), ["round 1", "over", "round 2", "frame 0"]);
+    }
+
+    #[test]
+    fn a_new_match_drops_the_last_one_and_keeps_only_the_latest_lobby() {
+        let mut feed = SpectatorFeed::default();
+        feed.push(ServerMessage::Lobby { players: vec![None] }, 0);
+        for message in [round_start(1), frame(0), ServerMessage::RoundOver { winner: None }] {
+            feed.push(message, 0);
+        }
+        feed.push(ServerMessage::Lobby { players: vec![None, None] }, 0);
+        feed.push(round_start(1), 0);
+        feed.release();
+
+        assert_eq!(catch_up(&feed), ["lobby 2", "round 1"]);
+    }
+}
@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+use super::glyphs::GlyphSet;
+
+/// Ready-made settings for players who can't tell things apart by color.
+/// A preset picks a theme and the ASCII glyphs, and turns on labels; a
+/// theme or glyph set chosen in the config still wins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Preset {
+    /// Okabe-Ito colors, which survive the common color vision deficiencies.
+    Colorblind,
+    /// Bright on black with bold text throughout.
+    HighContrast,
+}
+
+impl Preset {
+    pub fn theme(self) -> &'static str {
+        match self {
+            Preset::Colorblind => "colorblind",
+            Preset::HighContrast => "high-contrast",
+        }
+    }
+
+    /// Every entity has its own letters in the ASCII set, so the board
+    /// reads without color or emoji.
+    pub fn glyph_set(self) -> GlyphSet {
+        GlyphSet::Ascii
+    }
+}
+
+/// Board drawing switches; everything is off by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Accessibility {
+    /// Put what color alone would say into text: bombers show their slot
+    /// number, bombs their fuse in seconds, and the danger overlay marks
+    /// empty tiles with `!!` (about to blow) or `::` (later).
+    pub labels: bool,
+    /// Keep fuses and explosions steady instead of blinking.
+    pub reduced_motion: bool,
+}
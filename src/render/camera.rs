@@ -0,0 +1,122 @@
+use crate::game::components::Position;
+
+/// How many terminal columns a tile takes on the board.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Zoom {
+    /// Two columns per tile, the full glyphs.
+    #[default]
+    Near,
+    /// One column per tile, so twice as much of a big stage fits.
+    Far,
+}
+
+impl Zoom {
+    pub fn cell_width(self) -> usize {
+        match self {
+            Zoom::Near => 2,
+            Zoom::Far => 1,
+        }
+    }
+
+    pub fn toggle(self) -> Self {
+        match self {
+            Zoom::Near => Zoom::Far,
+            Zoom::Far => Zoom::Near,
+        }
+    }
+}
+
+/// The part of the world the board shows, in tiles. It follows a target
+/// with a dead zone: the view only scrolls once the target leaves the
+/// middle half of it, and it never scrolls past the edge of the map.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Camera {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Camera {
+    /// Fits the view to `view` tiles (or the whole world, if smaller) and
+    /// scrolls it as far as needed to keep `target` out of the margins.
+    pub fn follow(&mut self, target: Option<Position>, view: (usize, usize), world: (usize, usize)) {
+        self.width = view.0.min(world.0);
+        self.height = view.1.min(world.1);
+        if let Some(target) = target {
+            self.x = follow_axis(self.x, target.x, self.width);
+            self.y = follow_axis(self.y, target.y, self.height);
+        }
+        self.x = self.x.min(world.0 - self.width);
+        self.y = self.y.min(world.1 - self.height);
+    }
+}
+
+fn follow_axis(origin: usize, target: usize, view: usize) -> usize {
+    let margin = view / 4;
+    if target < origin + margin {
+        target.saturating_sub(margin)
+    } else if target + margin >= origin + view {
+        target + margin + 1 - view
+    } else {
+        origin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORLD: (usize, usize) = (40, 30);
+    const VIEW: (usize, usize) = (20, 10);
+
+    fn following(x: usize, y: usize) -> Camera {
+        let mut camera = Camera::default();
+        camera.follow(Some(Position::new(x, y)), VIEW, WORLD);
+        camera
+    }
+
+    #[test]
+    fn a_map_smaller_than_the_view_is_shown_whole() {
+        let mut camera = Camera { x: 3, y: 3, ..Camera::default() };
+        camera.follow(Some(Position::new(14, 12)), (30, 20), (15, 13));
+
+        assert_eq!(camera, Camera { x: 0, y: 0, width: 15, height: 13 });
+    }
+
+    #[test]
+    fn the_view_stops_at_every_edge_of_the_map() {
+        let corners = [
+            ((0, 0), (0, 0)),
+            ((39, 0), (20, 0)),
+            ((0, 29), (0, 20)),
+            ((39, 29), (20, 20)),
+        ];
+        for ((x, y), origin) in corners {
+            let camera = following(x, y);
+            assert_eq!((camera.x, camera.y), origin, "target at {}, {}", x, y);
+        }
+    }
+
+    #[test]
+    fn the_view_holds_still_while_the_target_is_in_the_dead_zone() {
+        let mut camera = Camera { x: 10, y: 10, ..Camera::default() };
+        for x in [15, 20, 24] {
+            camera.follow(Some(Position::new(x, 15)), VIEW, WORLD);
+            assert_eq!((camera.x, camera.y), (10, 10), "target at x {}", x);
+        }
+
+        camera.follow(Some(Position::new(26, 15)), VIEW, WORLD);
+        assert_eq!((camera.x, camera.y), (12, 10));
+        camera.follow(Some(Position::new(12, 11)), VIEW, WORLD);
+        assert_eq!((camera.x, camera.y), (7, 9));
+    }
+
+    #[test]
+    fn without_a_target_the_view_only_keeps_inside_the_map() {
+        let mut camera = Camera { x: 30, y: 25, ..Camera::default() };
+        camera.follow(None, VIEW, WORLD);
+
+        assert_eq!((camera.x, camera.y), (20, 20));
+    }
+}
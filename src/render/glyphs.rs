@@ -0,0 +1,347 @@
+use ratatui::text::Span;
+use serde::{Deserialize, Serialize};
+
+use crate::game::{
+    components::{EnemyKind, ItemType},
+    entity::{Entity, EntityType},
+    world::Tile,
+};
+
+/// Which characters the board is drawn with. Every glyph is two columns
+/// wide so that the grid lines up whatever the set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GlyphSet {
+    /// Plain ASCII, for any terminal, font or link.
+    Ascii,
+    /// Box-drawing and block characters, which most fonts have.
+    Unicode,
+    /// Pictures; needs a font and terminal that agree on emoji width.
+    Emoji,
+}
+
+impl GlyphSet {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "ascii" => Some(GlyphSet::Ascii),
+            "unicode" => Some(GlyphSet::Unicode),
+            "emoji" => Some(GlyphSet::Emoji),
+            _ => None,
+        }
+    }
+
+    /// Guesses what a terminal can show from its `TERM` and locale. A
+    /// non-UTF-8 locale or a console without a real font gets ASCII, and
+    /// multiplexers, which often disagree with the outer terminal about
+    /// emoji width, get the Unicode set.
+    pub fn detect(term: &str, locale: &str) -> Self {
+        let locale = locale.to_ascii_lowercase();
+        if !locale.is_empty() && !locale.contains("utf-8") && !locale.contains("utf8") {
+            return GlyphSet::Ascii;
+        }
+        match term {
+            "" | "dumb" | "linux" | "vt100" | "vt102" | "vt220" | "ansi" => GlyphSet::Ascii,
+            _ if term.starts_with("screen") || term.starts_with("tmux") => GlyphSet::Unicode,
+            _ => GlyphSet::Emoji,
+        }
+    }
+
+    /// `detect` for the terminal this process runs in, unless
+    /// `BMTUI_GLYPHS` names a set.
+    pub fn detect_local() -> Self {
+        let var = |name| std::env::var(name).unwrap_or_default();
+        if let Some(set) = Self::parse(&var("BMTUI_GLYPHS")) {
+            return set;
+        }
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .into_iter()
+            .map(var)
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        if std::env::var_os("TMUX").is_some() {
+            return Self::detect("tmux", &locale);
+        }
+        Self::detect(&var("TERM"), &locale)
+    }
+
+    pub fn glyphs(self) -> Glyphs {
+        match self {
+            GlyphSet::Ascii => Glyphs {
+                empty: "  ".into(),
+                wall: "##".into(),
+                breakable: "[]".into(),
+                player: "@@".into(),
+                bomb: "()".into(),
+                explosion: "**".into(),
+                fire_item: "F+".into(),
+                bomb_item: "B+".into(),
+                kick_item: "K+".into(),
+                remote_item: "R+".into(),
+                walker: "ww".into(),
+                chaser: "cc".into(),
+                ghost: "gg".into(),
+                tank: "TT".into(),
+                splitter: "ss".into(),
+                fragment: "..".into(),
+                bomb_icon: "B".into(),
+                fire_icon: "F".into(),
+                alive: "+".into(),
+                dead: "x".into(),
+                win: "*".into(),
+            },
+            GlyphSet::Unicode => Glyphs {
+                empty: "  ".into(),
+                wall: "██".into(),
+                breakable: "▒▒".into(),
+                player: "◖◗".into(),
+                bomb: "◍ ".into(),
+                explosion: "╬╬".into(),
+                fire_item: "▲F".into(),
+                bomb_item: "▲B".into(),
+                kick_item: "▲K".into(),
+                remote_item: "▲R".into(),
+                walker: "◇◇".into(),
+                chaser: "◆◆".into(),
+                ghost: "░░".into(),
+                tank: "▣▣".into(),
+                splitter: "◈◈".into(),
+                fragment: "◦◦".into(),
+                bomb_icon: "◍".into(),
+                fire_icon: "▲".into(),
+                alive: "●".into(),
+                dead: "✖".into(),
+                win: "★".into(),
+            },
+            GlyphSet::Emoji => Glyphs {
+                empty: "  ".into(),
+                wall: "🧱".into(),
+                breakable: "📦".into(),
+                player: "🧑".into(),
+                bomb: "💣".into(),
+                explosion: "💥".into(),
+                fire_item: "🔥".into(),
+                bomb_item: "💣".into(),
+                kick_item: "👟".into(),
+                remote_item: "📡".into(),
+                walker: "👾".into(),
+                chaser: "👹".into(),
+                ghost: "👻".into(),
+                tank: "🤖".into(),
+                splitter: "🦠".into(),
+                fragment: "🐛".into(),
+                bomb_icon: "💣".into(),
+                fire_icon: "🔥".into(),
+                alive: "●".into(),
+                dead: "✖".into(),
+                win: "★".into(),
+            },
+        }
+    }
+}
+
+/// The text drawn for every tile and entity, plus the HUD's icons. A
+/// theme may list only some of them; the rest come from the Unicode set.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Glyphs {
+    pub empty: String,
+    pub wall: String,
+    pub breakable: String,
+    pub player: String,
+    pub bomb: String,
+    pub explosion: String,
+    pub fire_item: String,
+    pub bomb_item: String,
+    pub kick_item: String,
+    pub remote_item: String,
+    pub walker: String,
+    pub chaser: String,
+    pub ghost: String,
+    pub tank: String,
+    pub splitter: String,
+    pub fragment: String,
+    pub bomb_icon: String,
+    pub fire_icon: String,
+    pub alive: String,
+    pub dead: String,
+    pub win: String,
+}
+
+impl Default for Glyphs {
+    fn default() -> Self {
+        GlyphSet::Unicode.glyphs()
+    }
+}
+
+impl Glyphs {
+    /// Makes every board glyph from a theme file exactly two columns wide:
+    /// a one-column glyph gets a space after it, and any other width is an
+    /// error, since it would shift the rest of the row.
+    pub fn checked(mut self) -> Result<Self, String> {
+        for (name, glyph) in self.board_mut() {
+            match Span::raw(glyph.as_str()).width() {
+                2 => {}
+                1 => glyph.push(' '),
+                width => {
+                    return Err(format!(
+                        "glyph {} is {:?}, {} columns wide instead of 2",
+                        name, glyph, width
+                    ));
+                }
+            }
+        }
+        Ok(self)
+    }
+
+    fn board_mut(&mut self) -> [(&'static str, &mut String); 16] {
+        [
+            ("empty", &mut self.empty),
+            ("wall", &mut self.wall),
+            ("breakable", &mut self.breakable),
+            ("player", &mut self.player),
+            ("bomb", &mut self.bomb),
+            ("explosion", &mut self.explosion),
+            ("fire_item", &mut self.fire_item),
+            ("bomb_item", &mut self.bomb_item),
+            ("kick_item", &mut self.kick_item),
+            ("remote_item", &mut self.remote_item),
+            ("walker", &mut self.walker),
+            ("chaser", &mut self.chaser),
+            ("ghost", &mut self.ghost),
+            ("tank", &mut self.tank),
+            ("splitter", &mut self.splitter),
+            ("fragment", &mut self.fragment),
+        ]
+    }
+
+    /// One-column versions for the zoomed-out board: the first character
+    /// of each glyph, or of the Unicode set's where that one is wide.
+    pub fn narrow(&self) -> Glyphs {
+        let fallback = GlyphSet::Unicode.glyphs();
+        let narrow = |glyph: &str, fallback: &str| {
+            [glyph, fallback]
+                .into_iter()
+                .filter_map(|text| text.chars().next())
+                .find(|ch| Span::raw(ch.to_string()).width() == 1)
+                .unwrap_or(' ')
+                .to_string()
+        };
+        Glyphs {
+            empty: narrow(&self.empty, &fallback.empty),
+            wall: narrow(&self.wall, &fallback.wall),
+            breakable: narrow(&self.breakable, &fallback.breakable),
+            player: narrow(&self.player, &fallback.player),
+            bomb: narrow(&self.bomb, &fallback.bomb),
+            explosion: narrow(&self.explosion, &fallback.explosion),
+            fire_item: narrow(&self.fire_item, &fallback.fire_item),
+            bomb_item: narrow(&self.bomb_item, &fallback.bomb_item),
+            kick_item: narrow(&self.kick_item, &fallback.kick_item),
+            remote_item: narrow(&self.remote_item, &fallback.remote_item),
+            walker: narrow(&self.walker, &fallback.walker),
+            chaser: narrow(&self.chaser, &fallback.chaser),
+            ghost: narrow(&self.ghost, &fallback.ghost),
+            tank: narrow(&self.tank, &fallback.tank),
+            splitter: narrow(&self.splitter, &fallback.splitter),
+            fragment: narrow(&self.fragment, &fallback.fragment),
+            bomb_icon: self.bomb_icon.clone(),
+            fire_icon: self.fire_icon.clone(),
+            alive: self.alive.clone(),
+            dead: self.dead.clone(),
+            win: self.win.clone(),
+        }
+    }
+
+    pub fn tile(&self, tile: Tile) -> &str {
+        match tile {
+            Tile::Empty => &self.empty,
+            Tile::Wall => &self.wall,
+            Tile::Breakable => &self.breakable,
+        }
+    }
+
+    pub fn item(&self, item_type: ItemType) -> &str {
+        match item_type {
+            ItemType::Fire => &self.fire_item,
+            ItemType::Bomb => &self.bomb_item,
+            ItemType::Kick => &self.kick_item,
+            ItemType::Remote => &self.remote_item,
+        }
+    }
+
+    pub fn enemy(&self, kind: EnemyKind) -> &str {
+        match kind {
+            EnemyKind::Walker => &self.walker,
+            EnemyKind::Chaser => &self.chaser,
+            EnemyKind::Ghost => &self.ghost,
+            EnemyKind::Tank => &self.tank,
+            EnemyKind::Splitter => &self.splitter,
+            EnemyKind::Fragment => &self.fragment,
+        }
+    }
+
+    pub fn entity(&self, entity: &Entity) -> &str {
+        match entity.entity_type {
+            EntityType::Player => &self.player,
+            EntityType::Enemy => entity
+                .enemy_kind()
+                .map(|kind| self.enemy(kind))
+                .unwrap_or(&self.walker),
+            EntityType::Bomb => &self.bomb,
+            EntityType::Explosion => &self.explosion,
+            EntityType::Item => entity
+                .item_type
+                .map(|item_type| self.item(item_type))
+                .unwrap_or("??"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SETS: [GlyphSet; 3] = [GlyphSet::Ascii, GlyphSet::Unicode, GlyphSet::Emoji];
+
+    fn width(text: &str) -> usize {
+        Span::raw(text).width()
+    }
+
+    #[test]
+    fn ascii_glyphs_are_plain_ascii_two_columns_wide() {
+        let mut glyphs = GlyphSet::Ascii.glyphs();
+        let icons = [&glyphs.bomb_icon, &glyphs.fire_icon, &glyphs.alive, &glyphs.dead, &glyphs.win];
+        for icon in icons {
+            assert!(icon.is_ascii() && width(icon) == 1, "icon {:?}", icon);
+        }
+        for (name, glyph) in glyphs.board_mut() {
+            assert!(glyph.is_ascii(), "{} is {:?}", name, glyph);
+            assert_eq!(width(glyph), 2, "{} is {:?}", name, glyph);
+        }
+    }
+
+    #[test]
+    fn every_set_passes_its_own_width_check() {
+        for set in SETS {
+            let glyphs = set.glyphs();
+            assert_eq!(glyphs.clone().checked(), Ok(glyphs), "{:?}", set);
+        }
+    }
+
+    #[test]
+    fn narrow_glyphs_are_one_column_wide() {
+        for set in SETS {
+            let mut narrow = set.glyphs().narrow();
+            for (name, glyph) in narrow.board_mut() {
+                assert_eq!(width(glyph), 1, "{:?} {} is {:?}", set, name, glyph);
+            }
+        }
+    }
+
+    #[test]
+    fn plain_terminals_and_locales_get_ascii() {
+        assert_eq!(GlyphSet::detect("linux", "en_US.UTF-8"), GlyphSet::Ascii);
+        assert_eq!(GlyphSet::detect("dumb", ""), GlyphSet::Ascii);
+        assert_eq!(GlyphSet::detect("xterm-256color", "C"), GlyphSet::Ascii);
+        assert_eq!(GlyphSet::detect("tmux-256color", "ja_JP.UTF-8"), GlyphSet::Unicode);
+        assert_eq!(GlyphSet::detect("xterm-256color", "ja_JP.utf8"), GlyphSet::Emoji);
+    }
+}
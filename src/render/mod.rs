@@ -1 +1,5 @@
+pub mod accessibility;
+pub mod camera;
+pub mod glyphs;
+pub mod theme;
 pub mod widgets;
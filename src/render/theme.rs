@@ -0,0 +1,204 @@
+use std::fs;
+use std::path::PathBuf;
+
+use ratatui::style::{Color, Modifier, Style};
+use serde::{Deserialize, Serialize};
+
+use super::glyphs::Glyphs;
+use crate::config::{config_dir, named_file};
+
+/// Themes compiled into the binary, in the order they are listed.
+const BUILT_IN: [(&str, &str); 5] = [
+    ("classic", include_str!("../../assets/themes/classic.ron")),
+    ("dark", include_str!("../../assets/themes/dark.ron")),
+    ("high-contrast", include_str!("../../assets/themes/high-contrast.ron")),
+    ("monochrome", include_str!("../../assets/themes/monochrome.ron")),
+    ("colorblind", include_str!("../../assets/themes/colorblind.ron")),
+];
+
+/// Colors, and optionally glyphs, for everything on screen. Built-in
+/// themes live in `assets/themes`; users can add their own as
+/// `themes/<name>.ron` in the config directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Theme {
+    pub name: String,
+    /// Replaces the glyph set chosen by config or detection, but not one
+    /// picked with `:set glyphs` or `:theme <set>`.
+    #[serde(default)]
+    pub glyphs: Option<Glyphs>,
+    /// One color per player slot, used on the board and in the HUD.
+    #[serde(default)]
+    pub players: Vec<Color>,
+    #[serde(default)]
+    pub board: BoardTheme,
+    #[serde(default)]
+    pub hud: HudTheme,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BoardTheme {
+    pub empty: StyleSpec,
+    pub wall: StyleSpec,
+    pub breakable: StyleSpec,
+    /// The story mode player; battle bombers get their slot color.
+    pub player: StyleSpec,
+    pub enemy: StyleSpec,
+    pub bomb: StyleSpec,
+    /// A bomb in its last second; it blinks between this and `bomb`.
+    pub bomb_fuse: StyleSpec,
+    pub explosion: StyleSpec,
+    pub fire_item: StyleSpec,
+    pub bomb_item: StyleSpec,
+    /// Kick and remote, which give an ability rather than one more of something.
+    pub ability_item: StyleSpec,
+    /// Danger overlay (F3) for tiles about to blow up, and later ones.
+    pub danger_soon: StyleSpec,
+    pub danger_later: StyleSpec,
+    /// Remembered terrain outside the light on a dark stage.
+    pub fog: StyleSpec,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HudTheme {
+    pub header: StyleSpec,
+    pub border: StyleSpec,
+    /// Headings of overlays and menus.
+    pub title: StyleSpec,
+    /// Scores, round wins and other numbers worth noticing.
+    pub accent: StyleSpec,
+    pub good: StyleSpec,
+    pub bad: StyleSpec,
+    pub info: StyleSpec,
+    /// Background of overlays drawn over the board.
+    pub overlay: StyleSpec,
+    /// The chosen line of a menu.
+    pub selected: StyleSpec,
+    /// Banners that need attention right away, like a desync.
+    pub alert: StyleSpec,
+}
+
+/// A style as written in a theme file: `(fg: "red", bold: true)`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StyleSpec {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub dim: bool,
+    pub reversed: bool,
+    pub underlined: bool,
+}
+
+impl StyleSpec {
+    pub fn style(&self) -> Style {
+        let mut style = Style::default();
+        if let Some(fg) = self.fg {
+            style = style.fg(fg);
+        }
+        if let Some(bg) = self.bg {
+            style = style.bg(bg);
+        }
+        for (enabled, modifier) in [
+            (self.bold, Modifier::BOLD),
+            (self.dim, Modifier::DIM),
+            (self.reversed, Modifier::REVERSED),
+            (self.underlined, Modifier::UNDERLINED),
+        ] {
+            if enabled {
+                style = style.add_modifier(modifier);
+            }
+        }
+        style
+    }
+}
+
+impl Theme {
+    pub fn classic() -> Self {
+        Self::built_in("classic").expect("the classic theme is built in")
+    }
+
+    fn built_in(name: &str) -> Option<Self> {
+        let (_, source) = BUILT_IN.iter().find(|(built_in, _)| *built_in == name)?;
+        Some(Self::parse(source).unwrap_or_else(|err| panic!("built-in theme {}: {}", name, err)))
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut theme: Theme = ron::from_str(text).map_err(|err| err.to_string())?;
+        theme.glyphs = theme.glyphs.map(Glyphs::checked).transpose()?;
+        Ok(theme)
+    }
+
+    fn user_dir() -> PathBuf {
+        config_dir().join("themes")
+    }
+
+    /// A built-in theme, or `themes/<name>.ron` from the config directory.
+    pub fn load(name: &str) -> Result<Self, String> {
+        if let Some(theme) = Self::built_in(name) {
+            return Ok(theme);
+        }
+        let path = named_file(Self::user_dir(), name)?;
+        let text = fs::read_to_string(&path).map_err(|_| {
+            format!("unknown theme: {} (available: {})", name, Self::available().join(", "))
+        })?;
+        Self::parse(&text).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    /// Names of the built-in themes followed by those in the config directory.
+    pub fn available() -> Vec<String> {
+        let mut names: Vec<String> = BUILT_IN.iter().map(|(name, _)| name.to_string()).collect();
+        let mut user: Vec<String> = fs::read_dir(Self::user_dir())
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "ron"))
+            .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+            .filter(|name| !names.contains(name))
+            .collect();
+        user.sort();
+        names.extend(user);
+        names
+    }
+
+    /// Color of a player slot, or the terminal default if the theme has none.
+    pub fn player(&self, slot: usize) -> Color {
+        if self.players.is_empty() {
+            Color::Reset
+        } else {
+            self.players[slot % self.players.len()]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::glyphs::GlyphSet;
+
+    #[test]
+    fn every_built_in_theme_loads() {
+        for (name, _) in BUILT_IN {
+            assert!(Theme::load(name).is_ok(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn missing_glyphs_come_from_the_unicode_set() {
+        let theme = Theme::parse(r#"(name: "bricks", glyphs: Some((wall: "[]")))"#).unwrap();
+        let glyphs = theme.glyphs.unwrap();
+        assert_eq!(glyphs.wall, "[]");
+        assert_eq!(glyphs.bomb, GlyphSet::Unicode.glyphs().bomb);
+    }
+
+    #[test]
+    fn board_glyphs_must_be_two_columns_wide() {
+        let padded = Theme::parse(r#"(name: "thin", glyphs: Some((bomb: "o")))"#).unwrap();
+        assert_eq!(padded.glyphs.unwrap().bomb, "o ");
+        assert!(Theme::parse(r#"(name: "wide", glyphs: Some((wall: "[=]")))"#).is_err());
+        assert!(Theme::parse(r#"(name: "empty", glyphs: Some((player: "")))"#).is_err());
+        assert!(Theme::parse(r#"(name: "emoji", glyphs: Some((bomb: "💣💣")))"#).is_err());
+    }
+}
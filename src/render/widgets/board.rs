@@ -0,0 +1,329 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::Widget,
+};
+
+use crate::game::{
+    components::ItemType,
+    entity::{Entity, EntityType},
+    mode::GameMode,
+    systems::{
+        danger::DangerMap,
+        visibility::{TerrainMemory, Visibility},
+    },
+    world::Tile,
+    GameState,
+};
+use crate::render::{
+    accessibility::Accessibility,
+    camera::{Camera, Zoom},
+    glyphs::Glyphs,
+    theme::Theme,
+};
+
+/// The part of the world under the camera, drawn cell by cell into the
+/// buffer. Several things can share a tile; the one on top is drawn and
+/// the one just below it shows through as the cell's background, so a
+/// bomb under its bomber or an item under an enemy stays visible.
+pub struct Board<'a> {
+    pub state: &'a GameState,
+    pub camera: Camera,
+    pub zoom: Zoom,
+    /// Glyphs already fitted to the zoom.
+    pub glyphs: &'a Glyphs,
+    pub theme: &'a Theme,
+    pub accessibility: Accessibility,
+    pub danger: Option<&'a DangerMap>,
+    /// Alternate phase for blinking fuses and flashing blasts.
+    pub blink: bool,
+    pub fog: Option<Fog<'a>>,
+}
+
+/// What can be seen on a dark stage, and what is remembered of the rest.
+#[derive(Clone, Copy)]
+pub struct Fog<'a> {
+    pub visibility: &'a Visibility,
+    pub memory: &'a TerrainMemory,
+}
+
+/// Drawing order of a tile's contents, bottom to top.
+pub fn layer(entity: &Entity) -> u8 {
+    match entity.entity_type {
+        EntityType::Item => 1,
+        EntityType::Bomb => 2,
+        EntityType::Explosion => 3,
+        EntityType::Enemy => 4,
+        EntityType::Player => 5,
+    }
+}
+
+impl Widget for Board<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let camera = self.camera;
+        let cell_width = self.zoom.cell_width();
+
+        let mut stacks: Vec<Vec<&Entity>> = vec![Vec::new(); camera.width * camera.height];
+        for entity in self.state.entities.iter().filter(|e| e.is_alive) {
+            let (x, y) = (entity.position.x, entity.position.y);
+            if (camera.x..camera.x + camera.width).contains(&x)
+                && (camera.y..camera.y + camera.height).contains(&y)
+            {
+                stacks[(y - camera.y) * camera.width + (x - camera.x)].push(entity);
+            }
+        }
+        for stack in &mut stacks {
+            stack.sort_by_key(|entity| std::cmp::Reverse(layer(entity)));
+        }
+
+        let width = (camera.width * cell_width) as u16;
+        let left = area.x + area.width.saturating_sub(width) / 2;
+        for row in 0..camera.height.min(area.height as usize) {
+            for column in 0..camera.width {
+                let x = left + (column * cell_width) as u16;
+                if x + cell_width as u16 > area.right() {
+                    break;
+                }
+                let stack = &stacks[row * camera.width + column];
+                let (text, style) = self.cell(camera.x + column, camera.y + row, stack);
+                let y = area.y + row as u16;
+                let (end, _) = buf.set_stringn(x, y, &text, cell_width, style);
+                for pad in end..x + cell_width as u16 {
+                    buf[(pad, y)].set_symbol(" ").set_style(style);
+                }
+            }
+        }
+    }
+}
+
+impl Board<'_> {
+    fn cell(&self, x: usize, y: usize, stack: &[&Entity]) -> (String, Style) {
+        if let Some(fog) = self.fog
+            && !fog.visibility.is_visible(x, y)
+        {
+            let remembered = fog.memory.get(x, y).unwrap_or(Tile::Empty);
+            return (self.glyphs.tile(remembered).to_string(), self.theme.board.fog.style());
+        }
+
+        let tile = self.state.world.get_tile(x, y).unwrap_or(Tile::Empty);
+        let (mut text, mut style) = match stack.first() {
+            Some(top) => (self.entity_text(top), self.entity_style(top)),
+            None => (self.glyphs.tile(tile).to_string(), self.tile_style(tile)),
+        };
+
+        if let Some(below) = stack.get(1) {
+            style = show_underneath(style, self.entity_style(below));
+        }
+
+        if let Some(time) = self.danger.and_then(|d| d.time_until(x, y)) {
+            let soon = time <= 1.0;
+            let spec = if soon {
+                &self.theme.board.danger_soon
+            } else {
+                &self.theme.board.danger_later
+            };
+            style = style.patch(spec.style());
+            if self.accessibility.labels && stack.is_empty() && tile == Tile::Empty {
+                let mark = if soon { "!" } else { ":" };
+                text = mark.repeat(self.zoom.cell_width());
+            }
+        }
+
+        (text, style)
+    }
+
+    /// Slot of a bomber in a battle; the story hero has none.
+    fn battle_slot(&self, entity: &Entity) -> Option<usize> {
+        self.state
+            .player_slot(entity.id)
+            .filter(|_| self.state.mode == GameMode::Battle)
+    }
+
+    fn entity_text(&self, entity: &Entity) -> String {
+        if self.accessibility.labels {
+            match entity.entity_type {
+                EntityType::Player if let Some(slot) = self.battle_slot(entity) => {
+                    return match self.zoom {
+                        Zoom::Near => format!("P{}", slot + 1),
+                        Zoom::Far => (slot + 1).to_string(),
+                    };
+                }
+                EntityType::Bomb if self.zoom == Zoom::Near
+                    && let Some(bomb) = &entity.bomb_data =>
+                {
+                    return format!("({}", bomb.timer.ceil().max(1.0) as u32);
+                }
+                _ => {}
+            }
+        }
+        self.glyphs.entity(entity).to_string()
+    }
+
+    /// Battle bombers wear their slot color when the theme has player
+    /// colors; everything else is styled straight from the theme.
+    fn entity_style(&self, entity: &Entity) -> Style {
+        let board = &self.theme.board;
+        match entity.entity_type {
+            EntityType::Player => {
+                let style = match self
+                    .battle_slot(entity)
+                    .filter(|_| !self.theme.players.is_empty())
+                {
+                    Some(slot) => Style::default().fg(Color::Black).bg(self.theme.player(slot)),
+                    None => board.player.style(),
+                };
+                // A revived hero flickers while its shield lasts.
+                if entity.is_shielded() && (self.accessibility.reduced_motion || self.blink) {
+                    style.add_modifier(Modifier::DIM)
+                } else {
+                    style
+                }
+            }
+            EntityType::Item => match entity.item_type {
+                Some(ItemType::Fire) => board.fire_item.style(),
+                Some(ItemType::Kick | ItemType::Remote) => board.ability_item.style(),
+                _ => board.bomb_item.style(),
+            },
+            EntityType::Enemy => board.enemy.style(),
+            EntityType::Bomb => {
+                let lit = entity.bomb_data.as_ref().is_some_and(|bomb| bomb.timer <= 1.0);
+                if lit && (self.accessibility.reduced_motion || self.blink) {
+                    board.bomb_fuse.style()
+                } else {
+                    board.bomb.style()
+                }
+            }
+            EntityType::Explosion if self.blink => {
+                board.explosion.style().add_modifier(Modifier::REVERSED)
+            }
+            EntityType::Explosion => board.explosion.style(),
+        }
+    }
+
+    fn tile_style(&self, tile: Tile) -> Style {
+        let board = &self.theme.board;
+        match tile {
+            Tile::Wall => board.wall.style(),
+            Tile::Breakable => board.breakable.style(),
+            Tile::Empty => board.empty.style(),
+        }
+    }
+}
+
+/// Gives the top of a tile the color of what lies below it as background.
+/// A top that already had a background keeps that color as its text. Themes
+/// without colors underline instead.
+fn show_underneath(top: Style, below: Style) -> Style {
+    match below.bg.or(below.fg) {
+        Some(under) => {
+            let top = match top.bg {
+                Some(bg) => top.fg(bg),
+                None => top,
+            };
+            top.bg(under)
+        }
+        None => top.add_modifier(Modifier::UNDERLINED),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{
+        components::Position,
+        mode::{BattleRules, BomberSlot},
+        stage::StageConfig,
+    };
+    use crate::render::glyphs::GlyphSet;
+
+    /// Two bombers in a corridor, the first on a bomb with two seconds left.
+    fn corridor() -> GameState {
+        let stage = StageConfig {
+            name: "corridor".to_string(),
+            width: 7,
+            height: 3,
+            layout: ["#######", "#1   2#", "#######"].map(String::from).to_vec(),
+            enemies: Vec::new(),
+            sight: None,
+        };
+        let mut state = GameState::new_battle(stage, &[BomberSlot::Human; 2], BattleRules::default(), 1);
+        state.place_bomb(state.players[1]);
+        if let Some(bomb) = state.entities.iter_mut().find_map(|e| e.bomb_data.as_mut()) {
+            bomb.timer = 0.5;
+        }
+        state
+    }
+
+    fn draw(state: &GameState, accessibility: Accessibility, danger: Option<&DangerMap>) -> Buffer {
+        let glyphs = GlyphSet::Ascii.glyphs();
+        let theme = Theme::classic();
+        let board = Board {
+            state,
+            camera: Camera { x: 0, y: 0, width: 7, height: 3 },
+            zoom: Zoom::Near,
+            glyphs: &glyphs,
+            theme: &theme,
+            accessibility,
+            danger,
+            blink: false,
+            fog: None,
+        };
+        let area = Rect::new(0, 0, 14, 3);
+        let mut buf = Buffer::empty(area);
+        board.render(area, &mut buf);
+        buf
+    }
+
+    fn cell(buf: &Buffer, x: u16, y: u16) -> String {
+        format!("{}{}", buf[(x * 2, y)].symbol(), buf[(x * 2 + 1, y)].symbol())
+    }
+
+    #[test]
+    fn labels_name_bombers_and_fuses_and_mark_danger() {
+        let mut state = corridor();
+        let bomber = state.players[1];
+        if let Some(entity) = state.entities.iter_mut().find(|e| e.id == bomber) {
+            entity.position = Position::new(3, 1);
+        }
+        let danger = state.danger_map();
+        let labels = Accessibility { labels: true, ..Accessibility::default() };
+
+        let buf = draw(&state, labels, Some(&danger));
+        assert_eq!(cell(&buf, 1, 1), "P1");
+        assert_eq!(cell(&buf, 3, 1), "P2");
+        assert_eq!(cell(&buf, 5, 1), "(1");
+        assert_eq!(cell(&buf, 4, 1), "!!");
+
+        let buf = draw(&state, Accessibility::default(), Some(&danger));
+        assert_eq!(cell(&buf, 1, 1), "@@");
+        assert_eq!(cell(&buf, 4, 1), "  ");
+    }
+
+    #[test]
+    fn later_danger_is_marked_apart_from_imminent_danger() {
+        let mut state = corridor();
+        let bomber = state.players[1];
+        state.entities.retain(|e| e.id != bomber);
+        if let Some(bomb) = state.entities.iter_mut().find_map(|e| e.bomb_data.as_mut()) {
+            bomb.timer = 2.5;
+        }
+        let danger = state.danger_map();
+        let buf = draw(&state, Accessibility { labels: true, ..Accessibility::default() }, Some(&danger));
+
+        assert_eq!(cell(&buf, 4, 1), "::");
+        assert_eq!(cell(&buf, 5, 1), "(3");
+    }
+
+    #[test]
+    fn reduced_motion_holds_a_lit_bomb_in_its_fuse_style() {
+        let mut state = corridor();
+        let bomber = state.players[1];
+        state.entities.retain(|e| e.id != bomber);
+        let fuse = Theme::classic().board.bomb_fuse.style().bg;
+
+        let still = Accessibility { reduced_motion: true, ..Accessibility::default() };
+        assert_eq!(Some(draw(&state, still, None)[(10, 1)].bg), fuse);
+        assert_ne!(Some(draw(&state, Accessibility::default(), None)[(10, 1)].bg), fuse);
+    }
+}
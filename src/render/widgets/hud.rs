@@ -1,90 +1,483 @@
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Paragraph},
+    widgets::{Block, BorderType, Borders, Paragraph},
     Frame,
 };
 
-use crate::game::{entity::EntityType, GameState};
+use crate::game::{
+    components::{EnemyKind, PlayerStats},
+    entity::EntityType,
+    mode::{GameMode, MatchState},
+    state::STORY_LIVES,
+    GameState,
+};
+use crate::render::{glyphs::Glyphs, theme::Theme};
+
+/// Everything the HUD shows or is styled by besides the game itself.
+pub struct HudContext<'a> {
+    pub match_state: Option<&'a MatchState>,
+    pub controls: &'a [String],
+    /// Player slot whose panel stands out.
+    pub highlighted: Option<usize>,
+    pub glyphs: &'a Glyphs,
+    pub theme: &'a Theme,
+    /// Alternate phase for flashing banners; never set with reduced motion.
+    pub blink: bool,
+}
+
+/// Columns the bomber panel of the story HUD takes.
+const STATS_WIDTH: u16 = 24;
+/// Narrowest the story status panel may get before the HUD folds into one.
+const STATUS_MIN_WIDTH: u16 = 26;
+/// Narrowest a bomber's battle panel may get before they become a list.
+const PANEL_MIN_WIDTH: u16 = 17;
+const MATCH_WIDTH: u16 = 18;
+
+/// Rows the HUD needs at `width`: five, or one per bomber plus borders
+/// when a narrow battle HUD lists them.
+pub fn height(game_state: &GameState, width: u16) -> u16 {
+    if game_state.mode == GameMode::Battle && battle_is_compact(game_state, width) {
+        (game_state.players.len() as u16 + 2).max(5)
+    } else {
+        5
+    }
+}
+
+fn battle_is_compact(game_state: &GameState, width: u16) -> bool {
+    width / (game_state.players.len() as u16 + 1) < PANEL_MIN_WIDTH
+}
 
-pub fn render_hud(frame: &mut Frame, area: Rect, game_state: &GameState) {
+pub fn render_hud(frame: &mut Frame, area: Rect, game_state: &GameState, context: &HudContext) {
+    if game_state.mode == GameMode::Battle {
+        if battle_is_compact(game_state, area.width) {
+            render_compact_battle_hud(frame, area, game_state, context);
+        } else {
+            render_battle_hud(frame, area, game_state, context);
+        }
+        return;
+    }
+
+    let controls_width = context
+        .controls
+        .iter()
+        .map(|keys| Line::from(keys.as_str()).width() as u16 + 2)
+        .max()
+        .unwrap_or(0);
+    if area.width >= STATS_WIDTH + STATUS_MIN_WIDTH + controls_width {
+        let [stats, status, controls] = Layout::horizontal([
+            Constraint::Length(STATS_WIDTH),
+            Constraint::Min(STATUS_MIN_WIDTH),
+            Constraint::Length(controls_width),
+        ])
+        .areas(area);
+        render_player_stats(frame, stats, game_state, context);
+        render_game_info(frame, status, game_state, context);
+        render_controls(frame, controls, context);
+    } else if area.width >= STATS_WIDTH + STATUS_MIN_WIDTH {
+        let [stats, status] = Layout::horizontal([
+            Constraint::Length(STATS_WIDTH),
+            Constraint::Min(STATUS_MIN_WIDTH),
+        ])
+        .areas(area);
+        render_player_stats(frame, stats, game_state, context);
+        render_game_info(frame, status, game_state, context);
+    } else {
+        render_compact_story_hud(frame, area, game_state, context);
+    }
+}
+
+fn render_battle_hud(frame: &mut Frame, area: Rect, game_state: &GameState, context: &HudContext) {
+    let HudContext {
+        match_state,
+        controls,
+        highlighted,
+        glyphs,
+        theme,
+        ..
+    } = *context;
+    let panels = game_state.players.len() as u32 + 1;
     let chunks = Layout::default()
         .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage(33),
-            Constraint::Percentage(34),
-            Constraint::Percentage(33),
-        ])
+        .constraints(vec![Constraint::Ratio(1, panels); panels as usize])
         .split(area);
 
-    render_player_stats(frame, chunks[0], game_state);
-    render_game_info(frame, chunks[1], game_state);
-    render_controls(frame, chunks[2]);
+    let mut human_index = 0;
+    for (slot, entity_id) in game_state.players.iter().enumerate() {
+        let player = game_state.entities.iter().find(|e| e.id == *entity_id);
+        let stats = player.and_then(|p| p.player_stats.as_ref());
+        let alive = player.map(|p| p.is_alive).unwrap_or(false);
+        let label = if game_state.is_cpu(slot) {
+            "CPU".to_string()
+        } else {
+            let keys = controls.get(human_index).cloned().unwrap_or_default();
+            human_index += 1;
+            keys
+        };
+        let wins = match_state
+            .and_then(|m| m.wins.get(game_state.rules.side_of(slot)))
+            .copied()
+            .unwrap_or(0);
+        let name = if game_state.rules.teams {
+            let team = (b'A' + game_state.rules.side_of(slot) as u8) as char;
+            format!("P{} [{}] {}", slot + 1, team, label)
+        } else {
+            format!("P{} {}", slot + 1, label)
+        };
+
+        let status = if alive {
+            Span::styled(glyphs.alive.as_str(), theme.hud.good.style())
+        } else {
+            Span::styled(glyphs.dead.as_str(), theme.hud.bad.style())
+        };
+
+        let mut fire = vec![Span::raw(format!(
+            "{} {}",
+            glyphs.fire_icon,
+            stats.map(|s| s.bomb_range).unwrap_or(0)
+        ))];
+        fire.extend(ability_spans(stats, false, theme));
+
+        let text = vec![
+            Line::from(vec![
+                status,
+                Span::raw(format!(
+                    " {} {}/{}",
+                    glyphs.bomb_icon,
+                    game_state.active_bombs(*entity_id),
+                    stats.map(|s| s.max_bombs).unwrap_or(0)
+                )),
+            ]),
+            Line::from(fire),
+            Line::from(Span::styled(
+                glyphs.win.repeat(wins as usize),
+                theme.hud.accent.style(),
+            )),
+        ];
+
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme.player(slot)));
+        if highlighted == Some(slot) {
+            block = block
+                .border_type(BorderType::Double)
+                .border_style(
+                    Style::default()
+                        .fg(theme.player(slot))
+                        .add_modifier(Modifier::BOLD),
+                )
+                .title(format!("▶ {}", name));
+        } else {
+            block = block.title(name);
+        }
+        let paragraph = Paragraph::new(text).block(block);
+        frame.render_widget(paragraph, chunks[slot]);
+    }
+
+    render_match_info(frame, chunks[game_state.players.len()], game_state, context);
 }
 
-fn render_player_stats(frame: &mut Frame, area: Rect, game_state: &GameState) {
+/// Round, bombers left and the sudden death countdown.
+fn render_match_info(frame: &mut Frame, area: Rect, game_state: &GameState, context: &HudContext) {
+    let HudContext {
+        match_state,
+        theme,
+        blink,
+        ..
+    } = *context;
+    let mut round_info = match match_state {
+        Some(m) => vec![
+            Line::from(format!("Round {} (Bo{})", m.round, m.best_of)),
+            Line::from(format!("残り: {}人", game_state.alive_players().len())),
+        ],
+        None => vec![Line::from(format!("残り: {}人", game_state.alive_players().len()))],
+    };
+    if game_state.rules.sudden_death {
+        let remaining = game_state.rules.sudden_death_after as f32 - game_state.elapsed;
+        round_info.push(if remaining > 0.0 {
+            Line::from(format!("サドンデス {:.0}秒", remaining.ceil()))
+        } else {
+            let mut style = theme.hud.bad.style().add_modifier(Modifier::BOLD);
+            if blink {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            Line::from(Span::styled("SUDDEN DEATH!", style))
+        });
+    }
+    let paragraph = Paragraph::new(round_info)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(theme.hud.border.style())
+                .title("Match"),
+        )
+        .alignment(Alignment::Center);
+    frame.render_widget(paragraph, area);
+}
+
+/// One line per bomber next to the match panel, for terminals too narrow
+/// for a panel each.
+fn render_compact_battle_hud(frame: &mut Frame, area: Rect, game_state: &GameState, context: &HudContext) {
+    let HudContext {
+        match_state,
+        highlighted,
+        glyphs,
+        theme,
+        ..
+    } = *context;
+    let [bombers, match_area] =
+        Layout::horizontal([Constraint::Min(0), Constraint::Length(MATCH_WIDTH)]).areas(area);
+
+    let lines: Vec<Line> = game_state
+        .players
+        .iter()
+        .enumerate()
+        .map(|(slot, entity_id)| {
+            let player = game_state.entities.iter().find(|e| e.id == *entity_id);
+            let stats = player.and_then(|p| p.player_stats.as_ref());
+            let alive = player.is_some_and(|p| p.is_alive);
+            let wins = match_state
+                .and_then(|m| m.wins.get(game_state.rules.side_of(slot)))
+                .copied()
+                .unwrap_or(0);
+            let mut name = Style::default().fg(theme.player(slot)).add_modifier(Modifier::BOLD);
+            if highlighted == Some(slot) {
+                name = name.add_modifier(Modifier::REVERSED);
+            }
+            let status = if alive {
+                Span::styled(glyphs.alive.as_str(), theme.hud.good.style())
+            } else {
+                Span::styled(glyphs.dead.as_str(), theme.hud.bad.style())
+            };
+            let mut line = vec![
+                Span::styled(format!("P{}", slot + 1), name),
+                Span::raw(" "),
+                status,
+                Span::raw(format!(
+                    " {}{}/{} {}{} ",
+                    glyphs.bomb_icon,
+                    game_state.active_bombs(*entity_id),
+                    stats.map(|s| s.max_bombs).unwrap_or(0),
+                    glyphs.fire_icon,
+                    stats.map(|s| s.bomb_range).unwrap_or(0)
+                )),
+                Span::styled(glyphs.win.repeat(wins as usize), theme.hud.accent.style()),
+            ];
+            line.extend(ability_spans(stats, true, theme));
+            Line::from(line)
+        })
+        .collect();
+
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.hud.border.style())
+            .title("Bombers"),
+    );
+    frame.render_widget(paragraph, bombers);
+    render_match_info(frame, match_area, game_state, context);
+}
+
+/// Bombs out against capacity, and fire range.
+fn bomb_spans(game_state: &GameState, glyphs: &Glyphs, theme: &Theme) -> Vec<Span<'static>> {
     let player = game_state.get_player();
     let stats = player.and_then(|p| p.player_stats.as_ref());
+    let active = player.map(|p| game_state.active_bombs(p.id)).unwrap_or(0);
+    vec![
+        Span::styled(format!("{} ", glyphs.bomb_icon), theme.hud.accent.style()),
+        Span::raw(format!("{}/{}", active, stats.map(|s| s.max_bombs).unwrap_or(0))),
+        Span::styled(format!("  {} ", glyphs.fire_icon), theme.hud.bad.style()),
+        Span::raw(stats.map(|s| s.bomb_range).unwrap_or(0).to_string()),
+    ]
+}
 
-    let max_bombs = stats.map(|s| s.max_bombs).unwrap_or(0);
-    let bomb_range = stats.map(|s| s.bomb_range).unwrap_or(0);
-
-    let text = vec![
-        Line::from(vec![
-            Span::styled("💣: ", Style::default().fg(Color::Yellow)),
-            Span::raw(format!("{}", max_bombs)),
-        ]),
-        Line::from(vec![
-            Span::styled("🔥: ", Style::default().fg(Color::Red)),
-            Span::raw(format!("{}", bomb_range)),
-        ]),
+/// Power-ups picked up this stage, as counts over the starting stats.
+fn power_up_spans(game_state: &GameState, glyphs: &Glyphs, theme: &Theme) -> Vec<Span<'static>> {
+    let start = game_state.starting_stats();
+    let stats = game_state.get_player().and_then(|p| p.player_stats.as_ref());
+    let gained = [
+        (&glyphs.fire_icon, stats.map(|s| s.bomb_range).unwrap_or(0), start.bomb_range),
+        (&glyphs.bomb_icon, stats.map(|s| s.max_bombs).unwrap_or(0), start.max_bombs),
     ];
+    let mut spans = vec![Span::raw("取得 ")];
+    for (icon, now, base) in gained {
+        if now > base {
+            spans.push(Span::styled(format!("{}+{} ", icon, now - base), theme.hud.good.style()));
+        }
+    }
+    if spans.len() == 1 {
+        spans.push(Span::styled("なし", theme.hud.border.style()));
+    }
+    spans
+}
 
-    let paragraph = Paragraph::new(text)
-        .block(Block::default().borders(Borders::ALL).title("Stats"))
-        .alignment(Alignment::Left);
+/// Kick and remote, if the bomber has them; `short` names them by letter.
+fn ability_spans(stats: Option<&PlayerStats>, short: bool, theme: &Theme) -> Vec<Span<'static>> {
+    let Some(stats) = stats else {
+        return Vec::new();
+    };
+    [(stats.kick, "Kick"), (stats.remote, "Remote")]
+        .into_iter()
+        .filter(|(has, _)| *has)
+        .map(|(_, name)| {
+            let name = if short { &name[..1] } else { name };
+            Span::styled(format!(" {}", name), theme.hud.good.style())
+        })
+        .collect()
+}
 
-    frame.render_widget(paragraph, area);
+/// Lives left out of those the story started with.
+fn lives_spans(game_state: &GameState, glyphs: &Glyphs, theme: &Theme) -> Vec<Span<'static>> {
+    let lost = STORY_LIVES.saturating_sub(game_state.lives);
+    vec![
+        Span::styled(glyphs.alive.repeat(game_state.lives as usize), theme.hud.good.style()),
+        Span::styled(glyphs.dead.repeat(lost as usize), theme.hud.border.style()),
+    ]
+}
+
+fn time_and_score(game_state: &GameState, theme: &Theme) -> Vec<Span<'static>> {
+    vec![
+        Span::raw(format!("Time {}  ", clock(game_state.elapsed))),
+        Span::styled(format!("Score {}", game_state.score), theme.hud.accent.style()),
+    ]
 }
 
-fn render_game_info(frame: &mut Frame, area: Rect, game_state: &GameState) {
+/// Game over, clear, or how many enemies are left.
+fn story_status(game_state: &GameState, theme: &Theme) -> Span<'static> {
     let enemies_alive = game_state
         .entities
         .iter()
         .filter(|e| e.entity_type == EntityType::Enemy && e.is_alive)
         .count();
-
     let player_alive = game_state
         .get_player()
         .map(|p| p.is_alive)
         .unwrap_or(false);
 
-    let status = if !player_alive {
-        Span::styled("GAME OVER", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))
+    if !player_alive {
+        Span::styled("GAME OVER", theme.hud.bad.style().add_modifier(Modifier::BOLD))
     } else if enemies_alive == 0 {
-        Span::styled("CLEAR!", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD))
+        Span::styled("CLEAR!", theme.hud.good.style().add_modifier(Modifier::BOLD))
     } else {
-        Span::styled(format!("敵: {}", enemies_alive), Style::default().fg(Color::Cyan))
-    };
+        Span::styled(format!("敵: {}", enemies_alive), theme.hud.info.style())
+    }
+}
+
+/// One icon and count per kind of enemy still alive.
+fn enemy_roster(game_state: &GameState, glyphs: &Glyphs, theme: &Theme) -> Line<'static> {
+    let spans: Vec<Span> = ENEMY_KINDS
+        .into_iter()
+        .filter_map(|kind| {
+            let count = game_state
+                .entities
+                .iter()
+                .filter(|e| e.is_alive && e.enemy_kind() == Some(kind))
+                .count();
+            (count > 0).then(|| Span::styled(format!("{}×{} ", glyphs.enemy(kind), count), theme.hud.info.style()))
+        })
+        .collect();
+    Line::from(spans)
+}
+
+const ENEMY_KINDS: [EnemyKind; 6] = [
+    EnemyKind::Walker,
+    EnemyKind::Chaser,
+    EnemyKind::Ghost,
+    EnemyKind::Tank,
+    EnemyKind::Splitter,
+    EnemyKind::Fragment,
+];
 
-    let text = vec![Line::from(status)];
+fn clock(seconds: f32) -> String {
+    let seconds = seconds.max(0.0) as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn render_player_stats(frame: &mut Frame, area: Rect, game_state: &GameState, context: &HudContext) {
+    let HudContext { glyphs, theme, .. } = *context;
+    let stats = game_state.get_player().and_then(|p| p.player_stats.as_ref());
+    let mut lives = vec![Span::raw("残機 ")];
+    lives.extend(lives_spans(game_state, glyphs, theme));
+    lives.extend(ability_spans(stats, false, theme));
+    let text = vec![
+        Line::from(bomb_spans(game_state, glyphs, theme)),
+        Line::from(power_up_spans(game_state, glyphs, theme)),
+        Line::from(lives),
+    ];
+
+    let paragraph = Paragraph::new(text)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(theme.hud.border.style())
+                .title("Stats"),
+        )
+        .alignment(Alignment::Left);
+
+    frame.render_widget(paragraph, area);
+}
+
+fn render_game_info(frame: &mut Frame, area: Rect, game_state: &GameState, context: &HudContext) {
+    let HudContext { glyphs, theme, .. } = *context;
+    let text = vec![
+        Line::from(time_and_score(game_state, theme)),
+        Line::from(story_status(game_state, theme)),
+        enemy_roster(game_state, glyphs, theme),
+    ];
 
     let paragraph = Paragraph::new(text)
-        .block(Block::default().borders(Borders::ALL).title("Status"))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(theme.hud.border.style())
+                .title("Status"),
+        )
         .alignment(Alignment::Center);
 
     frame.render_widget(paragraph, area);
 }
 
-fn render_controls(frame: &mut Frame, area: Rect) {
+/// Everything in one panel, for terminals too narrow for the others.
+fn render_compact_story_hud(frame: &mut Frame, area: Rect, game_state: &GameState, context: &HudContext) {
+    let HudContext { glyphs, theme, .. } = *context;
+    let stats = game_state.get_player().and_then(|p| p.player_stats.as_ref());
+    let mut first = bomb_spans(game_state, glyphs, theme);
+    first.extend(ability_spans(stats, true, theme));
+    first.push(Span::raw("  "));
+    first.push(story_status(game_state, theme));
+    let mut second = time_and_score(game_state, theme);
+    second.push(Span::raw("  "));
+    second.extend(lives_spans(game_state, glyphs, theme));
     let text = vec![
-        Line::from("hjkl: 移動"),
-        Line::from("Space: 爆弾"),
+        Line::from(first),
+        Line::from(second),
+        enemy_roster(game_state, glyphs, theme),
     ];
 
+    let paragraph = Paragraph::new(text).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.hud.border.style())
+            .title("Status"),
+    );
+    frame.render_widget(paragraph, area);
+}
+
+fn render_controls(frame: &mut Frame, area: Rect, context: &HudContext) {
+    let text = context
+        .controls
+        .iter()
+        .map(|keys| Line::from(keys.as_str()))
+        .collect::<Vec<_>>();
+
     let paragraph = Paragraph::new(text)
-        .block(Block::default().borders(Borders::ALL).title("Controls"))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(context.theme.hud.border.style())
+                .title("Controls"),
+        )
         .alignment(Alignment::Left);
 
     frame.render_widget(paragraph, area);
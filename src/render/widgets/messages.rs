@@ -0,0 +1,329 @@
+use std::collections::VecDeque;
+
+use ratatui::{
+    layout::Rect,
+    style::Style,
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::game::{
+    components::ItemType,
+    events::{EventKind, GameEvent},
+    mode::GameMode,
+    GameState,
+};
+use crate::render::theme::Theme;
+
+/// Seconds a toast stays up.
+const TOAST_SECONDS: f32 = 2.0;
+/// Toasts shown at once; older ones make way for new ones.
+const MAX_TOASTS: usize = 3;
+/// Messages the log keeps.
+const LOG_CAPACITY: usize = 200;
+const LOG_WIDTH: u16 = 36;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tone {
+    Good,
+    Bad,
+    Accent,
+    Info,
+}
+
+impl Tone {
+    fn style(self, theme: &Theme) -> Style {
+        match self {
+            Tone::Good => theme.hud.good.style(),
+            Tone::Bad => theme.hud.bad.style(),
+            Tone::Accent => theme.hud.accent.style(),
+            Tone::Info => theme.hud.info.style(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Message {
+    /// Round time, or `None` for notes between rounds.
+    pub at: Option<f32>,
+    pub text: String,
+    pub tone: Tone,
+}
+
+/// Game events turned into text: a few short-lived toasts over the board
+/// and a log of everything, which can be opened and scrolled back.
+#[derive(Debug, Default)]
+pub struct Messages {
+    log: VecDeque<Message>,
+    /// Toasts with the seconds they have left.
+    toasts: Vec<(Message, f32)>,
+    /// Round time of the newest event already taken, so an event a rolled
+    /// back state reports again is not shown twice.
+    seen: f32,
+    pub open: bool,
+    /// Lines scrolled back from the newest.
+    scroll: usize,
+}
+
+impl Messages {
+    /// Takes the events that happened since the last call.
+    pub fn collect(&mut self, state: &GameState) {
+        let new: Vec<&GameEvent> = state.events.iter().filter(|e| e.at > self.seen).collect();
+        for event in new {
+            self.seen = event.at;
+            let (text, tone) = describe(state, event.kind);
+            self.push(Message {
+                at: Some(event.at),
+                text,
+                tone,
+            });
+        }
+    }
+
+    /// Round time starts over; the log keeps going with a note.
+    pub fn new_round(&mut self, note: String) {
+        self.seen = 0.0;
+        self.toasts.clear();
+        self.log.push_back(Message {
+            at: None,
+            text: note,
+            tone: Tone::Info,
+        });
+        self.trim();
+    }
+
+    /// Ages the toasts by `delta_time` seconds.
+    pub fn tick(&mut self, delta_time: f32) {
+        for (_, left) in &mut self.toasts {
+            *left -= delta_time;
+        }
+        self.toasts.retain(|(_, left)| *left > 0.0);
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.scroll = 0;
+    }
+
+    /// Scrolls back (positive) or forward (negative) by `lines`.
+    pub fn scroll(&mut self, lines: isize) {
+        let max = self.log.len().saturating_sub(1);
+        self.scroll = self.scroll.saturating_add_signed(lines).min(max);
+    }
+
+    fn push(&mut self, message: Message) {
+        if self.toasts.len() == MAX_TOASTS {
+            self.toasts.remove(0);
+        }
+        self.toasts.push((message.clone(), TOAST_SECONDS));
+        self.log.push_back(message);
+        if self.scroll > 0 {
+            self.scroll += 1;
+        }
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        while self.log.len() > LOG_CAPACITY {
+            self.log.pop_front();
+        }
+    }
+}
+
+fn describe(state: &GameState, kind: EventKind) -> (String, Tone) {
+    let name = |id: usize| match state.player_slot(id) {
+        Some(slot) if state.mode == GameMode::Battle => format!("P{}", slot + 1),
+        _ => "You".to_string(),
+    };
+    let prefix = |id: Option<usize>| match id {
+        Some(id) if state.mode == GameMode::Battle && state.player_slot(id).is_some() => {
+            format!("{} ", name(id))
+        }
+        _ => String::new(),
+    };
+    match kind {
+        EventKind::ItemCollected { player, item } => {
+            let gain = match item {
+                ItemType::Fire | ItemType::Bomb => "+1 ",
+                ItemType::Kick | ItemType::Remote => "",
+            };
+            (format!("{}{}{}!", prefix(Some(player)), gain, item.name()), Tone::Good)
+        }
+        EventKind::EnemyDefeated { kind, score, by } => (
+            format!("{}{} defeated +{}", prefix(by), kind.name(), score),
+            Tone::Accent,
+        ),
+        EventKind::Chain { bombs, by } => {
+            (format!("{}Chain x{}!", prefix(Some(by)), bombs), Tone::Accent)
+        }
+        EventKind::BomberDefeated { victim, by } => {
+            let text = match by {
+                Some(by) if by == victim => format!("{} blew up", name(victim)),
+                Some(by) if state.mode == GameMode::Battle => {
+                    format!("{} defeated {}", name(by), name(victim))
+                }
+                _ => format!("{} went down", name(victim)),
+            };
+            (text, Tone::Bad)
+        }
+        EventKind::LifeLost { left } => (
+            format!("{} {} left", left, if left == 1 { "life" } else { "lives" }),
+            Tone::Bad,
+        ),
+    }
+}
+
+/// The live toasts stacked in the top right corner of `area`, newest first.
+pub fn render_toasts(frame: &mut Frame, area: Rect, messages: &Messages, theme: &Theme) {
+    let overlay = theme.hud.overlay.style();
+    for (row, (message, _)) in messages.toasts.iter().rev().enumerate() {
+        if row as u16 >= area.height {
+            break;
+        }
+        let text = format!(" {} ", message.text);
+        let width = (Line::from(text.as_str()).width() as u16).min(area.width);
+        let toast = Rect {
+            x: area.right() - width,
+            y: area.y + row as u16,
+            width,
+            height: 1,
+        };
+        frame.render_widget(Clear, toast);
+        frame.render_widget(
+            Paragraph::new(text).style(overlay.patch(message.tone.style(theme))),
+            toast,
+        );
+    }
+}
+
+/// The log as a panel down the right side of `area`, scrolled back as
+/// far as the player has asked.
+pub fn render_log(frame: &mut Frame, area: Rect, messages: &Messages, close: &str, theme: &Theme) {
+    let width = LOG_WIDTH.min(area.width);
+    let panel = Rect {
+        x: area.right() - width,
+        width,
+        ..area
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(theme.hud.border.style())
+        .style(theme.hud.overlay.style())
+        .title("Log")
+        .title_bottom(format!(" PgUp/PgDn | {} ", close));
+    let inner = block.inner(panel);
+
+    let end = messages.log.len() - messages.scroll.min(messages.log.len());
+    let start = end.saturating_sub(inner.height as usize);
+    let lines: Vec<Line> = messages
+        .log
+        .range(start..end)
+        .map(|message| {
+            let time = match message.at {
+                Some(at) => format!("{}:{:02} ", at as u32 / 60, at as u32 % 60),
+                None => String::new(),
+            };
+            Line::from(vec![
+                Span::styled(time, theme.hud.info.style()),
+                Span::styled(message.text.as_str(), message.tone.style(theme)),
+            ])
+        })
+        .collect();
+
+    frame.render_widget(Clear, panel);
+    frame.render_widget(Paragraph::new(lines).block(block), panel);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A story state with a chain reported at `at` seconds.
+    fn chain_at(state: &mut GameState, at: f32) {
+        state.elapsed = at;
+        state.push_event(EventKind::Chain { bombs: 2, by: state.player_id });
+    }
+
+    fn texts(messages: &Messages) -> Vec<&str> {
+        messages.log.iter().map(|message| message.text.as_str()).collect()
+    }
+
+    #[test]
+    fn events_a_rolled_back_state_reports_again_are_taken_once() {
+        let mut state = GameState::new(1);
+        let mut messages = Messages::default();
+        chain_at(&mut state, 0.1);
+        let mut replayed = state.clone();
+        messages.collect(&state);
+
+        // Replaying from a snapshot reports the same event again.
+        messages.collect(&replayed);
+        chain_at(&mut replayed, 0.2);
+        messages.collect(&replayed);
+
+        assert_eq!(texts(&messages), ["Chain x2!", "Chain x2!"]);
+        assert_eq!(messages.log.iter().map(|m| m.at).collect::<Vec<_>>(), [Some(0.1), Some(0.2)]);
+    }
+
+    #[test]
+    fn a_new_round_takes_events_from_the_start_again() {
+        let mut state = GameState::new(1);
+        let mut messages = Messages::default();
+        chain_at(&mut state, 5.0);
+        messages.collect(&state);
+
+        messages.new_round("── Round 2 ──".to_string());
+        let mut state = GameState::new(2);
+        chain_at(&mut state, 0.05);
+        messages.collect(&state);
+
+        assert_eq!(texts(&messages), ["Chain x2!", "── Round 2 ──", "Chain x2!"]);
+        assert_eq!(messages.toasts.len(), 1);
+    }
+
+    #[test]
+    fn scrolling_stays_within_the_log() {
+        let mut messages = Messages::default();
+        for round in 0..5 {
+            messages.new_round(format!("round {}", round));
+        }
+        messages.scroll(100);
+        assert_eq!(messages.scroll, 4);
+        messages.scroll(-2);
+        assert_eq!(messages.scroll, 2);
+        messages.scroll(-100);
+        assert_eq!(messages.scroll, 0);
+    }
+
+    #[test]
+    fn a_scrolled_back_log_holds_its_place_as_messages_arrive() {
+        let mut state = GameState::new(1);
+        let mut messages = Messages::default();
+        for round in 0..3 {
+            messages.new_round(format!("round {}", round));
+        }
+        messages.scroll(1);
+        chain_at(&mut state, 0.1);
+        messages.collect(&state);
+
+        assert_eq!(messages.scroll, 2);
+        messages.toggle();
+        assert_eq!(messages.scroll, 0);
+    }
+
+    #[test]
+    fn toasts_are_few_and_short_lived_while_the_log_is_capped() {
+        let mut state = GameState::new(1);
+        let mut messages = Messages::default();
+        for tick in 1..=LOG_CAPACITY + 10 {
+            chain_at(&mut state, tick as f32 * 0.01);
+            messages.collect(&state);
+        }
+        assert_eq!(messages.toasts.len(), MAX_TOASTS);
+        assert_eq!(messages.log.len(), LOG_CAPACITY);
+
+        messages.tick(TOAST_SECONDS);
+        assert!(messages.toasts.is_empty());
+    }
+}
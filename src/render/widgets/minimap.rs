@@ -0,0 +1,207 @@
+use ratatui::{buffer::Buffer, layout::Rect, style::Color, widgets::Widget};
+
+use super::board::{layer, Fog};
+use crate::game::{
+    components::ItemType,
+    entity::EntityType,
+    mode::GameMode,
+    world::Tile,
+    GameState,
+};
+use crate::render::theme::{StyleSpec, Theme};
+
+/// The whole world in solid colors, two tiles to a cell using the upper
+/// half block: the top tile is the foreground, the bottom one the
+/// background. Bombers, enemies and bombs are drawn over their tiles.
+pub struct Minimap<'a> {
+    pub state: &'a GameState,
+    pub theme: &'a Theme,
+    /// Columns and half-rows per tile.
+    pub scale: usize,
+    /// Only what is in sight is drawn on a dark stage, with remembered
+    /// terrain in the fog color.
+    pub fog: Option<Fog<'a>>,
+}
+
+impl Minimap<'_> {
+    /// Cells the map takes at `scale`.
+    pub fn size(state: &GameState, scale: usize) -> (u16, u16) {
+        let width = state.world.width() * scale;
+        let height = (state.world.height() * scale).div_ceil(2);
+        (width as u16, height as u16)
+    }
+
+    /// The largest scale at which the map fits in `area`, never below one.
+    pub fn fit(state: &GameState, area: Rect) -> usize {
+        (2..)
+            .take_while(|scale| {
+                let (width, height) = Self::size(state, *scale);
+                width <= area.width && height <= area.height
+            })
+            .last()
+            .unwrap_or(1)
+    }
+
+    fn colors(&self) -> Vec<Color> {
+        let world = &self.state.world;
+        let board = &self.theme.board;
+        let mut colors = Vec::with_capacity(world.width() * world.height());
+        let fog_color = fg(&board.fog, Color::DarkGray);
+        let visible = |x: usize, y: usize| self.fog.is_none_or(|fog| fog.visibility.is_visible(x, y));
+        for y in 0..world.height() {
+            for x in 0..world.width() {
+                if !visible(x, y) {
+                    let remembered = self.fog.and_then(|fog| fog.memory.get(x, y));
+                    colors.push(match remembered {
+                        Some(Tile::Wall | Tile::Breakable) => fog_color,
+                        _ => Color::Reset,
+                    });
+                    continue;
+                }
+                colors.push(match world.get_tile(x, y) {
+                    Some(Tile::Wall) => fg(&board.wall, Color::Gray),
+                    Some(Tile::Breakable) => fg(&board.breakable, Color::DarkGray),
+                    _ => board.empty.bg.unwrap_or(Color::Reset),
+                });
+            }
+        }
+
+        let mut entities: Vec<_> = self
+            .state
+            .entities
+            .iter()
+            .filter(|e| e.is_alive && visible(e.position.x, e.position.y))
+            .collect();
+        entities.sort_by_key(|entity| layer(entity));
+        for entity in entities {
+            let color = match entity.entity_type {
+                EntityType::Player => match self.state.player_slot(entity.id) {
+                    Some(slot)
+                        if self.state.mode == GameMode::Battle && !self.theme.players.is_empty() =>
+                    {
+                        self.theme.player(slot)
+                    }
+                    _ => fg(&board.player, Color::White),
+                },
+                EntityType::Enemy => fg(&board.enemy, Color::Gray),
+                EntityType::Bomb => fg(&board.bomb, Color::White),
+                EntityType::Explosion => bg(&board.explosion, Color::White),
+                EntityType::Item => match entity.item_type {
+                    Some(ItemType::Fire) => bg(&board.fire_item, Color::Gray),
+                    Some(ItemType::Kick | ItemType::Remote) => bg(&board.ability_item, Color::Gray),
+                    _ => bg(&board.bomb_item, Color::Gray),
+                },
+            };
+            let (x, y) = (entity.position.x, entity.position.y);
+            if x < world.width() && y < world.height() {
+                colors[y * world.width() + x] = color;
+            }
+        }
+        colors
+    }
+}
+
+impl Widget for Minimap<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let (world_width, world_height) = (self.state.world.width(), self.state.world.height());
+        let colors = self.colors();
+        let tile = |column: usize, half_row: usize| {
+            let (x, y) = (column / self.scale, half_row / self.scale);
+            (y < world_height).then(|| colors[y * world_width + x])
+        };
+
+        let (width, height) = Self::size(self.state, self.scale);
+        for row in 0..height.min(area.height) {
+            for column in 0..width.min(area.width) {
+                let top = tile(column as usize, row as usize * 2).unwrap_or(Color::Reset);
+                let bottom = tile(column as usize, row as usize * 2 + 1).unwrap_or(Color::Reset);
+                buf[(area.x + column, area.y + row)]
+                    .set_symbol("▀")
+                    .set_fg(top)
+                    .set_bg(bottom);
+            }
+        }
+    }
+}
+
+fn fg(spec: &StyleSpec, fallback: Color) -> Color {
+    spec.fg.or(spec.bg).unwrap_or(fallback)
+}
+
+fn bg(spec: &StyleSpec, fallback: Color) -> Color {
+    spec.bg.or(spec.fg).unwrap_or(fallback)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{
+        mode::{BattleRules, BomberSlot},
+        stage::StageConfig,
+        systems::visibility::{TerrainMemory, Visibility},
+    };
+
+    /// Two bombers in a 7×3 corridor.
+    fn corridor() -> GameState {
+        let stage = StageConfig {
+            name: "corridor".to_string(),
+            width: 7,
+            height: 3,
+            layout: ["#######", "#1 X 2#", "#######"].map(String::from).to_vec(),
+            enemies: Vec::new(),
+            sight: None,
+        };
+        GameState::new_battle(stage, &[BomberSlot::Human; 2], BattleRules::default(), 1)
+    }
+
+    fn draw(minimap: Minimap) -> Buffer {
+        let (width, height) = Minimap::size(minimap.state, minimap.scale);
+        let area = Rect::new(0, 0, width, height);
+        let mut buf = Buffer::empty(area);
+        minimap.render(area, &mut buf);
+        buf
+    }
+
+    #[test]
+    fn two_rows_of_tiles_share_a_row_of_cells() {
+        let state = corridor();
+        assert_eq!(Minimap::size(&state, 1), (7, 2));
+        assert_eq!(Minimap::size(&state, 2), (14, 3));
+
+        assert_eq!(Minimap::fit(&state, Rect::new(0, 0, 20, 5)), 2);
+        assert_eq!(Minimap::fit(&state, Rect::new(0, 0, 10, 2)), 1);
+        assert_eq!(Minimap::fit(&state, Rect::new(0, 0, 3, 1)), 1);
+    }
+
+    #[test]
+    fn bombers_and_terrain_get_their_theme_colors() {
+        let state = corridor();
+        let theme = Theme::classic();
+        let buf = draw(Minimap { state: &state, theme: &theme, scale: 1, fog: None });
+        let wall = fg(&theme.board.wall, Color::Gray);
+
+        assert_eq!(buf[(0, 0)].symbol(), "▀");
+        assert_eq!((buf[(0, 0)].fg, buf[(0, 0)].bg), (wall, wall));
+        assert_eq!((buf[(1, 0)].fg, buf[(1, 0)].bg), (wall, theme.player(0)));
+        assert_eq!(buf[(5, 0)].bg, theme.player(1));
+        assert_eq!(buf[(3, 0)].bg, fg(&theme.board.breakable, Color::DarkGray));
+        // The last tile row has nothing under it.
+        assert_eq!((buf[(1, 1)].fg, buf[(1, 1)].bg), (wall, Color::Reset));
+    }
+
+    #[test]
+    fn nothing_out_of_sight_is_drawn() {
+        let mut state = corridor();
+        state.sight = Some(1);
+        let theme = Theme::classic();
+        let visibility = Visibility::compute(&state, &[state.players[0]]).unwrap();
+        let mut memory = TerrainMemory::default();
+        memory.update(&state.world, &visibility);
+        let fog = Fog { visibility: &visibility, memory: &memory };
+        let buf = draw(Minimap { state: &state, theme: &theme, scale: 1, fog: Some(fog) });
+
+        assert_eq!(buf[(1, 0)].bg, theme.player(0));
+        assert_eq!(buf[(5, 0)].bg, Color::Reset);
+        assert_eq!(buf[(3, 0)].bg, Color::Reset);
+    }
+}
@@ -1 +1,6 @@
+pub mod board;
 pub mod hud;
+pub mod messages;
+pub mod minimap;
+pub mod scoreboard;
+pub mod settings;
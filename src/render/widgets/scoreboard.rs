@@ -0,0 +1,76 @@
+use ratatui::{
+    layout::{Alignment, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::game::mode::RoundStats;
+use crate::render::{glyphs::Glyphs, theme::Theme};
+
+/// Result overlay listing what every bomber did, with the round wins of
+/// each player's side.
+pub struct Scoreboard<'a> {
+    pub title: &'a str,
+    pub heading: &'a str,
+    pub stats: &'a [RoundStats],
+    /// Round wins of each player's side, by player slot.
+    pub wins: &'a [u32],
+    pub footer: &'a str,
+}
+
+pub fn render_scoreboard(frame: &mut Frame, scoreboard: &Scoreboard, glyphs: &Glyphs, theme: &Theme) {
+    let Scoreboard {
+        title,
+        heading,
+        stats,
+        wins,
+        footer,
+    } = *scoreboard;
+    let mut text = vec![
+        Line::from(Span::styled(title, theme.hud.title.style())).centered(),
+        Line::from(""),
+        Line::from(Span::styled(heading, Style::default().add_modifier(Modifier::UNDERLINED)))
+            .centered(),
+        Line::from("      キル  自爆  アイテム  勝利"),
+    ];
+
+    for (slot, stats) in stats.iter().enumerate() {
+        text.push(Line::from(vec![
+            Span::raw("  "),
+            Span::styled(format!("P{}", slot + 1), Style::default().fg(theme.player(slot))),
+            Span::raw(format!(
+                "  {:>4}  {:>4}  {:>8}  ",
+                stats.kills, stats.suicides, stats.items
+            )),
+            Span::styled(
+                glyphs.win.repeat(wins.get(slot).copied().unwrap_or(0) as usize),
+                theme.hud.accent.style(),
+            ),
+        ]));
+    }
+
+    text.push(Line::from(""));
+    text.push(Line::from(footer).centered());
+
+    let area = frame.area();
+    let width = 46.min(area.width);
+    let height = (text.len() as u16 + 2).min(area.height);
+    let overlay_area = Rect {
+        x: (area.width - width) / 2,
+        y: (area.height - height) / 2,
+        width,
+        height,
+    };
+    let paragraph = Paragraph::new(text)
+        .alignment(Alignment::Left)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(theme.hud.border.style())
+                .style(theme.hud.overlay.style()),
+        );
+    frame.render_widget(Clear, overlay_area);
+    frame.render_widget(paragraph, overlay_area);
+}
@@ -0,0 +1,95 @@
+use ratatui::{
+    layout::{Alignment, Rect},
+    style::Style,
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::game::{
+    mode::{BattleRules, RuleField},
+    stage::loader::battle_stages,
+};
+use crate::render::theme::Theme;
+
+pub fn render_settings(
+    frame: &mut Frame,
+    rules: &BattleRules,
+    players: usize,
+    cursor: usize,
+    footer: &str,
+    theme: &Theme,
+) {
+    let mut text = vec![
+        Line::from(Span::styled("MATCH SETTINGS", theme.hud.title.style())),
+        Line::from(""),
+    ];
+
+    for (index, field) in RuleField::ALL.iter().enumerate() {
+        let selected = index == cursor;
+        let style = if selected {
+            theme.hud.selected.style()
+        } else {
+            Style::default()
+        };
+        text.push(Line::from(vec![
+            Span::raw(if selected { "▶ " } else { "  " }),
+            Span::styled(label(*field), style),
+            Span::raw("　".repeat(7 - label(*field).chars().count())),
+            Span::raw(format!(" ◀ {} ▶", value(rules, *field, players))),
+        ]));
+    }
+
+    text.push(Line::from(""));
+    text.push(Line::from(footer));
+
+    let area = frame.area();
+    let width = 52.min(area.width);
+    let height = (text.len() as u16 + 2).min(area.height);
+    let overlay_area = Rect {
+        x: (area.width - width) / 2,
+        y: (area.height - height) / 2,
+        width,
+        height,
+    };
+    let paragraph = Paragraph::new(text)
+        .alignment(Alignment::Left)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(theme.hud.border.style())
+                .style(theme.hud.overlay.style()),
+        );
+    frame.render_widget(Clear, overlay_area);
+    frame.render_widget(paragraph, overlay_area);
+}
+
+fn label(field: RuleField) -> &'static str {
+    match field {
+        RuleField::Teams => "対戦形式",
+        RuleField::Rounds => "ラウンド数",
+        RuleField::Bombs => "初期ボム数",
+        RuleField::Fire => "初期火力",
+        RuleField::Items => "アイテム出現",
+        RuleField::SuddenDeath => "サドンデス",
+        RuleField::SuddenDeathAfter => "サドンデス開始",
+        RuleField::Stage => "ステージ",
+    }
+}
+
+fn value(rules: &BattleRules, field: RuleField, players: usize) -> String {
+    match field {
+        RuleField::Teams if rules.teams => format!("チーム戦 ({}人)", players),
+        RuleField::Teams => "個人戦".to_string(),
+        RuleField::Rounds => format!("{}本勝負 ({}本先取)", rules.best_of, rules.best_of / 2 + 1),
+        RuleField::Bombs => rules.start_bombs.to_string(),
+        RuleField::Fire => rules.start_fire.to_string(),
+        RuleField::Items => format!("{}%", rules.item_chance),
+        RuleField::SuddenDeath => if rules.sudden_death { "あり" } else { "なし" }.to_string(),
+        RuleField::SuddenDeathAfter => format!("{}秒後", rules.sudden_death_after),
+        RuleField::Stage => battle_stages()
+            .get(rules.stage)
+            .map(|stage| stage.name.clone())
+            .unwrap_or_default(),
+    }
+}
@@ -0,0 +1,130 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Turns the bytes a remote terminal sends into key events. Over SSH there
+/// is no local terminal for crossterm to read, so the common xterm escape
+/// sequences are decoded here instead.
+pub fn parse_keys(bytes: &[u8]) -> Vec<KeyEvent> {
+    let text = String::from_utf8_lossy(bytes);
+    let mut chars = text.chars().peekable();
+    let mut keys = Vec::new();
+
+    while let Some(c) = chars.next() {
+        let key = match c {
+            '\x1b' => match chars.peek() {
+                Some('[') => {
+                    chars.next();
+                    let mut params = String::new();
+                    while let Some(&p) = chars.peek()
+                        && ('0'..='?').contains(&p)
+                    {
+                        params.push(p);
+                        chars.next();
+                    }
+                    match chars.next() {
+                        Some(last) => csi_key(&params, last),
+                        None => None,
+                    }
+                }
+                Some('O') => {
+                    chars.next();
+                    chars.next().and_then(|last| csi_key("", last))
+                }
+                _ => Some(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)),
+            },
+            '\r' | '\n' => Some(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)),
+            '\t' => Some(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE)),
+            '\x7f' | '\x08' => Some(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE)),
+            '\x01'..='\x1a' => {
+                let letter = (b'a' + c as u8 - 1) as char;
+                Some(KeyEvent::new(KeyCode::Char(letter), KeyModifiers::CONTROL))
+            }
+            c if !c.is_control() => Some(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)),
+            _ => None,
+        };
+        keys.extend(key);
+    }
+
+    keys
+}
+
+/// Decodes the final byte and parameters of a `CSI` or `SS3` sequence.
+fn csi_key(params: &str, last: char) -> Option<KeyEvent> {
+    let code = match (last, params) {
+        ('A', _) => KeyCode::Up,
+        ('B', _) => KeyCode::Down,
+        ('C', _) => KeyCode::Right,
+        ('D', _) => KeyCode::Left,
+        ('H', _) => KeyCode::Home,
+        ('F', _) => KeyCode::End,
+        ('Z', _) => return Some(KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT)),
+        ('P', _) => KeyCode::F(1),
+        ('Q', _) => KeyCode::F(2),
+        ('R', _) => KeyCode::F(3),
+        ('S', _) => KeyCode::F(4),
+        ('~', "1" | "7") => KeyCode::Home,
+        ('~', "2") => KeyCode::Insert,
+        ('~', "3") => KeyCode::Delete,
+        ('~', "4" | "8") => KeyCode::End,
+        ('~', "5") => KeyCode::PageUp,
+        ('~', "6") => KeyCode::PageDown,
+        ('~', "11") => KeyCode::F(1),
+        ('~', "12") => KeyCode::F(2),
+        ('~', "13") => KeyCode::F(3),
+        ('~', "14") => KeyCode::F(4),
+        ('~', "15") => KeyCode::F(5),
+        ('~', "17") => KeyCode::F(6),
+        ('~', "18") => KeyCode::F(7),
+        ('~', "19") => KeyCode::F(8),
+        ('~', "20") => KeyCode::F(9),
+        ('~', "21") => KeyCode::F(10),
+        ('~', "23") => KeyCode::F(11),
+        ('~', "24") => KeyCode::F(12),
+        _ => return None,
+    };
+    Some(KeyEvent::new(code, KeyModifiers::NONE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(bytes: &[u8]) -> Vec<(KeyCode, KeyModifiers)> {
+        parse_keys(bytes).into_iter().map(|key| (key.code, key.modifiers)).collect()
+    }
+
+    #[test]
+    fn arrows_come_in_both_cursor_modes() {
+        let up = (KeyCode::Up, KeyModifiers::NONE);
+        assert_eq!(codes(b"\x1b[A\x1bOA"), vec![up, up]);
+    }
+
+    #[test]
+    fn control_letters_and_a_lone_escape() {
+        assert_eq!(
+            codes(b"\x03\x1b\r"),
+            vec![
+                (KeyCode::Char('c'), KeyModifiers::CONTROL),
+                (KeyCode::Esc, KeyModifiers::NONE),
+                (KeyCode::Enter, KeyModifiers::NONE),
+            ]
+        );
+    }
+
+    #[test]
+    fn tilde_sequences_and_text() {
+        assert_eq!(
+            codes("\x1b[3~\x1b[15~あ".as_bytes()),
+            vec![
+                (KeyCode::Delete, KeyModifiers::NONE),
+                (KeyCode::F(5), KeyModifiers::NONE),
+                (KeyCode::Char('あ'), KeyModifiers::NONE),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_or_cut_off_sequences_are_dropped() {
+        assert!(codes(b"\x1b[99~").is_empty());
+        assert!(codes(b"\x1b[1;").is_empty());
+    }
+}
@@ -0,0 +1,122 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use russh::keys::ssh_key::{
+    authorized_keys::AuthorizedKeys,
+    private::{Ed25519Keypair, KeypairData},
+    LineEnding,
+};
+use russh::keys::{PrivateKey, PublicKey};
+
+/// Reads the OpenSSH private key at `path`, creating an ed25519 key there
+/// on first run.
+pub fn load_host_key(path: &Path) -> io::Result<PrivateKey> {
+    if path.exists() {
+        return PrivateKey::read_openssh_file(path).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} is not an OpenSSH private key: {}", path.display(), err),
+            )
+        });
+    }
+
+    let seed: [u8; 32] = rand::random();
+    let keypair = KeypairData::Ed25519(Ed25519Keypair::from_seed(&seed));
+    let key = PrivateKey::new(keypair, "bmtui").map_err(io::Error::other)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    key.write_openssh_file(path, LineEnding::LF)
+        .map_err(io::Error::other)?;
+    eprintln!("[ssh] generated a new host key at {}", path.display());
+    Ok(key)
+}
+
+/// The public keys that may log in, one per line in the format of
+/// OpenSSH's `authorized_keys`. Options before a key are ignored.
+pub struct AllowList(Vec<PublicKey>);
+
+impl AllowList {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path).map_err(|err| {
+            io::Error::new(
+                err.kind(),
+                format!(
+                    "cannot read {}: {} (list the public keys of everyone who may play there)",
+                    path.display(),
+                    err
+                ),
+            )
+        })?;
+        Self::parse(&text).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), err),
+            )
+        })
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let keys = AuthorizedKeys::new(text)
+            .map(|entry| {
+                entry
+                    .map(|entry| entry.public_key().clone())
+                    .map_err(|err| format!("unreadable key: {}", err))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if keys.is_empty() {
+            return Err("no keys listed, so nobody could log in".to_string());
+        }
+        Ok(Self(keys))
+    }
+
+    pub fn allows(&self, key: &PublicKey) -> bool {
+        self.0.iter().any(|allowed| allowed.key_data() == key.key_data())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(seed: u8) -> PrivateKey {
+        let keypair = KeypairData::Ed25519(Ed25519Keypair::from_seed(&[seed; 32]));
+        PrivateKey::new(keypair, "").unwrap()
+    }
+
+    fn line(key: &PrivateKey, comment: &str) -> String {
+        let public = key.public_key().to_openssh().unwrap();
+        format!("{} {}", public, comment).trim_end().to_string()
+    }
+
+    #[test]
+    fn only_listed_keys_are_allowed() {
+        let text = format!("# players\n\n{}\n", line(&key(1), "alice@laptop"));
+        let list = AllowList::parse(&text).unwrap();
+        assert!(list.allows(key(1).public_key()));
+        assert!(!list.allows(key(2).public_key()));
+    }
+
+    #[test]
+    fn options_before_a_key_are_skipped() {
+        let text = format!("no-pty,from=\"10.0.0.1\" {}\n", line(&key(3), "bob@desk"));
+        assert!(AllowList::parse(&text).unwrap().allows(key(3).public_key()));
+    }
+
+    #[test]
+    fn an_empty_list_is_refused() {
+        assert!(AllowList::parse("").is_err());
+        assert!(AllowList::parse("# nobody yet\n").is_err());
+    }
+
+    #[test]
+    fn a_broken_line_is_refused() {
+        let text = format!("{}\nssh-ed25519 not-base64\n", line(&key(1), ""));
+        assert!(AllowList::parse(&text).is_err());
+    }
+}
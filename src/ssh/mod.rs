@@ -0,0 +1,417 @@
+mod input;
+mod keys;
+mod session;
+
+use std::io;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use russh::keys::{HashAlg, PrivateKey, PublicKey};
+use russh::server::{Auth, ChannelOpenHandle, Handler, Msg, Session};
+use russh::{Channel, ChannelId, ChannelOpenFailure, Disconnect, MethodKind, MethodSet};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot;
+use tokio::time::{sleep, timeout_at, Instant};
+
+use crate::app::App;
+use crate::config::config_dir;
+use crate::game::mode::GameSetup;
+use crate::net::{client::NetClient, server::ServerConfig, ACCEPT_BACKOFF};
+use keys::AllowList;
+
+pub const DEFAULT_SSH_PORT: u16 = 2222;
+/// How long a connection may take from the first byte to a successful
+/// login before it is dropped.
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub struct ServeConfig {
+    pub addr: String,
+    pub host_key: PathBuf,
+    pub authorized_keys: PathBuf,
+    /// Shared LAN lobby that every session joins; without it each session
+    /// plays the story stages on its own.
+    pub lobby: Option<ServerConfig>,
+}
+
+/// Serves the game over SSH: every connection gets its own `App`, drawn
+/// with ratatui into the SSH channel. Only keys listed in the authorized
+/// keys file may log in; the login name becomes the player name.
+pub async fn run(config: ServeConfig) -> io::Result<()> {
+    let host_key = keys::load_host_key(&config.host_key)?;
+    let allowed = AllowList::load(&config.authorized_keys)?;
+    let listener = TcpListener::bind(&config.addr).await?;
+    eprintln!("[ssh] listening on {}", config.addr);
+    eprintln!("[ssh] host key {}", host_key.fingerprint(HashAlg::Sha256));
+    eprintln!(
+        "[ssh] {} keys may log in (from {})",
+        allowed.len(),
+        config.authorized_keys.display()
+    );
+
+    let lobby_addr = match config.lobby {
+        Some(server) => {
+            let port = server.addr.rsplit(':').next().unwrap_or_default().to_string();
+            eprintln!("[ssh] sessions join the lobby on {}", server.addr);
+            tokio::spawn(async move {
+                if let Err(err) = crate::net::server::run(server).await {
+                    eprintln!("[ssh] lobby server failed: {}", err);
+                }
+            });
+            Some(format!("127.0.0.1:{}", port))
+        }
+        None => None,
+    };
+
+    let server = Arc::new(Server {
+        config: Arc::new(ssh_config(host_key)),
+        allowed,
+        lobby_addr,
+        login_timeout: LOGIN_TIMEOUT,
+    });
+    accept(listener, server).await
+}
+
+/// What every connection shares.
+struct Server {
+    config: Arc<russh::server::Config>,
+    allowed: AllowList,
+    lobby_addr: Option<String>,
+    login_timeout: Duration,
+}
+
+fn ssh_config(host_key: PrivateKey) -> russh::server::Config {
+    russh::server::Config {
+        methods: MethodSet::from(&[MethodKind::PublicKey][..]),
+        keys: vec![host_key],
+        auth_rejection_time: Duration::from_secs(1),
+        auth_rejection_time_initial: Some(Duration::ZERO),
+        max_auth_attempts: 6,
+        // Sessions keep their own idle clock, which only keystrokes reset.
+        inactivity_timeout: None,
+        keepalive_interval: Some(Duration::from_secs(30)),
+        nodelay: true,
+        ..Default::default()
+    }
+}
+
+async fn accept(listener: TcpListener, server: Arc<Server>) -> io::Result<()> {
+    loop {
+        // A failed accept (EMFILE, ECONNABORTED) loses that one client, not
+        // the server.
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(err) => {
+                eprintln!("[ssh] accept failed: {}", err);
+                sleep(ACCEPT_BACKOFF).await;
+                continue;
+            }
+        };
+        let server = server.clone();
+        tokio::spawn(async move {
+            if let Err(err) = serve(stream, peer, server).await {
+                eprintln!("[ssh] {}: {}", peer, err);
+            }
+        });
+    }
+}
+
+async fn serve(stream: TcpStream, peer: SocketAddr, server: Arc<Server>) -> io::Result<()> {
+    let deadline = Instant::now() + server.login_timeout;
+    let (logged_in_tx, logged_in) = oneshot::channel();
+    let guest = Guest {
+        server: server.clone(),
+        peer,
+        user: None,
+        logged_in: Some(logged_in_tx),
+        has_session: false,
+    };
+    let handshake = russh::server::run_stream(server.config.clone(), stream, guest);
+    let mut session = timeout_at(deadline, handshake)
+        .await
+        .map_err(|_| login_timed_out())?
+        .map_err(io::Error::other)?;
+
+    tokio::select! {
+        result = &mut session => return result.map_err(io::Error::other),
+        login = timeout_at(deadline, logged_in) => {
+            if !matches!(login, Ok(Ok(()))) {
+                let _ = session
+                    .handle()
+                    .disconnect(Disconnect::ByApplication, "login timed out".into(), String::new())
+                    .await;
+                let _ = session.await;
+                return Err(login_timed_out());
+            }
+        }
+    }
+    session.await.map_err(io::Error::other)
+}
+
+fn login_timed_out() -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, "did not log in in time")
+}
+
+/// One connection, from key exchange until it closes.
+struct Guest {
+    server: Arc<Server>,
+    peer: SocketAddr,
+    user: Option<String>,
+    logged_in: Option<oneshot::Sender<()>>,
+    has_session: bool,
+}
+
+impl Guest {
+    fn check(&self, key: &PublicKey) -> Auth {
+        if self.server.allowed.allows(key) {
+            Auth::Accept
+        } else {
+            Auth::reject()
+        }
+    }
+}
+
+impl Handler for Guest {
+    type Error = russh::Error;
+
+    async fn auth_publickey_offered(
+        &mut self,
+        _user: &str,
+        key: &PublicKey,
+    ) -> Result<Auth, Self::Error> {
+        Ok(self.check(key))
+    }
+
+    async fn auth_publickey(&mut self, user: &str, key: &PublicKey) -> Result<Auth, Self::Error> {
+        let auth = self.check(key);
+        if matches!(auth, Auth::Accept) {
+            self.user = Some(user.to_string());
+        }
+        Ok(auth)
+    }
+
+    async fn auth_succeeded(&mut self, _session: &mut Session) -> Result<(), Self::Error> {
+        if let Some(logged_in) = self.logged_in.take() {
+            let _ = logged_in.send(());
+        }
+        let user = self.user.as_deref().unwrap_or_default();
+        eprintln!("[ssh] {} connected from {}", user, self.peer);
+        Ok(())
+    }
+
+    async fn channel_open_session(
+        &mut self,
+        channel: Channel<Msg>,
+        reply: ChannelOpenHandle,
+        _session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        if self.has_session {
+            reply
+                .reject(ChannelOpenFailure::AdministrativelyProhibited)
+                .await;
+            return Ok(());
+        }
+        self.has_session = true;
+        reply.accept().await;
+
+        let user = self.user.clone().unwrap_or_default();
+        let lobby_addr = self.server.lobby_addr.clone();
+        let app = async move {
+            match lobby_addr {
+                Some(addr) => Ok(App::networked(NetClient::connect_with_retry(&addr, &user).await?)),
+                None => Ok(App::new(GameSetup::Story)),
+            }
+        };
+        let user = self.user.clone().unwrap_or_default();
+        let peer = self.peer;
+        tokio::spawn(async move {
+            if let Err(err) = session::run(channel, app).await {
+                eprintln!("[ssh] {}: {}", peer, err);
+            }
+            eprintln!("[ssh] {} disconnected", user);
+        });
+        Ok(())
+    }
+
+    async fn pty_request(
+        &mut self,
+        channel: ChannelId,
+        _term: &str,
+        _cols: u32,
+        _rows: u32,
+        _pix_width: u32,
+        _pix_height: u32,
+        _modes: &[(russh::Pty, u32)],
+        session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        session.channel_success(channel)
+    }
+
+    async fn env_request(
+        &mut self,
+        channel: ChannelId,
+        _name: &str,
+        _value: &str,
+        session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        session.channel_success(channel)
+    }
+
+    async fn shell_request(
+        &mut self,
+        channel: ChannelId,
+        session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        session.channel_success(channel)
+    }
+
+    async fn window_change_request(
+        &mut self,
+        channel: ChannelId,
+        _cols: u32,
+        _rows: u32,
+        _pix_width: u32,
+        _pix_height: u32,
+        session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        session.channel_success(channel)
+    }
+
+    async fn exec_request(
+        &mut self,
+        channel: ChannelId,
+        _command: &[u8],
+        session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        session.channel_failure(channel)
+    }
+
+    async fn subsystem_request(
+        &mut self,
+        channel: ChannelId,
+        _name: &str,
+        session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        session.channel_failure(channel)
+    }
+}
+
+/// Where the server keeps its host key unless told otherwise, so that
+/// clients see the same key every time.
+pub fn default_host_key_path() -> PathBuf {
+    config_dir().join("ssh_host_ed25519_key")
+}
+
+/// Where the server looks for the public keys allowed to log in.
+pub fn default_authorized_keys_path() -> PathBuf {
+    config_dir().join("authorized_keys")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use russh::keys::PrivateKeyWithHashAlg;
+    use russh::ChannelMsg;
+    use russh::keys::ssh_key::private::{Ed25519Keypair, KeypairData};
+    use russh::{client, keys::PublicKeyOrCertificate};
+    use tokio::io::AsyncReadExt;
+
+    struct Player;
+
+    impl client::Handler for Player {
+        type Error = russh::Error;
+
+        async fn check_server_key(&mut self, _key: &PublicKeyOrCertificate) -> Result<bool, Self::Error> {
+            Ok(true)
+        }
+    }
+
+    fn key(seed: u8) -> PrivateKey {
+        let keypair = KeypairData::Ed25519(Ed25519Keypair::from_seed(&[seed; 32]));
+        PrivateKey::new(keypair, "").unwrap()
+    }
+
+    /// Starts a server on a free port that lets in only `key(1)`.
+    async fn start(login_timeout: Duration) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let allowed = format!("{}\n", key(1).public_key().to_openssh().unwrap());
+        let server = Arc::new(Server {
+            config: Arc::new(ssh_config(key(100))),
+            allowed: AllowList::parse(&allowed).unwrap(),
+            lobby_addr: None,
+            login_timeout,
+        });
+        tokio::spawn(accept(listener, server));
+        addr
+    }
+
+    async fn log_in(addr: SocketAddr, key: PrivateKey) -> (client::Handle<Player>, bool) {
+        let config = Arc::new(client::Config::default());
+        let mut handle = client::connect(config, addr, Player).await.unwrap();
+        let auth = handle
+            .authenticate_publickey("alice", PrivateKeyWithHashAlg::new(Arc::new(key), None))
+            .await
+            .unwrap();
+        (handle, auth.success())
+    }
+
+    #[tokio::test]
+    async fn only_listed_keys_log_in() {
+        let addr = start(LOGIN_TIMEOUT).await;
+        assert!(!log_in(addr, key(2)).await.1);
+        assert!(log_in(addr, key(1)).await.1);
+    }
+
+    #[tokio::test]
+    async fn passwords_are_not_accepted() {
+        let addr = start(LOGIN_TIMEOUT).await;
+        let config = Arc::new(client::Config::default());
+        let mut handle = client::connect(config, addr, Player).await.unwrap();
+        let auth = handle.authenticate_password("alice", "hunter2").await.unwrap();
+        assert!(!auth.success());
+    }
+
+    #[tokio::test]
+    async fn a_silent_connection_is_dropped() {
+        let addr = start(Duration::from_millis(200)).await;
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let mut received = Vec::new();
+        let closed = tokio::time::timeout(Duration::from_secs(5), stream.read_to_end(&mut received));
+        assert!(closed.await.is_ok(), "the server kept a silent connection open");
+        assert!(received.starts_with(b"SSH-2.0-"));
+    }
+
+    #[tokio::test]
+    async fn a_shell_gets_the_game() {
+        let addr = start(LOGIN_TIMEOUT).await;
+        let (handle, _) = log_in(addr, key(1)).await;
+        let mut channel = handle.channel_open_session().await.unwrap();
+        channel.request_pty(true, "xterm", 80, 24, 0, 0, &[]).await.unwrap();
+        channel.request_shell(true).await.unwrap();
+
+        let mut screen = Vec::new();
+        while !screen.windows(8).any(|w| w == b"\x1b[?1049h") {
+            let message = tokio::time::timeout(Duration::from_secs(5), channel.wait()).await;
+            match message.expect("no output from the game") {
+                Some(ChannelMsg::Data { data }) => screen.extend_from_slice(&data),
+                Some(_) => {}
+                None => panic!("the session closed"),
+            }
+        }
+
+        channel.data_bytes(&b"\x03"[..]).await.unwrap();
+        loop {
+            let message = tokio::time::timeout(Duration::from_secs(5), channel.wait()).await;
+            match message.expect("Ctrl+C did not end the session") {
+                Some(ChannelMsg::ExitStatus { exit_status }) => {
+                    assert_eq!(exit_status, 0);
+                    break;
+                }
+                Some(_) => {}
+                None => panic!("closed without an exit status"),
+            }
+        }
+    }
+}
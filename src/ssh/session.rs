@@ -0,0 +1,200 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crossterm::{
+    cursor::{Hide, Show},
+    event::{Event, KeyCode, KeyModifiers},
+    execute,
+    terminal::{Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{backend::CrosstermBackend, layout::Rect, Terminal, TerminalOptions, Viewport};
+use russh::server::Msg;
+use russh::{Channel, ChannelMsg};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tokio::time::{interval, sleep, timeout, Instant};
+
+use super::input::parse_keys;
+use crate::app::App;
+use crate::render::glyphs::GlyphSet;
+
+/// A session with no keystrokes for this long is closed, and so is one
+/// that never asks for a shell.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+/// How long the goodbye (leaving the alternate screen) may take to reach a
+/// client before the channel is closed anyway.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Larger terminals are drawn at this size, so that a client cannot make
+/// us allocate an arbitrarily large screen buffer.
+const MAX_COLS: u16 = 500;
+const MAX_ROWS: u16 = 200;
+/// The only environment variables we keep; the rest are acknowledged and
+/// dropped.
+const LOCALE_VARS: [&str; 3] = ["LC_ALL", "LC_CTYPE", "LANG"];
+
+/// Runs one interactive session on `channel`: waits for a pty and a shell,
+/// then runs `App` with its output drawn into the channel and its input
+/// decoded from it. The SSH layer has already acknowledged each request.
+pub async fn run(
+    mut channel: Channel<Msg>,
+    app: impl Future<Output = io::Result<App>>,
+) -> io::Result<()> {
+    let mut size = (80, 24);
+    let mut term = String::new();
+    let mut env = BTreeMap::new();
+
+    loop {
+        let Ok(message) = timeout(IDLE_TIMEOUT, channel.wait()).await else {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "never asked for a shell"));
+        };
+        match message {
+            Some(ChannelMsg::RequestPty {
+                term: name,
+                col_width,
+                row_height,
+                ..
+            }) => {
+                term = name;
+                size = screen_size(col_width, row_height);
+            }
+            Some(ChannelMsg::WindowChange {
+                col_width,
+                row_height,
+                ..
+            }) => size = screen_size(col_width, row_height),
+            Some(ChannelMsg::SetEnv {
+                variable_name,
+                variable_value,
+                ..
+            }) if LOCALE_VARS.contains(&variable_name.as_str()) => {
+                env.insert(variable_name, variable_value);
+            }
+            Some(ChannelMsg::RequestShell { .. }) => break,
+            Some(ChannelMsg::Eof | ChannelMsg::Close) | None => return Ok(()),
+            Some(_) => {}
+        }
+    }
+
+    let mut app = app.await?;
+    let locale = LOCALE_VARS
+        .into_iter()
+        .filter_map(|name| env.get(name))
+        .find(|value: &&String| !value.is_empty())
+        .map(String::as_str)
+        .unwrap_or_default();
+    app.set_glyphs(GlyphSet::detect(&term, locale));
+    let output = Output::default();
+    let mut terminal = Terminal::with_options(
+        CrosstermBackend::new(output.writer()),
+        TerminalOptions {
+            viewport: Viewport::Fixed(Rect::new(0, 0, size.0, size.1)),
+        },
+    )?;
+    execute!(output.writer(), EnterAlternateScreen, Hide, Clear(ClearType::All))?;
+
+    // Frames are sent on their own task, which waits for the client's
+    // window. Only one frame is ever queued: while the client lags behind,
+    // frames are skipped rather than buffered, and since ratatui diffs
+    // against the last frame it drew, the next one catches the client up.
+    let (frames, mut outgoing) = mpsc::channel::<Vec<u8>>(1);
+    let mut writer = channel.make_writer();
+    let sender = tokio::spawn(async move {
+        while let Some(frame) = outgoing.recv().await {
+            if writer.write_all(&frame).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut ticker = interval(app.tick_period());
+    let idle = sleep(IDLE_TIMEOUT);
+    tokio::pin!(idle);
+    let mut running = true;
+
+    while running {
+        tokio::select! {
+            message = channel.wait() => match message {
+                Some(ChannelMsg::Data { data }) => {
+                    idle.as_mut().reset(Instant::now() + IDLE_TIMEOUT);
+                    for key in parse_keys(&data) {
+                        let interrupt = key.code == KeyCode::Char('c')
+                            && key.modifiers.contains(KeyModifiers::CONTROL);
+                        if interrupt || !app.handle_event(Event::Key(key)) {
+                            running = false;
+                            break;
+                        }
+                    }
+                }
+                Some(ChannelMsg::WindowChange { col_width, row_height, .. }) => {
+                    let (cols, rows) = screen_size(col_width, row_height);
+                    terminal.resize(Rect::new(0, 0, cols, rows))?;
+                    app.handle_event(Event::Resize(cols, rows));
+                }
+                Some(ChannelMsg::Eof | ChannelMsg::Close) | None => running = false,
+                Some(_) => {}
+            },
+            _ = ticker.tick() => app.tick(),
+            _ = &mut idle => running = false,
+        }
+
+        if ticker.period() != app.tick_period() {
+            ticker = interval(app.tick_period());
+        }
+        if running && let Ok(permit) = frames.try_reserve() {
+            terminal.draw(|frame| app.render(frame))?;
+            permit.send(output.take());
+        }
+    }
+
+    execute!(output.writer(), LeaveAlternateScreen, Show)?;
+    let goodbye = async {
+        if frames.send(output.take()).await.is_ok() {
+            drop(frames);
+            let _ = sender.await;
+        }
+    };
+    let _ = timeout(CLOSE_TIMEOUT, goodbye).await;
+    let _ = channel.exit_status(0).await;
+    let _ = channel.eof().await;
+    let _ = channel.close().await;
+    Ok(())
+}
+
+fn screen_size(cols: u32, rows: u32) -> (u16, u16) {
+    (
+        cols.min(MAX_COLS as u32) as u16,
+        rows.min(MAX_ROWS as u32) as u16,
+    )
+}
+
+/// Terminal output for the session. Ratatui writes into it through
+/// `OutputWriter`; each frame is taken out whole and handed to the sender.
+#[derive(Default)]
+struct Output {
+    pending: Arc<Mutex<Vec<u8>>>,
+}
+
+impl Output {
+    fn writer(&self) -> OutputWriter {
+        OutputWriter(self.pending.clone())
+    }
+
+    fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.pending.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+struct OutputWriter(Arc<Mutex<Vec<u8>>>);
+
+impl Write for OutputWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}